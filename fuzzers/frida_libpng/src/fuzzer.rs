@@ -16,8 +16,8 @@ use libafl::{
         tuples::{tuple_list, Merge},
     },
     corpus::{
-        ondisk::OnDiskMetadataFormat, Corpus, IndexesLenTimeMinimizerCorpusScheduler, OnDiskCorpus,
-        QueueCorpusScheduler,
+        ondisk::OnDiskMetadataFormat, Corpus, IndexesLenTimeMinimizerCorpusScheduler,
+        ObjectiveCorpus, OnDiskCorpus, QueueCorpusScheduler,
     },
     executors::{
         inprocess::InProcessExecutor, timeout::TimeoutExecutor, Executor, ExitKind,
@@ -353,8 +353,9 @@ unsafe fn fuzz(
                 // Corpus that will be evolved, we keep it in memory for performance
                 OnDiskCorpus::new(PathBuf::from("./corpus_discovered")).unwrap(),
                 // Corpus in which we store solutions (crashes in this example),
-                // on disk so the user can get them after stopping the fuzzer
-                OnDiskCorpus::new_save_meta(
+                // on disk so the user can get them after stopping the fuzzer,
+                // one subdirectory per kind of objective, and one testcase per distinct ASan error
+                ObjectiveCorpus::new_save_meta(
                     objective_dir.to_path_buf(),
                     Some(OnDiskMetadataFormat::JsonPretty),
                 )
@@ -1,4 +1,4 @@
-//! Compression of events passed between a broker and clients.
+//! Compression of events passed between a broker and clients, and of inputs stored on disk.
 //! Currently we use the gzip compression algorithm for its fast decompression performance.
 
 #[cfg(feature = "llmp_compression")]
@@ -52,6 +52,106 @@ impl GzipCompressor {
     }
 }
 
+/// The magic bytes at the start of gzip files
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// The only compression method defined for gzip, deflate
+const GZIP_METHOD_DEFLATE: u8 = 8;
+/// Gzip header flags
+const GZIP_FHCRC: u8 = 0x02;
+const GZIP_FEXTRA: u8 = 0x04;
+const GZIP_FNAME: u8 = 0x08;
+const GZIP_FCOMMENT: u8 = 0x10;
+
+/// The CRC-32 (IEEE) of the given buffer, as used in gzip trailers
+fn crc32(buf: &[u8]) -> u32 {
+    let mut crc = !0_u32;
+    for b in buf {
+        crc ^= u32::from(*b);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+impl GzipCompressor {
+    /// Returns `true` if the buffer starts like a gzip file
+    #[must_use]
+    pub fn is_gzip(buf: &[u8]) -> bool {
+        buf.len() >= 18 && buf[..2] == GZIP_MAGIC && buf[2] == GZIP_METHOD_DEFLATE
+    }
+
+    /// Compression to the gzip file format, readable by the `gzip` tool.
+    /// If the buffer is smaller than the threshold of this compressor, `None` will be returned.
+    pub fn compress_gzip(&self, buf: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        match self.compress(buf)? {
+            None => Ok(None),
+            Some(deflated) => {
+                let mut gzip = Vec::with_capacity(deflated.len() + 18);
+                // magic, method, flags, mtime, extra flags, OS (unknown)
+                gzip.extend_from_slice(&GZIP_MAGIC);
+                gzip.extend_from_slice(&[GZIP_METHOD_DEFLATE, 0, 0, 0, 0, 0, 0, 0xff]);
+                gzip.extend_from_slice(&deflated);
+                gzip.extend_from_slice(&crc32(buf).to_le_bytes());
+                gzip.extend_from_slice(&(buf.len() as u32).to_le_bytes());
+                Ok(Some(gzip))
+            }
+        }
+    }
+
+    /// Decompression of a gzip file, checking its integrity
+    pub fn decompress_gzip(&self, buf: &[u8]) -> Result<Vec<u8>, Error> {
+        if !Self::is_gzip(buf) {
+            return Err(Error::Compression);
+        }
+        let flags = buf[3];
+        let trailer = buf.len() - 8;
+        let mut off = 10;
+        if flags & GZIP_FEXTRA != 0 {
+            let xlen = u16::from_le_bytes([buf[off], buf[off + 1]]) as usize;
+            off += 2 + xlen;
+        }
+        for flag in &[GZIP_FNAME, GZIP_FCOMMENT] {
+            if flags & flag != 0 && off < trailer {
+                // Zero-terminated strings
+                off += buf[off..trailer]
+                    .iter()
+                    .position(|b| *b == 0)
+                    .ok_or(Error::Compression)?
+                    + 1;
+            }
+        }
+        if flags & GZIP_FHCRC != 0 {
+            off += 2;
+        }
+        if off > trailer {
+            return Err(Error::Compression);
+        }
+
+        let decompressed = self.decompress(&buf[off..trailer])?;
+        let crc = u32::from_le_bytes([
+            buf[trailer],
+            buf[trailer + 1],
+            buf[trailer + 2],
+            buf[trailer + 3],
+        ]);
+        let size = u32::from_le_bytes([
+            buf[trailer + 4],
+            buf[trailer + 5],
+            buf[trailer + 6],
+            buf[trailer + 7],
+        ]);
+        if crc32(&decompressed) != crc || decompressed.len() as u32 != size {
+            return Err(Error::Compression);
+        }
+        Ok(decompressed)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::bolts::compress::GzipCompressor;
@@ -73,4 +173,20 @@ mod tests {
         assert!(compressor.compress(&[1u8; 1023]).unwrap().is_none());
         assert!(compressor.compress(&[1u8; 1024]).unwrap().is_some());
     }
+
+    #[test]
+    fn test_gzip() {
+        let compressor = GzipCompressor::new(0);
+        let data = b"hello hello hello hello gzip".to_vec();
+        let gzip = compressor.compress_gzip(&data).unwrap().unwrap();
+        assert!(GzipCompressor::is_gzip(&gzip));
+        assert_eq!(compressor.decompress_gzip(&gzip).unwrap(), data);
+
+        // Corrupted files are detected
+        let mut corrupted = gzip;
+        let last = corrupted.len() - 5;
+        corrupted[last] ^= 0xff;
+        assert!(compressor.decompress_gzip(&corrupted).is_err());
+        assert!(!GzipCompressor::is_gzip(&data));
+    }
 }
@@ -14,6 +14,9 @@ pub mod tuples;
 #[cfg(feature = "llmp_compression")]
 pub mod compress;
 
+#[cfg(feature = "python")]
+pub mod python;
+
 use core::time;
 #[cfg(feature = "std")]
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -18,7 +18,9 @@ pub mod unix_signals;
 pub mod pipes;
 
 #[cfg(all(unix, feature = "std"))]
-use std::ffi::CString;
+use core::{hash::Hasher, mem::MaybeUninit};
+#[cfg(all(unix, feature = "std"))]
+use std::ffi::{CStr, CString};
 
 #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
 use std::fs::File;
@@ -99,6 +101,41 @@ pub fn dup2(fd: i32, device: i32) -> Result<(), Error> {
     }
 }
 
+/// Hashes the module-relative offset of `addr`, together with the path of the module mapping it,
+/// so that the hash doesn't depend on the address the module was loaded at (ASLR).
+/// Addresses outside of any module, e.g. in JIT code, are hashed as they are.
+#[cfg(all(unix, feature = "std"))]
+pub fn hash_module_offset<H: Hasher>(hasher: &mut H, addr: usize) {
+    hash_module_offset_impl(hasher, addr, false);
+}
+
+/// Hashes the module-relative offset of the function containing `addr`, together with the path of the module,
+/// so that all the addresses of a function get the same hash, e.g. for the pc a hang is interrupted at.
+/// Falls back to [`hash_module_offset`] if the function has no symbol.
+#[cfg(all(unix, feature = "std"))]
+pub fn hash_function_offset<H: Hasher>(hasher: &mut H, addr: usize) {
+    hash_module_offset_impl(hasher, addr, true);
+}
+
+#[cfg(all(unix, feature = "std"))]
+fn hash_module_offset_impl<H: Hasher>(hasher: &mut H, addr: usize, function: bool) {
+    let mut info = MaybeUninit::<libc::Dl_info>::zeroed();
+    if unsafe { libc::dladdr(addr as *const libc::c_void, info.as_mut_ptr()) } == 0 {
+        hasher.write_usize(addr);
+        return;
+    }
+    let info = unsafe { info.assume_init() };
+    if !info.dli_fname.is_null() {
+        hasher.write(unsafe { CStr::from_ptr(info.dli_fname) }.to_bytes());
+    }
+    let addr = if function && !info.dli_saddr.is_null() {
+        info.dli_saddr as usize
+    } else {
+        addr
+    };
+    hasher.write_usize(addr.wrapping_sub(info.dli_fbase as usize));
+}
+
 /// Parses core binding args from user input
 /// Returns a Vec of CPU IDs.
 /// `./fuzzer --cores 1,2-4,6` -> clients run in cores 1,2,3,4,6
@@ -132,3 +169,35 @@ pub fn parse_core_bind_arg(args: &str) -> Option<Vec<usize>> {
 
     Some(cores)
 }
+
+#[cfg(all(test, unix, feature = "std"))]
+mod tests {
+    use ahash::AHasher;
+    use core::hash::Hasher;
+
+    use crate::bolts::os::{hash_function_offset, hash_module_offset};
+
+    fn hash(hash_fn: fn(&mut AHasher, usize), addr: usize) -> u64 {
+        let mut hasher = AHasher::new_with_keys(0, 0);
+        hash_fn(&mut hasher, addr);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_hash_module_offset() {
+        let addr = libc::getpid as *const () as usize;
+        assert_eq!(
+            hash(hash_module_offset, addr),
+            hash(hash_module_offset, addr)
+        );
+        assert_ne!(
+            hash(hash_module_offset, addr),
+            hash(hash_module_offset, addr + 1)
+        );
+        // Both addresses are in the same function
+        assert_eq!(
+            hash(hash_function_offset, addr),
+            hash(hash_function_offset, addr + 1)
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! Helpers for the components implemented by Python scripts, such as [`crate::mutators::PythonMutator`]
+//! and [`crate::feedbacks::PythonFeedback`], running in an embedded interpreter.
+
+use alloc::{string::String, vec::Vec};
+use hashbrown::HashMap;
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyBytes, PyDict},
+};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::{
+    bolts::rands::{Rand, StdRand},
+    Error,
+};
+
+/// A state metadata the Python scripts can read and write, mapping names to bytes.
+/// The scripts get it as a `dict`, and the changes they make are stored back in the state.
+/// It is the only metadata of the state the scripts can access, shared by all of them.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PythonMetadata {
+    /// The values set by the scripts
+    pub map: HashMap<String, Vec<u8>>,
+}
+
+crate::impl_serdeany!(PythonMetadata);
+
+impl PythonMetadata {
+    /// Creates a new, empty, [`PythonMetadata`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Converts the metadata to a Python `dict` of `bytes`
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> Result<&'py PyDict, Error> {
+        let dict = PyDict::new(py);
+        for (key, value) in &self.map {
+            dict.set_item(key, PyBytes::new(py, value))?;
+        }
+        Ok(dict)
+    }
+
+    /// Replaces the metadata with the content of a Python `dict`, whose keys must be `str` and values `bytes`
+    pub fn update_from_dict(&mut self, dict: &PyDict) -> Result<(), Error> {
+        self.map.clear();
+        for (key, value) in dict.iter() {
+            self.map
+                .insert(key.extract::<String>()?, value.extract::<&[u8]>()?.to_vec());
+        }
+        Ok(())
+    }
+}
+
+/// The rand given to the Python scripts, as an object with the `next()`, `below(n)` and `between(a, b)` methods.
+/// It is seeded from the rand of the state at every call, so that the runs stay reproducible.
+#[pyclass]
+#[derive(Debug)]
+pub struct PythonRand {
+    rand: StdRand,
+}
+
+impl PythonRand {
+    /// Creates a new [`PythonRand`], seeded with `seed`
+    #[must_use]
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            rand: StdRand::with_seed(seed),
+        }
+    }
+}
+
+#[pymethods]
+impl PythonRand {
+    /// A random `int` of 64 bits
+    fn next(&mut self) -> u64 {
+        self.rand.next()
+    }
+
+    /// A random `int` below `upper_bound_excl`, or `0` if it is `0`
+    fn below(&mut self, upper_bound_excl: u64) -> u64 {
+        self.rand.below(upper_bound_excl)
+    }
+
+    /// A random `int` from `lower_bound_incl` to `upper_bound_incl`
+    fn between(&mut self, lower_bound_incl: u64, upper_bound_incl: u64) -> PyResult<u64> {
+        if lower_bound_incl > upper_bound_incl {
+            return Err(PyValueError::new_err(
+                "The lower bound is above the upper bound",
+            ));
+        }
+        Ok(self.rand.between(lower_bound_incl, upper_bound_incl))
+    }
+}
+
+/// Runs the Python script at `path`, returning a new instance of the class `class_name` it defines.
+/// The class is instantiated without arguments.
+pub fn load_python_object(path: &str, class_name: &str) -> Result<PyObject, Error> {
+    let code = fs::read_to_string(path)?;
+    Python::with_gil(|py| {
+        let module = PyModule::from_code(py, &code, path, "libafl_script")?;
+        let object = module.getattr(class_name)?.call0()?;
+        Ok(object.to_object(py))
+    })
+}
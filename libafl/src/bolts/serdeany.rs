@@ -249,6 +249,17 @@ macro_rules! create_serde_registry_for_trait {
                         .insert(unpack_type_id(TypeId::of::<T>()), Box::new(t));
                 }
 
+                /// Remove an element from the map, returning `true` if it was present.
+                #[inline]
+                pub fn remove<T>(&mut self) -> bool
+                where
+                    T: $trait_name,
+                {
+                    self.map
+                        .remove(&unpack_type_id(TypeId::of::<T>()))
+                        .is_some()
+                }
+
                 /// Returns the count of elements in this map.
                 #[must_use]
                 #[inline]
@@ -0,0 +1,228 @@
+//! The cached ondisk corpus stores testcases to disk, keeping only a few inputs in memory.
+
+use alloc::collections::vec_deque::VecDeque;
+use core::cell::RefCell;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::{
+    corpus::{
+        ondisk::{OnDiskCorpus, OnDiskMetadataFormat},
+        Corpus, CorpusId, Testcase,
+    },
+    inputs::Input,
+    Error,
+};
+
+/// A corpus storing all testcases to disk, and keeping at most `cache_max_len` inputs in memory.
+/// The least recently used inputs are evicted from memory, and loaded again from disk by [`Corpus::get`].
+/// When an input is evicted, the metadata of its testcase is written back to disk, if a metadata format is set.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(bound = "I: serde::de::DeserializeOwned")]
+pub struct CachedOnDiskCorpus<I>
+where
+    I: Input,
+{
+    inner: OnDiskCorpus<I>,
+    /// The ids of the resident inputs, from the least to the most recently used
+    cached_indexes: RefCell<VecDeque<CorpusId>>,
+    cache_max_len: usize,
+}
+
+impl<I> Corpus<I> for CachedOnDiskCorpus<I>
+where
+    I: Input,
+{
+    /// Returns the number of elements
+    #[inline]
+    fn count(&self) -> usize {
+        self.inner.count()
+    }
+
+    /// Add an entry to the corpus and return its id
+    #[inline]
+    fn add(&mut self, testcase: Testcase<I>) -> Result<CorpusId, Error> {
+        let idx = self.inner.add(testcase)?;
+        self.touch(idx)?;
+        Ok(idx)
+    }
+
+    /// Replaces the testcase with the given id
+    #[inline]
+    fn replace(&mut self, idx: CorpusId, testcase: Testcase<I>) -> Result<(), Error> {
+        self.inner.replace(idx, testcase)?;
+        self.cached_indexes.borrow_mut().retain(|i| *i != idx);
+        if self.inner.get(idx)?.borrow().input().is_some() {
+            self.touch(idx)?;
+        }
+        Ok(())
+    }
+
+    /// Removes an entry from the corpus, returning it if it was present.
+    #[inline]
+    fn remove(&mut self, idx: CorpusId) -> Result<Option<Testcase<I>>, Error> {
+        let testcase = self.inner.remove(idx)?;
+        self.cached_indexes.borrow_mut().retain(|i| *i != idx);
+        Ok(testcase)
+    }
+
+    /// The id of the `nth` entry, in insertion order
+    #[inline]
+    fn nth(&self, nth: usize) -> Option<CorpusId> {
+        self.inner.nth(nth)
+    }
+
+    /// The id of the entry following `id`, in insertion order
+    #[inline]
+    fn next(&self, id: CorpusId) -> Option<CorpusId> {
+        self.inner.next(id)
+    }
+
+    /// Get by id, loading the input from disk if it is not in memory
+    #[inline]
+    fn get(&self, idx: CorpusId) -> Result<&RefCell<Testcase<I>>, Error> {
+        let testcase = self.inner.get(idx)?;
+        // A testcase already borrowed is in use, so its input is resident
+        if let Ok(mut borrowed) = testcase.try_borrow_mut() {
+            borrowed.load_input()?;
+        }
+        self.touch(idx)?;
+        Ok(testcase)
+    }
+
+    /// Current testcase scheduled
+    #[inline]
+    fn current(&self) -> &Option<CorpusId> {
+        self.inner.current()
+    }
+
+    /// Current testcase scheduled (mut)
+    #[inline]
+    fn current_mut(&mut self) -> &mut Option<CorpusId> {
+        self.inner.current_mut()
+    }
+}
+
+impl<I> CachedOnDiskCorpus<I>
+where
+    I: Input,
+{
+    /// Marks the input with id `idx` as the most recently used, evicting the least recently used inputs if the cache is full.
+    /// Inputs currently borrowed, or never stored to disk, are not evicted.
+    fn touch(&self, idx: CorpusId) -> Result<(), Error> {
+        let mut cached_indexes = self.cached_indexes.borrow_mut();
+        if cached_indexes.back() == Some(&idx) {
+            return Ok(());
+        }
+        cached_indexes.retain(|i| *i != idx);
+
+        let mut kept = 0;
+        while cached_indexes.len() - kept >= self.cache_max_len && cached_indexes.len() > kept {
+            let evicted = cached_indexes.pop_front().unwrap();
+            match self.inner.get(evicted)?.try_borrow_mut() {
+                Ok(mut testcase) if testcase.filename().is_some() => {
+                    self.inner.save_testcase_metadata(&testcase)?;
+                    *testcase.input_mut() = None;
+                }
+                _ => {
+                    cached_indexes.push_back(evicted);
+                    kept += 1;
+                }
+            }
+        }
+        cached_indexes.push_back(idx);
+        Ok(())
+    }
+
+    /// Creates the [`CachedOnDiskCorpus`], keeping at most `cache_max_len` inputs in memory.
+    /// Will error, if [`std::fs::create_dir_all()`] failed for `dir_path`.
+    pub fn new(dir_path: PathBuf, cache_max_len: usize) -> Result<Self, Error> {
+        Self::new_save_meta(dir_path, None, cache_max_len)
+    }
+
+    /// Creates the [`CachedOnDiskCorpus`] specifying the type of `Metadata` to be saved to disk,
+    /// keeping at most `cache_max_len` inputs in memory.
+    /// Will error, if [`std::fs::create_dir_all()`] failed for `dir_path`.
+    pub fn new_save_meta(
+        dir_path: PathBuf,
+        meta_format: Option<OnDiskMetadataFormat>,
+        cache_max_len: usize,
+    ) -> Result<Self, Error> {
+        if cache_max_len == 0 {
+            return Err(Error::IllegalArgument(
+                "The max cache len of a CachedOnDiskCorpus cannot be 0".into(),
+            ));
+        }
+        Ok(Self {
+            inner: OnDiskCorpus::new_save_meta(dir_path, meta_format)?,
+            cached_indexes: RefCell::new(VecDeque::new()),
+            cache_max_len,
+        })
+    }
+
+    /// The maximum number of inputs kept in memory
+    #[must_use]
+    pub fn cache_max_len(&self) -> usize {
+        self.cache_max_len
+    }
+
+    /// The number of inputs currently in memory
+    #[must_use]
+    pub fn cached_len(&self) -> usize {
+        self.cached_indexes.borrow().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        corpus::{cached::CachedOnDiskCorpus, ondisk::OnDiskMetadataFormat, Corpus, Testcase},
+        inputs::{BytesInput, HasBytesVec},
+        mutators::LogMutationMetadata,
+        state::HasMetadata,
+    };
+
+    #[test]
+    fn test_cached_ondisk_corpus() {
+        let dir = std::env::temp_dir().join(format!(
+            "libafl_test_cached_ondisk_corpus_{}",
+            std::process::id()
+        ));
+        let mut corpus = CachedOnDiskCorpus::<BytesInput>::new_save_meta(
+            dir.clone(),
+            Some(OnDiskMetadataFormat::Json),
+            2,
+        )
+        .unwrap();
+        let ids: Vec<_> = (0..4_u8)
+            .map(|i| {
+                corpus
+                    .add(Testcase::new(BytesInput::new(vec![i; 4])))
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(corpus.cached_len(), 2);
+        assert!(corpus.get(ids[0]).unwrap().borrow().input().is_some());
+        assert_eq!(corpus.cached_len(), 2);
+
+        // Metadata added while resident is persisted on eviction
+        corpus
+            .get(ids[1])
+            .unwrap()
+            .borrow_mut()
+            .add_metadata(LogMutationMetadata::new(vec!["test".into()], None));
+        corpus.get(ids[2]).unwrap();
+        corpus.get(ids[3]).unwrap();
+        let testcase = corpus.get(ids[1]).unwrap().borrow();
+        assert_eq!(testcase.input().as_ref().unwrap().bytes(), &[1; 4]);
+        let meta = fs::read_to_string(testcase.filename().clone().unwrap() + ".metadata").unwrap();
+        assert!(meta.contains("test"));
+        drop(testcase);
+
+        // The least recently used inputs got evicted
+        assert_eq!(corpus.cached_len(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
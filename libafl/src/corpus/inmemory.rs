@@ -1,10 +1,13 @@
 //! In-memory corpus, keeps all test cases in memory at all times
 
-use alloc::vec::Vec;
 use core::cell::RefCell;
 use serde::{Deserialize, Serialize};
 
-use crate::{corpus::Corpus, corpus::Testcase, inputs::Input, Error};
+use crate::{
+    corpus::{Corpus, CorpusId, Testcase, TestcaseStorage},
+    inputs::Input,
+    Error,
+};
 
 /// A corpus handling all in memory.
 #[derive(Default, Serialize, Deserialize, Clone, Debug)]
@@ -13,8 +16,8 @@ pub struct InMemoryCorpus<I>
 where
     I: Input,
 {
-    entries: Vec<RefCell<Testcase<I>>>,
-    current: Option<usize>,
+    entries: TestcaseStorage<I>,
+    current: Option<CorpusId>,
 }
 
 impl<I> Corpus<I> for InMemoryCorpus<I>
@@ -24,51 +27,59 @@ where
     /// Returns the number of elements
     #[inline]
     fn count(&self) -> usize {
-        self.entries.len()
+        self.entries.count()
     }
 
-    /// Add an entry to the corpus and return its index
+    /// Add an entry to the corpus and return its id
     #[inline]
-    fn add(&mut self, testcase: Testcase<I>) -> Result<usize, Error> {
-        self.entries.push(RefCell::new(testcase));
-        Ok(self.entries.len() - 1)
+    fn add(&mut self, testcase: Testcase<I>) -> Result<CorpusId, Error> {
+        Ok(self.entries.insert(testcase))
     }
 
-    /// Replaces the testcase at the given idx
+    /// Replaces the testcase with the given id
     #[inline]
-    fn replace(&mut self, idx: usize, testcase: Testcase<I>) -> Result<(), Error> {
-        if idx >= self.entries.len() {
-            return Err(Error::KeyNotFound(format!("Index {} out of bounds", idx)));
-        }
-        self.entries[idx] = RefCell::new(testcase);
+    fn replace(&mut self, id: CorpusId, testcase: Testcase<I>) -> Result<(), Error> {
+        self.entries
+            .replace(id, testcase)
+            .ok_or_else(|| Error::KeyNotFound(format!("Index {} not found", id)))?;
         Ok(())
     }
 
     /// Removes an entry from the corpus, returning it if it was present.
     #[inline]
-    fn remove(&mut self, idx: usize) -> Result<Option<Testcase<I>>, Error> {
-        if idx >= self.entries.len() {
-            Ok(None)
-        } else {
-            Ok(Some(self.entries.remove(idx).into_inner()))
-        }
+    fn remove(&mut self, id: CorpusId) -> Result<Option<Testcase<I>>, Error> {
+        Ok(self.entries.remove(id))
     }
 
     /// Get by id
     #[inline]
-    fn get(&self, idx: usize) -> Result<&RefCell<Testcase<I>>, Error> {
-        Ok(&self.entries[idx])
+    fn get(&self, id: CorpusId) -> Result<&RefCell<Testcase<I>>, Error> {
+        self.entries
+            .get(id)
+            .ok_or_else(|| Error::KeyNotFound(format!("Index {} not found", id)))
+    }
+
+    /// The id of the `nth` entry, in insertion order
+    #[inline]
+    fn nth(&self, nth: usize) -> Option<CorpusId> {
+        self.entries.nth(nth)
+    }
+
+    /// The id of the entry following `id`, in insertion order
+    #[inline]
+    fn next(&self, id: CorpusId) -> Option<CorpusId> {
+        self.entries.next(id)
     }
 
     /// Current testcase scheduled
     #[inline]
-    fn current(&self) -> &Option<usize> {
+    fn current(&self) -> &Option<CorpusId> {
         &self.current
     }
 
     /// Current testcase scheduled (mut)
     #[inline]
-    fn current_mut(&mut self) -> &mut Option<usize> {
+    fn current_mut(&mut self) -> &mut Option<CorpusId> {
         &mut self.current
     }
 }
@@ -82,7 +93,7 @@ where
     #[must_use]
     pub fn new() -> Self {
         Self {
-            entries: vec![],
+            entries: TestcaseStorage::new(),
             current: None,
         }
     }
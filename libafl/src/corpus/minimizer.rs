@@ -3,7 +3,7 @@
 
 use crate::{
     bolts::{rands::Rand, serdeany::SerdeAny, AsSlice},
-    corpus::{Corpus, CorpusScheduler, Testcase},
+    corpus::{Corpus, CorpusId, CorpusScheduler, Testcase},
     feedbacks::MapIndexesMetadata,
     inputs::{HasLen, Input},
     state::{HasCorpus, HasMetadata, HasRand},
@@ -26,8 +26,8 @@ crate::impl_serdeany!(IsFavoredMetadata);
 /// A state metadata holding a map of favoreds testcases for each map entry
 #[derive(Serialize, Deserialize)]
 pub struct TopRatedsMetadata {
-    /// map index -> corpus index
-    pub map: HashMap<usize, usize>,
+    /// map index -> corpus id
+    pub map: HashMap<usize, CorpusId>,
 }
 
 crate::impl_serdeany!(TopRatedsMetadata);
@@ -104,28 +104,34 @@ where
     R: Rand,
 {
     /// Add an entry to the corpus and return its index
-    fn on_add(&self, state: &mut S, idx: usize) -> Result<(), Error> {
+    fn on_add(&self, state: &mut S, idx: CorpusId) -> Result<(), Error> {
         self.update_score(state, idx)?;
         self.base.on_add(state, idx)
     }
 
     /// Replaces the testcase at the given idx
-    fn on_replace(&self, state: &mut S, idx: usize, testcase: &Testcase<I>) -> Result<(), Error> {
+    fn on_replace(
+        &self,
+        state: &mut S,
+        idx: CorpusId,
+        testcase: &Testcase<I>,
+    ) -> Result<(), Error> {
         self.base.on_replace(state, idx, testcase)
     }
 
-    /// Removes an entry from the corpus, returning M if M was present.
+    /// Removes an entry from the corpus, electing new favoreds for the map entries it was the favored of.
     fn on_remove(
         &self,
         state: &mut S,
-        idx: usize,
+        idx: CorpusId,
         testcase: &Option<Testcase<I>>,
     ) -> Result<(), Error> {
-        self.base.on_remove(state, idx, testcase)
+        self.base.on_remove(state, idx, testcase)?;
+        self.remove_score(state, idx)
     }
 
     /// Gets the next entry
-    fn next(&self, state: &mut S) -> Result<usize, Error> {
+    fn next(&self, state: &mut S) -> Result<CorpusId, Error> {
         self.cull(state)?;
         let mut idx = self.base.next(state)?;
         while {
@@ -155,7 +161,7 @@ where
 {
     /// Update the `Corpus` score using the `MinimizerCorpusScheduler`
     #[allow(clippy::unused_self)]
-    pub fn update_score(&self, state: &mut S, idx: usize) -> Result<(), Error> {
+    pub fn update_score(&self, state: &mut S, idx: CorpusId) -> Result<(), Error> {
         // Create a new top rated meta if not existing
         if state.metadata().get::<TopRatedsMetadata>().is_none() {
             state.add_metadata(TopRatedsMetadata::new());
@@ -199,6 +205,57 @@ where
         Ok(())
     }
 
+    /// Remove the removed [`Testcase`] with id `idx` from the `Corpus` score of the `MinimizerCorpusScheduler`,
+    /// electing the best remaining [`Testcase`]s as favoreds of the map entries it was the favored of
+    #[allow(clippy::unused_self)]
+    pub fn remove_score(&self, state: &mut S, idx: CorpusId) -> Result<(), Error> {
+        let orphans = match state.metadata_mut().get_mut::<TopRatedsMetadata>() {
+            None => return Ok(()),
+            Some(top_rated) => {
+                let orphans: HashSet<usize> = top_rated
+                    .map
+                    .iter()
+                    .filter(|(_, favored)| **favored == idx)
+                    .map(|(elem, _)| *elem)
+                    .collect();
+                top_rated.map.retain(|elem, _| !orphans.contains(elem));
+                orphans
+            }
+        };
+        if orphans.is_empty() {
+            return Ok(());
+        }
+
+        // map index -> (factor, corpus id)
+        let mut new_favoreds: HashMap<usize, (u64, CorpusId)> = HashMap::default();
+        let mut current = state.corpus().first();
+        while let Some(other_idx) = current {
+            let mut entry = state.corpus().get(other_idx)?.borrow_mut();
+            let factor = F::compute(&mut *entry)?;
+            if let Some(meta) = entry.metadata().get::<M>() {
+                for elem in meta.as_slice() {
+                    if !orphans.contains(elem) {
+                        continue;
+                    }
+                    match new_favoreds.get(elem) {
+                        Some((best, _)) if *best <= factor => {}
+                        _ => {
+                            new_favoreds.insert(*elem, (factor, other_idx));
+                        }
+                    }
+                }
+            }
+            drop(entry);
+            current = state.corpus().next(other_idx);
+        }
+
+        let top_rated = state.metadata_mut().get_mut::<TopRatedsMetadata>().unwrap();
+        for (elem, (_, other_idx)) in new_favoreds {
+            top_rated.map.insert(elem, other_idx);
+        }
+        Ok(())
+    }
+
     /// Cull the `Corpus` using the `MinimizerCorpusScheduler`
     #[allow(clippy::unused_self)]
     pub fn cull(&self, state: &mut S) -> Result<(), Error> {
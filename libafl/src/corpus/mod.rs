@@ -3,13 +3,26 @@
 pub mod testcase;
 pub use testcase::Testcase;
 
+pub mod storage;
+pub use storage::TestcaseStorage;
+
 pub mod inmemory;
 pub use inmemory::InMemoryCorpus;
 
 #[cfg(feature = "std")]
 pub mod ondisk;
 #[cfg(feature = "std")]
-pub use ondisk::OnDiskCorpus;
+pub use ondisk::{OnDiskCorpus, OnDiskMetadata};
+
+#[cfg(feature = "std")]
+pub mod cached;
+#[cfg(feature = "std")]
+pub use cached::CachedOnDiskCorpus;
+
+#[cfg(feature = "std")]
+pub mod objective;
+#[cfg(feature = "std")]
+pub use objective::ObjectiveCorpus;
 
 pub mod queue;
 pub use queue::QueueCorpusScheduler;
@@ -22,7 +35,8 @@ pub use minimizer::{
 };
 
 use alloc::borrow::ToOwned;
-use core::{cell::RefCell, marker::PhantomData};
+use core::{cell::RefCell, fmt, marker::PhantomData};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     bolts::rands::Rand,
@@ -31,6 +45,33 @@ use crate::{
     Error,
 };
 
+/// The id of a [`Testcase`] in a [`Corpus`].
+/// Ids are never reused, so an id stays valid, and keeps pointing to the same [`Testcase`],
+/// when other testcases are removed from the corpus.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize,
+)]
+#[repr(transparent)]
+pub struct CorpusId(pub usize);
+
+impl fmt::Display for CorpusId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<usize> for CorpusId {
+    fn from(id: usize) -> Self {
+        Self(id)
+    }
+}
+
+impl From<CorpusId> for usize {
+    fn from(id: CorpusId) -> Self {
+        id.0
+    }
+}
+
 /// Corpus with all current testcases
 pub trait Corpus<I>: serde::Serialize + serde::de::DeserializeOwned
 where
@@ -44,23 +85,35 @@ where
         self.count() == 0
     }
 
-    /// Add an entry to the corpus and return its index
-    fn add(&mut self, testcase: Testcase<I>) -> Result<usize, Error>;
+    /// Add an entry to the corpus and return its id
+    fn add(&mut self, testcase: Testcase<I>) -> Result<CorpusId, Error>;
 
-    /// Replaces the testcase at the given idx
-    fn replace(&mut self, idx: usize, testcase: Testcase<I>) -> Result<(), Error>;
+    /// Replaces the testcase with the given id
+    fn replace(&mut self, id: CorpusId, testcase: Testcase<I>) -> Result<(), Error>;
 
     /// Removes an entry from the corpus, returning it if it was present.
-    fn remove(&mut self, idx: usize) -> Result<Option<Testcase<I>>, Error>;
+    /// The ids of the other entries are unchanged.
+    fn remove(&mut self, id: CorpusId) -> Result<Option<Testcase<I>>, Error>;
 
     /// Get by id
-    fn get(&self, idx: usize) -> Result<&RefCell<Testcase<I>>, Error>;
+    fn get(&self, id: CorpusId) -> Result<&RefCell<Testcase<I>>, Error>;
+
+    /// The id of the `nth` entry, in insertion order
+    fn nth(&self, nth: usize) -> Option<CorpusId>;
+
+    /// The id of the entry following `id`, in insertion order
+    fn next(&self, id: CorpusId) -> Option<CorpusId>;
+
+    /// The id of the first entry, in insertion order
+    fn first(&self) -> Option<CorpusId> {
+        self.nth(0)
+    }
 
     /// Current testcase scheduled
-    fn current(&self) -> &Option<usize>;
+    fn current(&self) -> &Option<CorpusId>;
 
     /// Current testcase scheduled (mut)
-    fn current_mut(&mut self) -> &mut Option<usize>;
+    fn current_mut(&mut self) -> &mut Option<CorpusId>;
 }
 
 /// The scheduler define how the fuzzer requests a testcase from the corpus.
@@ -70,7 +123,7 @@ where
     I: Input,
 {
     /// Add an entry to the corpus and return its index
-    fn on_add(&self, _state: &mut S, _idx: usize) -> Result<(), Error> {
+    fn on_add(&self, _state: &mut S, _idx: CorpusId) -> Result<(), Error> {
         Ok(())
     }
 
@@ -78,7 +131,7 @@ where
     fn on_replace(
         &self,
         _state: &mut S,
-        _idx: usize,
+        _idx: CorpusId,
         _testcase: &Testcase<I>,
     ) -> Result<(), Error> {
         Ok(())
@@ -88,14 +141,14 @@ where
     fn on_remove(
         &self,
         _state: &mut S,
-        _idx: usize,
+        _idx: CorpusId,
         _testcase: &Option<Testcase<I>>,
     ) -> Result<(), Error> {
         Ok(())
     }
 
     /// Gets the next entry
-    fn next(&self, state: &mut S) -> Result<usize, Error>;
+    fn next(&self, state: &mut S) -> Result<CorpusId, Error>;
 }
 
 /// Feed the fuzzer simpply with a random testcase on request
@@ -117,12 +170,16 @@ where
     R: Rand,
 {
     /// Gets the next entry at random
-    fn next(&self, state: &mut S) -> Result<usize, Error> {
+    fn next(&self, state: &mut S) -> Result<CorpusId, Error> {
         if state.corpus().count() == 0 {
             Err(Error::Empty("No entries in corpus".to_owned()))
         } else {
             let len = state.corpus().count();
-            let id = state.rand_mut().below(len as u64) as usize;
+            let nth = state.rand_mut().below(len as u64) as usize;
+            let id = state
+                .corpus()
+                .nth(nth)
+                .ok_or_else(|| Error::Empty("No entries in corpus".into()))?;
             *state.corpus_mut().current_mut() = Some(id);
             Ok(id)
         }
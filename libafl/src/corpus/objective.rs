@@ -0,0 +1,263 @@
+//! The objective corpus stores the solutions to disk, in a subdirectory for each kind of objective,
+//! keeping only one testcase for each root cause.
+
+use alloc::string::String;
+use core::cell::RefCell;
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+
+use crate::{
+    corpus::{
+        ondisk::{OnDiskCorpus, OnDiskMetadataFormat},
+        Corpus, CorpusId, Testcase,
+    },
+    feedbacks::ObjectiveMetadata,
+    inputs::Input,
+    state::HasMetadata,
+    Error,
+};
+
+/// The bucket of the objectives without an [`struct@ObjectiveMetadata`], e.g. found by a custom feedback
+pub const CUSTOM_OBJECTIVE_KIND: &str = "custom";
+
+/// The name of the subdirectory storing the objectives of the given kind
+fn bucket_name(kind: &str) -> String {
+    let name: String = kind
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() {
+        CUSTOM_OBJECTIVE_KIND.into()
+    } else {
+        name
+    }
+}
+
+/// A corpus for the solutions, storing each objective to disk in a subdirectory named after its kind
+/// (`crash`, `timeout`, the type of a sanitizer error, or `custom`), as given by its [`struct@ObjectiveMetadata`].
+/// Within a kind, objectives with the same signature are duplicates of the same bug: only the first one is stored,
+/// and adding a duplicate returns the id of the stored one.
+/// Objectives without a signature are always stored.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(bound = "I: serde::de::DeserializeOwned")]
+pub struct ObjectiveCorpus<I>
+where
+    I: Input,
+{
+    inner: OnDiskCorpus<I>,
+    /// kind -> signature -> corpus id
+    signatures: HashMap<String, HashMap<u64, CorpusId>>,
+    duplicates: usize,
+}
+
+impl<I> Corpus<I> for ObjectiveCorpus<I>
+where
+    I: Input,
+{
+    /// Returns the number of elements
+    #[inline]
+    fn count(&self) -> usize {
+        self.inner.count()
+    }
+
+    /// Add an entry to the corpus and return its id, or the id of the entry it duplicates
+    fn add(&mut self, mut testcase: Testcase<I>) -> Result<CorpusId, Error> {
+        let (bucket, signature) = match testcase.metadata().get::<ObjectiveMetadata>() {
+            Some(meta) => (bucket_name(&meta.kind), meta.signature),
+            None => (CUSTOM_OBJECTIVE_KIND.into(), None),
+        };
+        if let Some(signature) = signature {
+            if let Some(idx) = self
+                .signatures
+                .get(&bucket)
+                .and_then(|signatures| signatures.get(&signature))
+            {
+                self.duplicates += 1;
+                return Ok(*idx);
+            }
+        }
+
+        if testcase.filename().is_none() {
+            let dir = self.inner.dir_path().join(&bucket);
+            fs::create_dir_all(&dir)?;
+            let filename = dir.join(
+                testcase
+                    .input()
+                    .as_ref()
+                    .unwrap()
+                    .generate_name(self.inner.peek_free_id().0),
+            );
+            let filename_str = filename.to_str().expect("Invalid Path");
+            testcase.set_filename(filename_str.into());
+        }
+        let idx = self.inner.add(testcase)?;
+        if let Some(signature) = signature {
+            self.signatures
+                .entry(bucket)
+                .or_default()
+                .insert(signature, idx);
+        }
+        Ok(idx)
+    }
+
+    /// Replaces the testcase with the given id
+    #[inline]
+    fn replace(&mut self, idx: CorpusId, testcase: Testcase<I>) -> Result<(), Error> {
+        self.inner.replace(idx, testcase)
+    }
+
+    /// Removes an entry from the corpus, returning it if it was present.
+    /// Later objectives with its signature are stored again.
+    fn remove(&mut self, idx: CorpusId) -> Result<Option<Testcase<I>>, Error> {
+        for signatures in self.signatures.values_mut() {
+            signatures.retain(|_, other| *other != idx);
+        }
+        self.inner.remove(idx)
+    }
+
+    /// Get by id
+    #[inline]
+    fn get(&self, idx: CorpusId) -> Result<&RefCell<Testcase<I>>, Error> {
+        self.inner.get(idx)
+    }
+
+    /// The id of the `nth` entry, in insertion order
+    #[inline]
+    fn nth(&self, nth: usize) -> Option<CorpusId> {
+        self.inner.nth(nth)
+    }
+
+    /// The id of the entry following `id`, in insertion order
+    #[inline]
+    fn next(&self, id: CorpusId) -> Option<CorpusId> {
+        self.inner.next(id)
+    }
+
+    /// Current testcase scheduled
+    #[inline]
+    fn current(&self) -> &Option<CorpusId> {
+        self.inner.current()
+    }
+
+    /// Current testcase scheduled (mut)
+    #[inline]
+    fn current_mut(&mut self) -> &mut Option<CorpusId> {
+        self.inner.current_mut()
+    }
+}
+
+impl<I> ObjectiveCorpus<I>
+where
+    I: Input,
+{
+    /// Creates the [`ObjectiveCorpus`], storing the objectives in subdirectories of `dir_path`.
+    /// Will error, if [`std::fs::create_dir_all()`] failed for `dir_path`.
+    pub fn new(dir_path: PathBuf) -> Result<Self, Error> {
+        Self::new_save_meta(dir_path, None)
+    }
+
+    /// Creates the [`ObjectiveCorpus`] specifying the type of `Metadata` to be saved to disk.
+    /// Will error, if [`std::fs::create_dir_all()`] failed for `dir_path`.
+    pub fn new_save_meta(
+        dir_path: PathBuf,
+        meta_format: Option<OnDiskMetadataFormat>,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            inner: OnDiskCorpus::new_save_meta(dir_path, meta_format)?,
+            signatures: HashMap::default(),
+            duplicates: 0,
+        })
+    }
+
+    /// The number of objectives dropped as duplicates of a stored one
+    #[must_use]
+    pub fn duplicates(&self) -> usize {
+        self.duplicates
+    }
+
+    /// The number of distinct signatures stored for the given kind of objective
+    #[must_use]
+    pub fn signatures_of(&self, kind: &str) -> usize {
+        self.signatures
+            .get(&bucket_name(kind))
+            .map_or(0, HashMap::len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        corpus::{objective::ObjectiveCorpus, Corpus, Testcase},
+        feedbacks::ObjectiveMetadata,
+        inputs::BytesInput,
+        state::HasMetadata,
+    };
+
+    fn objective(byte: u8, kind: Option<&str>, signature: Option<u64>) -> Testcase<BytesInput> {
+        let mut testcase = Testcase::new(BytesInput::new(vec![byte; 4]));
+        if let Some(kind) = kind {
+            testcase.add_metadata(ObjectiveMetadata::new(kind, signature));
+        }
+        testcase
+    }
+
+    #[test]
+    fn test_objective_corpus() {
+        let dir = std::env::temp_dir().join(format!(
+            "libafl_test_objective_corpus_{}",
+            std::process::id()
+        ));
+        let mut corpus = ObjectiveCorpus::<BytesInput>::new(dir.clone()).unwrap();
+        assert_eq!(corpus.duplicates(), 0);
+        assert_eq!(corpus.signatures_of("crash"), 0);
+
+        let first = corpus.add(objective(0, Some("crash"), Some(1))).unwrap();
+        assert_eq!(
+            corpus.add(objective(1, Some("crash"), Some(1))).unwrap(),
+            first
+        );
+        corpus.add(objective(2, Some("crash"), Some(2))).unwrap();
+        corpus
+            .add(objective(3, Some("asan heap-use-after-free"), Some(1)))
+            .unwrap();
+        corpus.add(objective(4, Some("timeout"), None)).unwrap();
+        corpus.add(objective(5, Some("timeout"), None)).unwrap();
+        corpus.add(objective(6, None, None)).unwrap();
+
+        assert_eq!(corpus.count(), 6);
+        assert_eq!(corpus.duplicates(), 1);
+        assert_eq!(corpus.signatures_of("crash"), 2);
+        let count = |bucket: &str| fs::read_dir(dir.join(bucket)).unwrap().count();
+        assert_eq!(count("crash"), 2);
+        assert_eq!(count("asan_heap-use-after-free"), 1);
+        assert_eq!(count("timeout"), 2);
+        assert_eq!(count("custom"), 1);
+
+        // The signatures survive a restart of the fuzzer, which reloads the serialized state
+        let reloaded: ObjectiveCorpus<BytesInput> =
+            postcard::from_bytes(&postcard::to_allocvec(&corpus).unwrap()).unwrap();
+        assert_eq!(reloaded.duplicates(), 1);
+        assert_eq!(reloaded.signatures_of("crash"), 2);
+        assert_eq!(reloaded.signatures_of("asan heap-use-after-free"), 1);
+        assert_eq!(reloaded.signatures_of("timeout"), 0);
+
+        // Once removed, a bug is stored again
+        corpus.remove(first).unwrap();
+        assert_ne!(
+            corpus.add(objective(1, Some("crash"), Some(1))).unwrap(),
+            first
+        );
+        assert_eq!(corpus.count(), 6);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
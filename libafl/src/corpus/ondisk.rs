@@ -1,13 +1,20 @@
 //! The ondisk corpus stores unused testcases to disk.
 
-use alloc::vec::Vec;
-use core::cell::RefCell;
+use core::{cell::RefCell, time::Duration};
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "std")]
 use std::{fs, fs::File, io::Write, path::PathBuf};
 
-use crate::{corpus::Corpus, corpus::Testcase, inputs::Input, state::HasMetadata, Error};
+#[cfg(feature = "llmp_compression")]
+use crate::bolts::compress::GzipCompressor;
+use crate::{
+    bolts::serdeany::SerdeAnyMap,
+    corpus::{Corpus, CorpusId, Testcase, TestcaseStorage},
+    inputs::Input,
+    state::HasMetadata,
+    Error,
+};
 
 /// Options for the the format of the on-disk metadata
 #[cfg(feature = "std")]
@@ -21,6 +28,48 @@ pub enum OnDiskMetadataFormat {
     JsonPretty,
 }
 
+/// The data of a testcase stored in its `.metadata` file, next to its input
+#[cfg(feature = "std")]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OnDiskMetadata {
+    /// The metadata of the testcase
+    pub metadata: SerdeAnyMap,
+    /// The execution time of the testcase, if known
+    pub exec_time: Option<Duration>,
+}
+
+/// Borrows the data of a testcase to serialize it in the same layout as [`OnDiskMetadata`]
+#[cfg(feature = "std")]
+#[derive(Serialize)]
+struct OnDiskMetadataRef<'a> {
+    metadata: &'a SerdeAnyMap,
+    exec_time: &'a Option<Duration>,
+}
+
+#[cfg(feature = "std")]
+impl OnDiskMetadata {
+    /// Deserializes the content of a `.metadata` file, written in any of the [`OnDiskMetadataFormat`]`s`.
+    /// The files of older versions, holding only the [`SerdeAnyMap`] of the testcase, are accepted too.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if let Ok(meta) = serde_json::from_slice(bytes) {
+            return Ok(meta);
+        }
+        if let Ok(metadata) = serde_json::from_slice(bytes) {
+            return Ok(Self {
+                metadata,
+                exec_time: None,
+            });
+        }
+        match postcard::from_bytes(bytes) {
+            Ok(meta) => Ok(meta),
+            Err(_) => Ok(Self {
+                metadata: postcard::from_bytes(bytes)?,
+                exec_time: None,
+            }),
+        }
+    }
+}
+
 /// A corpus able to store testcases to disk, and load them from disk, when they are being used.
 #[cfg(feature = "std")]
 #[derive(Default, Serialize, Deserialize, Clone, Debug)]
@@ -29,10 +78,13 @@ pub struct OnDiskCorpus<I>
 where
     I: Input,
 {
-    entries: Vec<RefCell<Testcase<I>>>,
-    current: Option<usize>,
+    entries: TestcaseStorage<I>,
+    current: Option<CorpusId>,
     dir_path: PathBuf,
     meta_format: Option<OnDiskMetadataFormat>,
+    /// If set, inputs at least this large are stored gzip-compressed
+    #[cfg(feature = "llmp_compression")]
+    compression_threshold: Option<usize>,
 }
 
 impl<I> Corpus<I> for OnDiskCorpus<I>
@@ -42,12 +94,12 @@ where
     /// Returns the number of elements
     #[inline]
     fn count(&self) -> usize {
-        self.entries.len()
+        self.entries.count()
     }
 
-    /// Add an entry to the corpus and return its index
+    /// Add an entry to the corpus and return its id
     #[inline]
-    fn add(&mut self, mut testcase: Testcase<I>) -> Result<usize, Error> {
+    fn add(&mut self, mut testcase: Testcase<I>) -> Result<CorpusId, Error> {
         if testcase.filename().is_none() {
             // TODO walk entry metadata to ask for pices of filename (e.g. :havoc in AFL)
             let filename = self.dir_path.join(
@@ -55,64 +107,68 @@ where
                     .input()
                     .as_ref()
                     .unwrap()
-                    .generate_name(self.entries.len()),
+                    .generate_name(self.entries.peek_free_id().0),
             );
             let filename_str = filename.to_str().expect("Invalid Path");
             testcase.set_filename(filename_str.into());
         };
-        if self.meta_format.is_some() {
-            let filename = testcase.filename().as_ref().unwrap().clone() + ".metadata";
-            let mut file = File::create(filename)?;
-
-            let serialized = match self.meta_format.as_ref().unwrap() {
-                OnDiskMetadataFormat::Postcard => postcard::to_allocvec(testcase.metadata())?,
-                OnDiskMetadataFormat::Json => serde_json::to_vec(testcase.metadata())?,
-                OnDiskMetadataFormat::JsonPretty => serde_json::to_vec_pretty(testcase.metadata())?,
-            };
-            file.write_all(&serialized)?;
-        }
-        testcase
-            .store_input()
-            .expect("Could not save testcase to disk");
-        self.entries.push(RefCell::new(testcase));
-        Ok(self.entries.len() - 1)
+        // Compressed inputs get a suffix, the metadata file follows the final filename
+        #[cfg(feature = "llmp_compression")]
+        let stored = match self.compression_threshold {
+            Some(threshold) => testcase.store_input_compressed(&GzipCompressor::new(threshold)),
+            None => testcase.store_input(),
+        };
+        #[cfg(not(feature = "llmp_compression"))]
+        let stored = testcase.store_input();
+        stored.expect("Could not save testcase to disk");
+        self.save_testcase_metadata(&testcase)?;
+        Ok(self.entries.insert(testcase))
     }
 
-    /// Replaces the testcase at the given idx
+    /// Replaces the testcase with the given id
     #[inline]
-    fn replace(&mut self, idx: usize, testcase: Testcase<I>) -> Result<(), Error> {
-        if idx >= self.entries.len() {
-            return Err(Error::KeyNotFound(format!("Index {} out of bounds", idx)));
-        }
-        self.entries[idx] = RefCell::new(testcase);
+    fn replace(&mut self, id: CorpusId, testcase: Testcase<I>) -> Result<(), Error> {
+        self.entries
+            .replace(id, testcase)
+            .ok_or_else(|| Error::KeyNotFound(format!("Index {} not found", id)))?;
         Ok(())
     }
 
     /// Removes an entry from the corpus, returning it if it was present.
     #[inline]
-    fn remove(&mut self, idx: usize) -> Result<Option<Testcase<I>>, Error> {
-        if idx >= self.entries.len() {
-            Ok(None)
-        } else {
-            Ok(Some(self.entries.remove(idx).into_inner()))
-        }
+    fn remove(&mut self, id: CorpusId) -> Result<Option<Testcase<I>>, Error> {
+        Ok(self.entries.remove(id))
     }
 
     /// Get by id
     #[inline]
-    fn get(&self, idx: usize) -> Result<&RefCell<Testcase<I>>, Error> {
-        Ok(&self.entries[idx])
+    fn get(&self, id: CorpusId) -> Result<&RefCell<Testcase<I>>, Error> {
+        self.entries
+            .get(id)
+            .ok_or_else(|| Error::KeyNotFound(format!("Index {} not found", id)))
+    }
+
+    /// The id of the `nth` entry, in insertion order
+    #[inline]
+    fn nth(&self, nth: usize) -> Option<CorpusId> {
+        self.entries.nth(nth)
+    }
+
+    /// The id of the entry following `id`, in insertion order
+    #[inline]
+    fn next(&self, id: CorpusId) -> Option<CorpusId> {
+        self.entries.next(id)
     }
 
     /// Current testcase scheduled
     #[inline]
-    fn current(&self) -> &Option<usize> {
+    fn current(&self) -> &Option<CorpusId> {
         &self.current
     }
 
     /// Current testcase scheduled (mut)
     #[inline]
-    fn current_mut(&mut self) -> &mut Option<usize> {
+    fn current_mut(&mut self) -> &mut Option<CorpusId> {
         &mut self.current
     }
 }
@@ -121,15 +177,74 @@ impl<I> OnDiskCorpus<I>
 where
     I: Input,
 {
+    /// The id the next added testcase will get
+    pub(crate) fn peek_free_id(&self) -> CorpusId {
+        self.entries.peek_free_id()
+    }
+
+    /// The directory the testcases are stored in
+    #[must_use]
+    pub fn dir_path(&self) -> &PathBuf {
+        &self.dir_path
+    }
+
+    /// Removes an entry from the corpus, returning it if it was present, and deletes its input and `.metadata` files.
+    /// Filenames are derived from the content of the inputs, so the files are kept if another entry still uses them.
+    /// The input of the returned testcase is loaded in memory before the files are deleted.
+    pub fn remove_and_delete(&mut self, id: CorpusId) -> Result<Option<Testcase<I>>, Error> {
+        if let Some(testcase) = self.entries.get(id) {
+            testcase.borrow_mut().load_input()?;
+        }
+        let testcase = self.entries.remove(id);
+        if let Some(filename) = testcase
+            .as_ref()
+            .and_then(|testcase| testcase.filename().as_ref())
+        {
+            let referenced = self.entries.ids().iter().any(|idx| {
+                self.entries.get(*idx).unwrap().borrow().filename().as_ref() == Some(filename)
+            });
+            if !referenced {
+                fs::remove_file(filename)?;
+                let meta_path = PathBuf::from(format!("{}.metadata", filename));
+                if meta_path.is_file() {
+                    fs::remove_file(meta_path)?;
+                }
+            }
+        }
+        Ok(testcase)
+    }
+
+    /// Writes the metadata of the testcase next to its input, in the `.metadata` file, if a format is set
+    pub(crate) fn save_testcase_metadata(&self, testcase: &Testcase<I>) -> Result<(), Error> {
+        if let Some(meta_format) = self.meta_format.as_ref() {
+            let filename = testcase.filename().as_ref().unwrap().clone() + ".metadata";
+            let mut file = File::create(filename)?;
+
+            let meta = OnDiskMetadataRef {
+                metadata: testcase.metadata(),
+                exec_time: testcase.exec_time(),
+            };
+            let serialized = match meta_format {
+                OnDiskMetadataFormat::Postcard => postcard::to_allocvec(&meta)?,
+                OnDiskMetadataFormat::Json => serde_json::to_vec(&meta)?,
+                OnDiskMetadataFormat::JsonPretty => serde_json::to_vec_pretty(&meta)?,
+            };
+            file.write_all(&serialized)?;
+        }
+        Ok(())
+    }
+
     /// Creates the [`OnDiskCorpus`].
     /// Will error, if [`std::fs::create_dir_all()`] failed for `dir_path`.
     pub fn new(dir_path: PathBuf) -> Result<Self, Error> {
         fs::create_dir_all(&dir_path)?;
         Ok(Self {
-            entries: vec![],
+            entries: TestcaseStorage::new(),
             current: None,
             dir_path,
             meta_format: None,
+            #[cfg(feature = "llmp_compression")]
+            compression_threshold: None,
         })
     }
 
@@ -141,10 +256,208 @@ where
     ) -> Result<Self, Error> {
         fs::create_dir_all(&dir_path)?;
         Ok(Self {
-            entries: vec![],
+            entries: TestcaseStorage::new(),
             current: None,
             dir_path,
             meta_format,
+            #[cfg(feature = "llmp_compression")]
+            compression_threshold: None,
         })
     }
+
+    /// Creates the [`OnDiskCorpus`] from the testcases already stored in `dir_path`, e.g. by a previous run,
+    /// saving the `Metadata` of new testcases in `meta_format`.
+    /// The testcases are added in the order of their filenames, together with the metadata and execution time
+    /// in their `.metadata` file, if any, in any of the [`OnDiskMetadataFormat`]`s`.
+    /// The inputs are not read, they are loaded from their file when they are used.
+    /// Use [`crate::state::StdState::replay_corpus`] to let the scheduler know about the loaded testcases.
+    /// Will error, if the directory, or any of its `.metadata` files, cannot be read.
+    pub fn load_from_dir(
+        dir_path: PathBuf,
+        meta_format: Option<OnDiskMetadataFormat>,
+    ) -> Result<Self, Error> {
+        let mut corpus = Self::new_save_meta(dir_path, meta_format)?;
+
+        let mut paths = vec![];
+        for entry in fs::read_dir(&corpus.dir_path)? {
+            let path = entry?.path();
+            let is_input = path.is_file()
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .map_or(false, |name| {
+                        !name.starts_with('.') && !name.ends_with(".metadata")
+                    });
+            if is_input {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        for path in paths {
+            let filename = path
+                .to_str()
+                .ok_or_else(|| Error::IllegalArgument(format!("Invalid path {:?}", path)))?;
+            let mut testcase = Testcase::default();
+            testcase.set_filename(filename.into());
+
+            let meta_path = path.with_file_name(format!(
+                "{}.metadata",
+                path.file_name().unwrap().to_str().unwrap()
+            ));
+            if meta_path.is_file() {
+                let meta = OnDiskMetadata::from_bytes(&fs::read(&meta_path)?)?;
+                *testcase.metadata_mut() = meta.metadata;
+                *testcase.exec_time_mut() = meta.exec_time;
+            }
+            corpus.entries.insert(testcase);
+        }
+        Ok(corpus)
+    }
+
+    /// Creates the [`OnDiskCorpus`], storing inputs of at least `threshold` bytes gzip-compressed.
+    /// Compressed inputs are decompressed transparently when they are loaded.
+    /// Will error, if [`std::fs::create_dir_all()`] failed for `dir_path`.
+    #[cfg(feature = "llmp_compression")]
+    pub fn new_compressed(
+        dir_path: PathBuf,
+        meta_format: Option<OnDiskMetadataFormat>,
+        threshold: usize,
+    ) -> Result<Self, Error> {
+        let mut corpus = Self::new_save_meta(dir_path, meta_format)?;
+        corpus.compression_threshold = Some(threshold);
+        Ok(corpus)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+    use std::{fs, path::Path};
+
+    use crate::{
+        bolts::{rands::StdRand, serdeany::SerdeAnyMap},
+        corpus::{
+            ondisk::OnDiskMetadataFormat, Corpus, InMemoryCorpus, OnDiskCorpus, OnDiskMetadata,
+            QueueCorpusScheduler, Testcase,
+        },
+        inputs::{BytesInput, HasBytesVec},
+        mutators::LogMutationMetadata,
+        state::{HasMetadata, StdState},
+    };
+
+    #[test]
+    fn test_ondisk_load_from_dir() {
+        let dir = std::env::temp_dir().join(format!(
+            "libafl_test_ondisk_load_from_dir_{}",
+            std::process::id()
+        ));
+        for meta_format in &[
+            OnDiskMetadataFormat::Postcard,
+            OnDiskMetadataFormat::Json,
+            OnDiskMetadataFormat::JsonPretty,
+        ] {
+            let mut corpus =
+                OnDiskCorpus::<BytesInput>::new_save_meta(dir.clone(), Some(meta_format.clone()))
+                    .unwrap();
+            for i in 0..3_u8 {
+                let mut testcase = Testcase::new(BytesInput::new(vec![i; 4]));
+                testcase.add_metadata(LogMutationMetadata::new(vec!["test".into()], None));
+                *testcase.exec_time_mut() = Some(Duration::from_millis(u64::from(i)));
+                corpus.add(testcase).unwrap();
+            }
+
+            let corpus =
+                OnDiskCorpus::<BytesInput>::load_from_dir(dir.clone(), Some(meta_format.clone()))
+                    .unwrap();
+            assert_eq!(corpus.count(), 3);
+            let mut exec_times = vec![];
+            let mut current = corpus.first();
+            while let Some(idx) = current {
+                let mut testcase = corpus.get(idx).unwrap().borrow_mut();
+                // The inputs are loaded lazily
+                assert!(testcase.input().is_none());
+                assert!(testcase.filename().is_some());
+                assert_eq!(testcase.load_input().unwrap().bytes().len(), 4);
+                assert!(testcase.has_metadata::<LogMutationMetadata>());
+                exec_times.push(testcase.exec_time().unwrap().as_millis());
+                drop(testcase);
+                current = corpus.next(idx);
+            }
+            exec_times.sort_unstable();
+            assert_eq!(exec_times, vec![0, 1, 2]);
+
+            let mut state = StdState::new(StdRand::with_seed(0), corpus, InMemoryCorpus::new(), ());
+            let scheduler = QueueCorpusScheduler::new();
+            assert_eq!(state.replay_corpus(&scheduler).unwrap(), 3);
+
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_ondisk_remove() {
+        let dir =
+            std::env::temp_dir().join(format!("libafl_test_ondisk_remove_{}", std::process::id()));
+        let mut corpus = OnDiskCorpus::<BytesInput>::new_save_meta(
+            dir.clone(),
+            Some(OnDiskMetadataFormat::Json),
+        )
+        .unwrap();
+        let filename_of = |corpus: &OnDiskCorpus<BytesInput>, idx| {
+            corpus
+                .get(idx)
+                .unwrap()
+                .borrow()
+                .filename()
+                .clone()
+                .unwrap()
+        };
+
+        // Removing a testcase leaves its files alone
+        let idx = corpus
+            .add(Testcase::new(BytesInput::new(b"abc".to_vec())))
+            .unwrap();
+        let filename = filename_of(&corpus, idx);
+        let meta_filename = format!("{}.metadata", filename);
+        corpus.remove(idx).unwrap().unwrap();
+        assert_eq!(corpus.count(), 0);
+        assert!(Path::new(&filename).is_file());
+        assert!(Path::new(&meta_filename).is_file());
+
+        // Identical testcases share their files, which are deleted with the last of them
+        let first = corpus
+            .add(Testcase::new(BytesInput::new(b"abc".to_vec())))
+            .unwrap();
+        let second = corpus
+            .add(Testcase::new(BytesInput::new(b"abc".to_vec())))
+            .unwrap();
+        assert_eq!(filename_of(&corpus, second), filename);
+
+        let testcase = corpus.remove_and_delete(first).unwrap().unwrap();
+        assert_eq!(testcase.input().as_ref().unwrap().bytes(), b"abc");
+        assert!(Path::new(&filename).is_file());
+        assert!(Path::new(&meta_filename).is_file());
+
+        corpus.remove_and_delete(second).unwrap().unwrap();
+        assert_eq!(corpus.count(), 0);
+        assert!(!Path::new(&filename).exists());
+        assert!(!Path::new(&meta_filename).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_ondisk_metadata_old_format() {
+        let mut metadata = SerdeAnyMap::new();
+        metadata.insert(LogMutationMetadata::new(vec!["test".into()], None));
+        for bytes in &[
+            serde_json::to_vec(&metadata).unwrap(),
+            postcard::to_allocvec(&metadata).unwrap(),
+        ] {
+            let meta = OnDiskMetadata::from_bytes(bytes).unwrap();
+            assert!(meta.metadata.get::<LogMutationMetadata>().is_some());
+            assert!(meta.exec_time.is_none());
+        }
+    }
 }
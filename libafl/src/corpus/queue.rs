@@ -4,7 +4,7 @@ use alloc::borrow::ToOwned;
 use core::marker::PhantomData;
 
 use crate::{
-    corpus::{Corpus, CorpusScheduler},
+    corpus::{Corpus, CorpusId, CorpusScheduler},
     inputs::Input,
     state::HasCorpus,
     Error,
@@ -27,20 +27,16 @@ where
     I: Input,
 {
     /// Gets the next entry in the queue
-    fn next(&self, state: &mut S) -> Result<usize, Error> {
+    fn next(&self, state: &mut S) -> Result<CorpusId, Error> {
         if state.corpus().count() == 0 {
             Err(Error::Empty("No entries in corpus".to_owned()))
         } else {
-            let id = match state.corpus().current() {
-                Some(cur) => {
-                    if *cur + 1 >= state.corpus().count() {
-                        0
-                    } else {
-                        *cur + 1
-                    }
-                }
-                None => 0,
-            };
+            let corpus = state.corpus();
+            let id = corpus
+                .current()
+                .and_then(|cur| corpus.next(cur))
+                .or_else(|| corpus.first())
+                .unwrap();
             *state.corpus_mut().current_mut() = Some(id);
             Ok(id)
         }
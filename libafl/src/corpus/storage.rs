@@ -0,0 +1,143 @@
+//! The testcase storage maps the stable [`CorpusId`]`s` of a corpus to its [`Testcase`]`s`.
+
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{CorpusId, Testcase},
+    inputs::Input,
+};
+
+/// The storage of the [`Testcase`]`s` of a corpus, by [`CorpusId`].
+/// Ids are given in increasing order and never reused, so removing an entry leaves the other ids untouched.
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
+#[serde(bound = "I: serde::de::DeserializeOwned")]
+pub struct TestcaseStorage<I>
+where
+    I: Input,
+{
+    map: HashMap<CorpusId, RefCell<Testcase<I>>>,
+    /// The ids of the entries, in insertion (and increasing) order
+    ids: Vec<CorpusId>,
+    progressive_id: usize,
+}
+
+impl<I> TestcaseStorage<I>
+where
+    I: Input,
+{
+    /// Creates a new, empty, [`TestcaseStorage`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            map: HashMap::default(),
+            ids: vec![],
+            progressive_id: 0,
+        }
+    }
+
+    /// The number of entries
+    #[inline]
+    #[must_use]
+    pub fn count(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// The id the next inserted entry will get
+    #[inline]
+    #[must_use]
+    pub fn peek_free_id(&self) -> CorpusId {
+        CorpusId(self.progressive_id)
+    }
+
+    /// Inserts a new entry, returning its id
+    pub fn insert(&mut self, testcase: Testcase<I>) -> CorpusId {
+        let id = CorpusId(self.progressive_id);
+        self.progressive_id += 1;
+        self.map.insert(id, RefCell::new(testcase));
+        self.ids.push(id);
+        id
+    }
+
+    /// Replaces the entry with the given id, returning the old one, if it was present
+    pub fn replace(&mut self, id: CorpusId, testcase: Testcase<I>) -> Option<Testcase<I>> {
+        self.map.get_mut(&id).map(|entry| entry.replace(testcase))
+    }
+
+    /// Removes the entry with the given id, returning it, if it was present
+    pub fn remove(&mut self, id: CorpusId) -> Option<Testcase<I>> {
+        let testcase = self.map.remove(&id)?;
+        if let Ok(pos) = self.ids.binary_search(&id) {
+            self.ids.remove(pos);
+        }
+        Some(testcase.into_inner())
+    }
+
+    /// Gets the entry with the given id
+    #[inline]
+    #[must_use]
+    pub fn get(&self, id: CorpusId) -> Option<&RefCell<Testcase<I>>> {
+        self.map.get(&id)
+    }
+
+    /// The id of the `nth` entry, in insertion order
+    #[inline]
+    #[must_use]
+    pub fn nth(&self, nth: usize) -> Option<CorpusId> {
+        self.ids.get(nth).copied()
+    }
+
+    /// The id of the first entry inserted after `id`.
+    /// `id` itself does not need to be present anymore.
+    #[must_use]
+    pub fn next(&self, id: CorpusId) -> Option<CorpusId> {
+        let pos = match self.ids.binary_search(&id) {
+            Ok(pos) => pos + 1,
+            Err(pos) => pos,
+        };
+        self.ids.get(pos).copied()
+    }
+
+    /// The ids of all the entries, in insertion order
+    #[inline]
+    #[must_use]
+    pub fn ids(&self) -> &[CorpusId] {
+        &self.ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        corpus::{CorpusId, Testcase, TestcaseStorage},
+        inputs::BytesInput,
+    };
+
+    #[test]
+    fn test_testcase_storage() {
+        let mut storage = TestcaseStorage::<BytesInput>::new();
+        let ids: Vec<CorpusId> = (0..4_u8)
+            .map(|i| storage.insert(Testcase::new(BytesInput::new(vec![i]))))
+            .collect();
+
+        assert!(storage.remove(ids[1]).is_some());
+        assert!(storage.remove(ids[1]).is_none());
+        assert_eq!(storage.count(), 3);
+
+        // The other ids still point to the same testcases
+        let input = storage.get(ids[2]).unwrap().borrow().input().clone();
+        assert_eq!(input, Some(BytesInput::new(vec![2])));
+        assert_eq!(storage.nth(1), Some(ids[2]));
+        assert_eq!(storage.next(ids[0]), Some(ids[2]));
+        assert_eq!(storage.next(ids[1]), Some(ids[2]));
+        assert_eq!(storage.next(ids[3]), None);
+
+        // Removed ids are not reused
+        assert_ne!(
+            storage.insert(Testcase::new(BytesInput::new(vec![]))),
+            ids[1]
+        );
+    }
+}
@@ -5,6 +5,8 @@ use alloc::string::String;
 use core::{convert::Into, default::Default, option::Option, time::Duration};
 use serde::{Deserialize, Serialize};
 
+#[cfg(all(feature = "std", feature = "llmp_compression"))]
+use crate::{bolts::compress::GzipCompressor, inputs::COMPRESSED_SUFFIX};
 use crate::{
     bolts::serdeany::SerdeAnyMap,
     inputs::{HasLen, Input},
@@ -56,7 +58,17 @@ where
     /// Returns this testcase with a loaded input
     pub fn load_input(&mut self) -> Result<&I, Error> {
         if self.input.is_none() {
-            self.input = Some(I::from_file(self.filename.as_ref().unwrap())?);
+            let filename = self.filename.as_ref().unwrap();
+            // Only the files written compressed by `store_input_compressed` are decompressed
+            #[cfg(all(feature = "std", feature = "llmp_compression"))]
+            let input = if filename.ends_with(COMPRESSED_SUFFIX) {
+                I::from_file_compressed(filename)?
+            } else {
+                I::from_file(filename)?
+            };
+            #[cfg(not(all(feature = "std", feature = "llmp_compression")))]
+            let input = I::from_file(filename)?;
+            self.input = Some(input);
         }
         Ok(self.input.as_ref().unwrap())
     }
@@ -81,6 +93,22 @@ where
         }
     }
 
+    /// Store the input to disk if possible, gzip-compressed if it is larger than the threshold of the `compressor`.
+    /// The filename of compressed inputs gets the [`COMPRESSED_SUFFIX`] appended.
+    #[cfg(all(feature = "std", feature = "llmp_compression"))]
+    pub fn store_input_compressed(&mut self, compressor: &GzipCompressor) -> Result<bool, Error> {
+        let fname = match self.filename() {
+            Some(f) => f.clone(),
+            None => return Ok(false),
+        };
+        let written = match self.input() {
+            None => return Ok(false),
+            Some(i) => i.to_file_compressed(fname, compressor)?,
+        };
+        self.set_filename(written.to_str().expect("Invalid Path").into());
+        Ok(true)
+    }
+
     /// Get the input, if any
     #[inline]
     pub fn input(&self) -> &Option<I> {
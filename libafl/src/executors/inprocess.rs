@@ -14,6 +14,8 @@ use core::{
 use crate::bolts::os::unix_signals::setup_signal_handler;
 #[cfg(all(windows, feature = "std"))]
 use crate::bolts::os::windows_exceptions::setup_exception_handler;
+#[cfg(all(unix, feature = "std"))]
+pub use unix_signal_handler::last_fault_signature;
 
 use crate::{
     corpus::Corpus,
@@ -71,6 +73,11 @@ where
             write_volatile(&mut data.state_ptr, state as *mut _ as *mut c_void);
             write_volatile(&mut data.event_mgr_ptr, mgr as *mut _ as *mut c_void);
             write_volatile(&mut data.fuzzer_ptr, fuzzer as *mut _ as *mut c_void);
+            #[cfg(feature = "std")]
+            write_volatile(
+                ptr::addr_of_mut!(unix_signal_handler::FAULT_SIGNATURE),
+                None,
+            );
             compiler_fence(Ordering::SeqCst);
         }
         #[cfg(all(windows, feature = "std"))]
@@ -225,7 +232,11 @@ mod unix_signal_handler {
     use libc::{c_void, siginfo_t, ucontext_t};
     #[cfg(feature = "std")]
     use std::io::{stdout, Write};
+    #[cfg(feature = "std")]
+    use {ahash::AHasher, core::hash::Hasher};
 
+    #[cfg(feature = "std")]
+    use crate::bolts::os::{hash_function_offset, hash_module_offset};
     use crate::{
         bolts::os::unix_signals::{Handler, Signal},
         corpus::{Corpus, Testcase},
@@ -271,6 +282,84 @@ mod unix_signal_handler {
     unsafe impl Send for InProcessExecutorHandlerData {}
     unsafe impl Sync for InProcessExecutorHandlerData {}
 
+    /// The signature of the crash or timeout of the last execution, set by the handlers before asking the objective
+    #[cfg(feature = "std")]
+    pub static mut FAULT_SIGNATURE: Option<u64> = None;
+
+    /// The number of frames of the stack hashed in the signature of a fault
+    #[cfg(all(feature = "std", feature = "backtrace"))]
+    const FAULT_SIGNATURE_FRAMES: usize = 8;
+
+    /// The signature of the crash or timeout of the last execution of an [`super::InProcessExecutor`], if any.
+    /// It hashes the module-relative offsets of the pc the target faulted at and, with the `backtrace` feature,
+    /// of the return addresses of its stack, so it stays the same across runs despite ASLR.
+    /// For timeouts, the function the target was interrupted in is hashed instead of the pc.
+    #[must_use]
+    #[cfg(feature = "std")]
+    pub fn last_fault_signature() -> Option<u64> {
+        unsafe { *ptr::addr_of!(FAULT_SIGNATURE) }
+    }
+
+    /// Computes the signature of the fault interrupted at `context`, see [`last_fault_signature`]
+    #[cfg(feature = "std")]
+    unsafe fn fault_signature(context: &ucontext_t, timeout: bool) -> Option<u64> {
+        let pc = ucontext_pc(context)?;
+        let mut hasher = AHasher::new_with_keys(0, 0);
+        if timeout {
+            hash_function_offset(&mut hasher, pc);
+        } else {
+            hash_module_offset(&mut hasher, pc);
+        }
+        #[cfg(feature = "backtrace")]
+        {
+            // Skip the frames of the signal handler, up to the one of the fault
+            let mut in_target = false;
+            let mut frames = 0;
+            backtrace::trace_unsynchronized(|frame| {
+                let ip = frame.ip() as usize;
+                if in_target {
+                    hash_module_offset(&mut hasher, ip);
+                    frames += 1;
+                } else {
+                    in_target = ip == pc;
+                }
+                frames < FAULT_SIGNATURE_FRAMES
+            });
+        }
+        Some(hasher.finish())
+    }
+
+    /// The pc the signal interrupted the target at
+    #[cfg(all(feature = "std", target_os = "linux", target_arch = "x86_64"))]
+    fn ucontext_pc(context: &ucontext_t) -> Option<usize> {
+        Some(context.uc_mcontext.gregs[libc::REG_RIP as usize] as usize)
+    }
+
+    /// The pc the signal interrupted the target at
+    #[cfg(all(
+        feature = "std",
+        any(target_os = "linux", target_os = "android"),
+        target_arch = "aarch64"
+    ))]
+    fn ucontext_pc(context: &ucontext_t) -> Option<usize> {
+        Some(context.uc_mcontext.pc as usize)
+    }
+
+    /// The pc the signal interrupted the target at, unknown on this platform
+    #[cfg(all(
+        feature = "std",
+        not(any(
+            all(target_os = "linux", target_arch = "x86_64"),
+            all(
+                any(target_os = "linux", target_os = "android"),
+                target_arch = "aarch64"
+            )
+        ))
+    ))]
+    fn ucontext_pc(_context: &ucontext_t) -> Option<usize> {
+        None
+    }
+
     unsafe fn nop_handler(
         _signal: Signal,
         _info: siginfo_t,
@@ -340,6 +429,10 @@ mod unix_signal_handler {
             let input = (data.current_input_ptr as *const I).as_ref().unwrap();
             data.current_input_ptr = ptr::null();
 
+            #[cfg(feature = "std")]
+            {
+                *ptr::addr_of_mut!(FAULT_SIGNATURE) = fault_signature(_context, true);
+            }
             let interesting = fuzzer
                 .objective_mut()
                 .is_interesting(state, event_mgr, input, observers, &ExitKind::Timeout)
@@ -478,6 +571,12 @@ mod unix_signal_handler {
             // Make sure we don't crash in the crash handler forever.
             data.current_input_ptr = ptr::null();
 
+            // `_context` is a copy on android aarch64
+            #[cfg(feature = "std")]
+            #[allow(clippy::needless_borrow)]
+            {
+                *ptr::addr_of_mut!(FAULT_SIGNATURE) = fault_signature(&_context, false);
+            }
             let interesting = fuzzer
                 .objective_mut()
                 .is_interesting(state, event_mgr, input, observers, &ExitKind::Crash)
@@ -4,6 +4,11 @@
 pub mod map;
 pub use map::*;
 
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "python")]
+pub use python::PythonFeedback;
+
 use alloc::string::{String, ToString};
 use serde::{Deserialize, Serialize};
 
@@ -13,7 +18,9 @@ use crate::{
     events::EventFirer,
     executors::ExitKind,
     inputs::Input,
+    mutators::LogMutationMetadata,
     observers::{ObserversTuple, TimeObserver},
+    state::HasMetadata,
     Error,
 };
 
@@ -720,9 +727,61 @@ impl Named for () {
     }
 }
 
+/// A testcase metadata describing the objective an input fulfilled.
+/// Objective corpora, like the [`crate::corpus::ObjectiveCorpus`], use it to bucket and deduplicate the solutions.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ObjectiveMetadata {
+    /// The kind of the objective, e.g. `crash`, `timeout`, or the type of a sanitizer error
+    pub kind: String,
+    /// The signature of the root cause, if known, e.g. a hash of the faulting pc and of the backtrace.
+    /// Objectives of the same kind with the same signature are considered duplicates.
+    pub signature: Option<u64>,
+}
+
+crate::impl_serdeany!(ObjectiveMetadata);
+
+impl ObjectiveMetadata {
+    /// Creates a new [`struct@ObjectiveMetadata`]
+    #[must_use]
+    pub fn new(kind: &str, signature: Option<u64>) -> Self {
+        Self {
+            kind: kind.to_string(),
+            signature,
+        }
+    }
+}
+
+/// Adds an [`struct@ObjectiveMetadata`] of the given `kind` to the testcase, unless a more specific feedback already did
+fn append_objective_kind<I>(testcase: &mut Testcase<I>, kind: &str, signature: Option<u64>)
+where
+    I: Input,
+{
+    if !testcase.has_metadata::<ObjectiveMetadata>() {
+        testcase.add_metadata(ObjectiveMetadata::new(kind, signature));
+    }
+}
+
+/// The signature of the fault of the last execution, if the executor computed one,
+/// as the [`crate::executors::InProcessExecutor`] does on unix
+fn last_fault_signature() -> Option<u64> {
+    #[cfg(all(unix, feature = "std"))]
+    {
+        crate::executors::inprocess::last_fault_signature()
+    }
+    #[cfg(not(all(unix, feature = "std")))]
+    {
+        None
+    }
+}
+
 /// A [`CrashFeedback`] reports as interesting if the target crashed.
+/// Crashing testcases are tagged with an [`struct@ObjectiveMetadata`] of kind `crash`,
+/// with the signature of the faulting pc and stack, if the executor computed one.
 #[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct CrashFeedback {}
+pub struct CrashFeedback {
+    crashed: bool,
+    signature: Option<u64>,
+}
 
 impl<I, S> Feedback<I, S> for CrashFeedback
 where
@@ -740,11 +799,24 @@ where
         EM: EventFirer<I, S>,
         OT: ObserversTuple,
     {
-        if let ExitKind::Crash = exit_kind {
-            Ok(true)
-        } else {
-            Ok(false)
+        self.crashed = matches!(exit_kind, ExitKind::Crash);
+        self.signature = last_fault_signature().filter(|_| self.crashed);
+        Ok(self.crashed)
+    }
+
+    fn append_metadata(&mut self, _state: &mut S, testcase: &mut Testcase<I>) -> Result<(), Error> {
+        if self.crashed {
+            append_objective_kind(testcase, "crash", self.signature);
         }
+        self.crashed = false;
+        self.signature = None;
+        Ok(())
+    }
+
+    fn discard_metadata(&mut self, _state: &mut S, _input: &I) -> Result<(), Error> {
+        self.crashed = false;
+        self.signature = None;
+        Ok(())
     }
 }
 
@@ -759,7 +831,10 @@ impl CrashFeedback {
     /// Creates a new [`CrashFeedback`]
     #[must_use]
     pub fn new() -> Self {
-        Self {}
+        Self {
+            crashed: false,
+            signature: None,
+        }
     }
 }
 
@@ -770,8 +845,13 @@ impl Default for CrashFeedback {
 }
 
 /// A [`TimeoutFeedback`] reduces the timeout value of a run.
+/// Timed out testcases are tagged with an [`struct@ObjectiveMetadata`] of kind `timeout`,
+/// with the signature of the function and stack the target was interrupted in, if the executor computed one.
 #[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct TimeoutFeedback {}
+pub struct TimeoutFeedback {
+    timed_out: bool,
+    signature: Option<u64>,
+}
 
 impl<I, S> Feedback<I, S> for TimeoutFeedback
 where
@@ -789,11 +869,24 @@ where
         EM: EventFirer<I, S>,
         OT: ObserversTuple,
     {
-        if let ExitKind::Timeout = exit_kind {
-            Ok(true)
-        } else {
-            Ok(false)
+        self.timed_out = matches!(exit_kind, ExitKind::Timeout);
+        self.signature = last_fault_signature().filter(|_| self.timed_out);
+        Ok(self.timed_out)
+    }
+
+    fn append_metadata(&mut self, _state: &mut S, testcase: &mut Testcase<I>) -> Result<(), Error> {
+        if self.timed_out {
+            append_objective_kind(testcase, "timeout", self.signature);
         }
+        self.timed_out = false;
+        self.signature = None;
+        Ok(())
+    }
+
+    fn discard_metadata(&mut self, _state: &mut S, _input: &I) -> Result<(), Error> {
+        self.timed_out = false;
+        self.signature = None;
+        Ok(())
     }
 }
 
@@ -808,7 +901,10 @@ impl TimeoutFeedback {
     /// Returns a new [`TimeoutFeedback`].
     #[must_use]
     pub fn new() -> Self {
-        Self {}
+        Self {
+            timed_out: false,
+            signature: None,
+        }
     }
 }
 
@@ -891,3 +987,60 @@ impl TimeFeedback {
         }
     }
 }
+
+/// Nop feedback that annotates the new testcase with the [`LogMutationMetadata`] of the mutation that produced it, if any.
+/// The log is left in the state by a [`crate::mutators::LoggerScheduledMutator`].
+/// For this Feedback, the testcase is never interesting (use with an OR), both for the corpus and the objectives.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MutationLogFeedback {}
+
+impl<I, S> Feedback<I, S> for MutationLogFeedback
+where
+    I: Input,
+    S: HasMetadata,
+{
+    fn is_interesting<EM, OT>(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &I,
+        _observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<I, S>,
+        OT: ObserversTuple,
+    {
+        Ok(false)
+    }
+
+    /// Append the log of the last mutation to the testcase, before it gets stored
+    #[inline]
+    fn append_metadata(&mut self, state: &mut S, testcase: &mut Testcase<I>) -> Result<(), Error> {
+        if let Some(log) = state.metadata().get::<LogMutationMetadata>() {
+            testcase.add_metadata(log.clone());
+        }
+        Ok(())
+    }
+}
+
+impl Named for MutationLogFeedback {
+    #[inline]
+    fn name(&self) -> &str {
+        "MutationLogFeedback"
+    }
+}
+
+impl MutationLogFeedback {
+    /// Creates a new [`MutationLogFeedback`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Default for MutationLogFeedback {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,145 @@
+//! A [`Feedback`] implemented by a Python object, loaded from a script at runtime.
+
+use alloc::string::String;
+use pyo3::{prelude::*, types::PyBytes};
+
+use crate::{
+    bolts::{
+        python::{load_python_object, PythonMetadata},
+        tuples::Named,
+    },
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::Feedback,
+    inputs::{HasBytesVec, Input},
+    observers::ObserversTuple,
+    state::HasMetadata,
+    Error,
+};
+
+/// A [`Feedback`] calling the `is_interesting(self, buf, exit_kind, meta)` method of a Python object.
+/// `buf` is the input as `bytes`, `exit_kind` one of `"ok"`, `"crash"`, `"oom"`, `"timeout"` or `"custom"`,
+/// and `meta` the `dict` of the [`PythonMetadata`] of the state, whose changes are kept.
+/// The method returns a `bool`.
+/// The decision is expected to depend on the execution only, so the script gets no rand,
+/// and the other metadata of the state are not accessible to it.
+pub struct PythonFeedback {
+    name: String,
+    object: PyObject,
+}
+
+impl<I, S> Feedback<I, S> for PythonFeedback
+where
+    I: Input + HasBytesVec,
+    S: HasMetadata,
+{
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        input: &I,
+        _observers: &OT,
+        exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<I, S>,
+        OT: ObserversTuple,
+    {
+        if state.metadata().get::<PythonMetadata>().is_none() {
+            state.add_metadata(PythonMetadata::new());
+        }
+        let meta = state.metadata_mut().get_mut::<PythonMetadata>().unwrap();
+        let exit_kind = match exit_kind {
+            ExitKind::Ok => "ok",
+            ExitKind::Crash => "crash",
+            ExitKind::Oom => "oom",
+            ExitKind::Timeout => "timeout",
+            ExitKind::Custom(_) => "custom",
+        };
+
+        Python::with_gil(|py| {
+            let dict = meta.to_dict(py)?;
+            let ret = self.object.call_method1(
+                py,
+                "is_interesting",
+                (PyBytes::new(py, input.bytes()), exit_kind, dict),
+            )?;
+            meta.update_from_dict(dict)?;
+            Ok(ret.as_ref(py).is_true()?)
+        })
+    }
+}
+
+impl Named for PythonFeedback {
+    #[inline]
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl PythonFeedback {
+    /// Creates a new [`PythonFeedback`], running the script at `path` and instantiating its class `class_name`
+    pub fn new(path: &str, class_name: &str) -> Result<Self, Error> {
+        Ok(Self {
+            name: format!("PythonFeedback({})", class_name),
+            object: load_python_object(path, class_name)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        bolts::{python::PythonMetadata, rands::StdRand},
+        corpus::InMemoryCorpus,
+        events::NopEventManager,
+        executors::ExitKind,
+        feedbacks::{python::PythonFeedback, Feedback},
+        inputs::BytesInput,
+        state::{HasMetadata, StdState},
+    };
+
+    #[test]
+    fn test_python_feedback() {
+        let dir = std::env::temp_dir().join(format!(
+            "libafl_test_python_feedback_{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("feedback.py");
+        fs::write(
+            &path,
+            "class Feedback:\n\
+             \x20   def is_interesting(self, buf, exit_kind, meta):\n\
+             \x20       meta['calls'] = bytes([meta.get('calls', b'\\x00')[0] + 1])\n\
+             \x20       return exit_kind == 'crash' or b'magic' in buf\n",
+        )
+        .unwrap();
+
+        let mut state = StdState::new(
+            StdRand::with_seed(1337),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            (),
+        );
+        let mut feedback = PythonFeedback::new(path.to_str().unwrap(), "Feedback").unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let mut mgr = NopEventManager {};
+
+        let boring = BytesInput::new(b"abc".to_vec());
+        let magic = BytesInput::new(b"a magic value".to_vec());
+        assert!(!feedback
+            .is_interesting(&mut state, &mut mgr, &boring, &(), &ExitKind::Ok)
+            .unwrap());
+        assert!(feedback
+            .is_interesting(&mut state, &mut mgr, &magic, &(), &ExitKind::Ok)
+            .unwrap());
+        assert!(feedback
+            .is_interesting(&mut state, &mut mgr, &boring, &(), &ExitKind::Crash)
+            .unwrap());
+        let meta = state.metadata().get::<PythonMetadata>().unwrap();
+        assert_eq!(meta.map["calls"], vec![3]);
+    }
+}
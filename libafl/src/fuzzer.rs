@@ -2,7 +2,7 @@
 
 use crate::{
     bolts::current_time,
-    corpus::{Corpus, CorpusScheduler, Testcase},
+    corpus::{Corpus, CorpusId, CorpusScheduler, Testcase},
     events::{Event, EventFirer, EventManager},
     executors::{Executor, ExitKind, HasExecHooksTuple, HasObservers, HasObserversHooks},
     feedbacks::Feedback,
@@ -92,7 +92,7 @@ pub trait IfInteresting<I, S> {
         state: &mut S,
         input: &I,
         is_interesting: bool,
-    ) -> Result<Option<usize>, Error>;
+    ) -> Result<Option<CorpusId>, Error>;
 }
 
 /// Evaluate an input modyfing the state of the fuzzer
@@ -105,7 +105,7 @@ pub trait Evaluator<E, EM, I, S> {
         executor: &mut E,
         manager: &mut EM,
         input: I,
-    ) -> Result<(bool, Option<usize>), Error>;
+    ) -> Result<(bool, Option<CorpusId>), Error>;
 
     /// Runs the input and triggers observers and feedback.
     /// Adds an input, to the corpus even if it's not considered `interesting` by the `feedback`.
@@ -117,7 +117,7 @@ pub trait Evaluator<E, EM, I, S> {
         executor: &mut E,
         manager: &mut EM,
         input: I,
-    ) -> Result<usize, Error>;
+    ) -> Result<CorpusId, Error>;
 }
 
 /// The main fuzzer trait.
@@ -134,7 +134,7 @@ pub trait Fuzzer<E, EM, I, S, ST> {
         executor: &mut E,
         state: &mut S,
         manager: &mut EM,
-    ) -> Result<usize, Error>;
+    ) -> Result<CorpusId, Error>;
 
     /// Fuzz forever (or until stopped)
     fn fuzz_loop(
@@ -143,7 +143,7 @@ pub trait Fuzzer<E, EM, I, S, ST> {
         executor: &mut E,
         state: &mut S,
         manager: &mut EM,
-    ) -> Result<usize, Error> {
+    ) -> Result<CorpusId, Error> {
         let mut last = current_time();
         let stats_timeout = STATS_TIMEOUT_DEFAULT;
         loop {
@@ -165,14 +165,14 @@ pub trait Fuzzer<E, EM, I, S, ST> {
         executor: &mut E,
         manager: &mut EM,
         iters: u64,
-    ) -> Result<usize, Error> {
+    ) -> Result<CorpusId, Error> {
         if iters == 0 {
             return Err(Error::IllegalArgument(
                 "Cannot fuzz for 0 iterations!".to_string(),
             ));
         }
 
-        let mut ret = 0;
+        let mut ret = CorpusId::default();
         let mut last = current_time();
         let stats_timeout = STATS_TIMEOUT_DEFAULT;
 
@@ -314,7 +314,7 @@ where
         state: &mut S,
         input: &I,
         is_interesting: bool,
-    ) -> Result<Option<usize>, Error> {
+    ) -> Result<Option<CorpusId>, Error> {
         if is_interesting {
             let mut testcase = Testcase::new(input.clone());
             self.feedback_mut().append_metadata(state, &mut testcase)?;
@@ -350,7 +350,7 @@ where
         executor: &mut E,
         manager: &mut EM,
         input: I,
-    ) -> Result<(bool, Option<usize>), Error> {
+    ) -> Result<(bool, Option<CorpusId>), Error> {
         let result = self.execute_input(state, executor, manager, &input)?;
         let observers = executor.observers();
 
@@ -411,7 +411,7 @@ where
         executor: &mut E,
         manager: &mut EM,
         input: I,
-    ) -> Result<usize, Error> {
+    ) -> Result<CorpusId, Error> {
         let _ = self.execute_input(state, executor, manager, &input)?;
         let observers = executor.observers();
         // Always consider this to be "interesting"
@@ -505,7 +505,7 @@ where
         executor: &mut E,
         state: &mut S,
         manager: &mut EM,
-    ) -> Result<usize, Error> {
+    ) -> Result<CorpusId, Error> {
         // Init timer for scheduler
         #[cfg(feature = "introspection")]
         state.introspection_stats_mut().start_timer();
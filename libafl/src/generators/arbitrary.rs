@@ -0,0 +1,106 @@
+//! Generates [`ArbitraryInput`]s, i.e., random buffers an [`Arbitrary`] type can be built from.
+
+use alloc::vec::Vec;
+use arbitrary::Arbitrary;
+use core::{cmp::min, marker::PhantomData};
+
+use crate::{
+    bolts::rands::Rand,
+    generators::{Generator, DUMMY_BYTES_MAX},
+    inputs::arbitrary::ArbitraryInput,
+    Error,
+};
+
+/// The number of random buffers tried before giving up on building a valid `T`
+const ARBITRARY_GENERATE_TRIES: usize = 64;
+
+/// Generates random bytes that can be turned into a `T` through [`Arbitrary`]
+#[derive(Clone, Debug)]
+pub struct ArbitraryGenerator<T>
+where
+    T: for<'a> Arbitrary<'a>,
+{
+    max_size: usize,
+    phantom: PhantomData<fn() -> T>,
+}
+
+impl<R, T> Generator<ArbitraryInput<T>, R> for ArbitraryGenerator<T>
+where
+    R: Rand,
+    T: for<'a> Arbitrary<'a>,
+{
+    fn generate(&mut self, rand: &mut R) -> Result<ArbitraryInput<T>, Error> {
+        for _ in 0..ARBITRARY_GENERATE_TRIES {
+            let size = 1 + rand.below(self.max_size as u64);
+            let random_bytes: Vec<u8> = (0..size).map(|_| rand.below(256) as u8).collect();
+            let input = ArbitraryInput::new(random_bytes);
+            if input.value().is_ok() {
+                return Ok(input);
+            }
+        }
+        Err(Error::IllegalState(format!(
+            "Could not generate a valid input in {} tries, try a larger max_size",
+            ARBITRARY_GENERATE_TRIES
+        )))
+    }
+
+    /// Generates up to `DUMMY_BYTES_MAX` non-random dummy bytes (0)
+    fn generate_dummy(&self) -> ArbitraryInput<T> {
+        let size = min(self.max_size, DUMMY_BYTES_MAX);
+        ArbitraryInput::new(vec![0; size])
+    }
+}
+
+impl<T> ArbitraryGenerator<T>
+where
+    T: for<'a> Arbitrary<'a>,
+{
+    /// Creates a new [`ArbitraryGenerator`], generating buffers of up to `max_size` bytes.
+    #[must_use]
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            max_size,
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arbitrary::{Arbitrary, Result, Unstructured};
+
+    use crate::{
+        bolts::rands::StdRand,
+        generators::{arbitrary::ArbitraryGenerator, Generator},
+        inputs::{arbitrary::ArbitraryInput, HasBytesVec},
+    };
+
+    #[derive(Debug, PartialEq)]
+    struct Point {
+        x: u16,
+        y: u16,
+    }
+
+    impl<'a> Arbitrary<'a> for Point {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            Ok(Self {
+                x: u.arbitrary()?,
+                y: u.arbitrary()?,
+            })
+        }
+    }
+
+    #[test]
+    fn test_arbitrary_input() {
+        let input: ArbitraryInput<Point> = ArbitraryInput::from(&[1_u8, 0, 2, 0][..]);
+        assert_eq!(input.value().unwrap(), Point { x: 1, y: 2 });
+
+        let mut rand = StdRand::with_seed(1337);
+        let mut generator = ArbitraryGenerator::<Point>::new(16);
+        for _ in 0..16 {
+            let input = generator.generate(&mut rand).unwrap();
+            assert!(!input.bytes().is_empty());
+            input.value().unwrap();
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! Generators for [`GrammarInput`]s, deriving random trees from a [`Grammar`].
+
+use crate::{
+    bolts::rands::Rand,
+    generators::Generator,
+    inputs::grammar::{Grammar, GrammarInput},
+    Error,
+};
+
+/// The default maximum depth of generated derivation trees
+pub const DEFAULT_GRAMMAR_MAX_DEPTH: usize = 15;
+
+#[derive(Clone, Debug)]
+/// Generates random derivation trees of a [`Grammar`], starting at its start symbol
+pub struct GrammarGenerator<'a> {
+    grammar: &'a Grammar,
+    max_depth: usize,
+}
+
+impl<'a, R> Generator<GrammarInput, R> for GrammarGenerator<'a>
+where
+    R: Rand,
+{
+    fn generate(&mut self, rand: &mut R) -> Result<GrammarInput, Error> {
+        let root = self
+            .grammar
+            .generate(rand, self.grammar.start(), self.max_depth);
+        Ok(GrammarInput::new(root))
+    }
+
+    /// Generates the shallowest derivation of the start symbol
+    fn generate_dummy(&self) -> GrammarInput {
+        GrammarInput::new(self.grammar.generate_minimal(self.grammar.start()))
+    }
+}
+
+impl<'a> GrammarGenerator<'a> {
+    /// Creates a new [`GrammarGenerator`], generating trees up to `max_depth` deep.
+    #[must_use]
+    pub fn new(grammar: &'a Grammar, max_depth: usize) -> Self {
+        Self { grammar, max_depth }
+    }
+}
@@ -1,5 +1,15 @@
 //! Generators may generate bytes or, in general, data, for inputs.
 
+pub mod grammar;
+pub use grammar::GrammarGenerator;
+pub mod text;
+pub use text::{JsonGenerator, NumericTextGenerator, XmlGenerator};
+
+#[cfg(feature = "arbitrary_input")]
+pub mod arbitrary;
+#[cfg(feature = "arbitrary_input")]
+pub use self::arbitrary::ArbitraryGenerator;
+
 use alloc::vec::Vec;
 use core::cmp::min;
 
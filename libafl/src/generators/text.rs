@@ -0,0 +1,353 @@
+//! Generators for syntactically valid text formats: JSON documents, XML documents and numbers.
+//! They give text-parsing targets a good initial corpus, without seed files.
+
+use alloc::vec::Vec;
+
+use crate::{bolts::rands::Rand, generators::Generator, inputs::bytes::BytesInput, Error};
+
+/// The maximum number of chars in generated strings, names and text nodes
+const TEXT_MAX_LEN: u64 = 16;
+
+/// The maximum number of digits in the parts of generated numbers
+const NUMBER_MAX_DIGITS: u64 = 12;
+
+/// Chars used in generated strings and text, besides the ones that need escaping
+const TEXT_CHARS: &[u8] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz _-.,:;!?()[]{}/=+*#@$%";
+
+/// Appends `1..=max` random digits, without leading zeros if `no_leading_zero` is set
+fn push_digits<R>(rand: &mut R, bytes: &mut Vec<u8>, max: u64, no_leading_zero: bool)
+where
+    R: Rand,
+{
+    let count = rand.between(1, max);
+    for i in 0..count {
+        if i == 0 && no_leading_zero && count > 1 {
+            bytes.push(b'1' + rand.below(9) as u8);
+        } else {
+            bytes.push(b'0' + rand.below(10) as u8);
+        }
+    }
+}
+
+/// Appends a random number, in the strict syntax of JSON
+fn push_json_number<R>(rand: &mut R, bytes: &mut Vec<u8>)
+where
+    R: Rand,
+{
+    if rand.below(2) == 0 {
+        bytes.push(b'-');
+    }
+    push_digits(rand, bytes, NUMBER_MAX_DIGITS, true);
+    if rand.below(3) == 0 {
+        bytes.push(b'.');
+        push_digits(rand, bytes, NUMBER_MAX_DIGITS, false);
+    }
+    if rand.below(4) == 0 {
+        bytes.push(*rand.choose(b"eE"));
+        if rand.below(2) == 0 {
+            bytes.push(*rand.choose(b"+-"));
+        }
+        push_digits(rand, bytes, 3, false);
+    }
+}
+
+/// Generates random, syntactically valid, JSON documents
+#[derive(Clone, Debug)]
+pub struct JsonGenerator {
+    max_depth: usize,
+    max_width: usize,
+}
+
+impl JsonGenerator {
+    /// Creates a new [`JsonGenerator`], nesting up to `max_depth` objects and arrays,
+    /// each of them containing up to `max_width` elements.
+    #[must_use]
+    pub fn new(max_depth: usize, max_width: usize) -> Self {
+        Self {
+            max_depth,
+            max_width,
+        }
+    }
+
+    fn push_string<R>(rand: &mut R, bytes: &mut Vec<u8>)
+    where
+        R: Rand,
+    {
+        bytes.push(b'"');
+        for _ in 0..rand.below(TEXT_MAX_LEN + 1) {
+            match rand.below(16) {
+                0 => bytes.extend_from_slice(rand.choose(&[
+                    &b"\\\""[..],
+                    b"\\\\",
+                    b"\\/",
+                    b"\\b",
+                    b"\\f",
+                    b"\\n",
+                    b"\\r",
+                    b"\\t",
+                ])),
+                1 => bytes.extend_from_slice(format!("\\u{:04x}", rand.below(0xd800)).as_bytes()),
+                _ => bytes.push(*rand.choose(TEXT_CHARS)),
+            }
+        }
+        bytes.push(b'"');
+    }
+
+    fn push_value<R>(&self, rand: &mut R, bytes: &mut Vec<u8>, depth: usize)
+    where
+        R: Rand,
+    {
+        let kinds = if depth < self.max_depth { 6 } else { 4 };
+        match rand.below(kinds) {
+            0 => bytes.extend_from_slice(rand.choose(&[&b"null"[..], b"true", b"false"])),
+            1 => push_json_number(rand, bytes),
+            2 | 3 => Self::push_string(rand, bytes),
+            4 => {
+                bytes.push(b'[');
+                for i in 0..rand.below(self.max_width as u64 + 1) {
+                    if i > 0 {
+                        bytes.push(b',');
+                    }
+                    self.push_value(rand, bytes, depth + 1);
+                }
+                bytes.push(b']');
+            }
+            _ => {
+                bytes.push(b'{');
+                for i in 0..rand.below(self.max_width as u64 + 1) {
+                    if i > 0 {
+                        bytes.push(b',');
+                    }
+                    Self::push_string(rand, bytes);
+                    bytes.push(b':');
+                    self.push_value(rand, bytes, depth + 1);
+                }
+                bytes.push(b'}');
+            }
+        }
+    }
+}
+
+impl<R> Generator<BytesInput, R> for JsonGenerator
+where
+    R: Rand,
+{
+    fn generate(&mut self, rand: &mut R) -> Result<BytesInput, Error> {
+        let mut bytes = vec![];
+        self.push_value(rand, &mut bytes, 0);
+        Ok(BytesInput::new(bytes))
+    }
+
+    /// Generates an empty object
+    fn generate_dummy(&self) -> BytesInput {
+        BytesInput::new(b"{}".to_vec())
+    }
+}
+
+/// Generates random, well-formed, XML documents
+#[derive(Clone, Debug)]
+pub struct XmlGenerator {
+    max_depth: usize,
+    max_width: usize,
+}
+
+impl XmlGenerator {
+    /// Creates a new [`XmlGenerator`], nesting up to `max_depth` elements,
+    /// each of them containing up to `max_width` attributes and child nodes.
+    #[must_use]
+    pub fn new(max_depth: usize, max_width: usize) -> Self {
+        Self {
+            max_depth,
+            max_width,
+        }
+    }
+
+    fn push_name<R>(rand: &mut R, bytes: &mut Vec<u8>)
+    where
+        R: Rand,
+    {
+        bytes.push(*rand.choose(b"abcdefghijklmnopqrstuvwxyz"));
+        for _ in 0..rand.below(TEXT_MAX_LEN / 2) {
+            bytes.push(*rand.choose(b"abcdefghijklmnopqrstuvwxyz0123456789_-."));
+        }
+    }
+
+    /// Appends escaped text, for text nodes and attribute values
+    fn push_text<R>(rand: &mut R, bytes: &mut Vec<u8>)
+    where
+        R: Rand,
+    {
+        for _ in 0..rand.between(1, TEXT_MAX_LEN) {
+            if rand.below(16) == 0 {
+                bytes.extend_from_slice(rand.choose(&[
+                    &b"&amp;"[..],
+                    b"&lt;",
+                    b"&gt;",
+                    b"&quot;",
+                    b"&apos;",
+                ]));
+            } else {
+                bytes.push(*rand.choose(TEXT_CHARS));
+            }
+        }
+    }
+
+    fn push_element<R>(&self, rand: &mut R, bytes: &mut Vec<u8>, depth: usize)
+    where
+        R: Rand,
+    {
+        let start = bytes.len();
+        bytes.push(b'<');
+        Self::push_name(rand, bytes);
+        let name = bytes[start + 1..].to_vec();
+
+        // Attribute names must be unique, so we suffix them with their index
+        for i in 0..rand.below(self.max_width as u64 + 1) {
+            bytes.push(b' ');
+            Self::push_name(rand, bytes);
+            bytes.extend_from_slice(format!("_{}=\"", i).as_bytes());
+            Self::push_text(rand, bytes);
+            bytes.push(b'"');
+        }
+
+        let children = if depth < self.max_depth {
+            rand.below(self.max_width as u64 + 1)
+        } else {
+            0
+        };
+        if children == 0 && rand.below(2) == 0 {
+            bytes.extend_from_slice(b"/>");
+            return;
+        }
+        bytes.push(b'>');
+        for _ in 0..children {
+            match rand.below(8) {
+                0 => {
+                    bytes.extend_from_slice(b"<![CDATA[");
+                    Self::push_text(rand, bytes);
+                    bytes.extend_from_slice(b"]]>");
+                }
+                1 => {
+                    // Comments can't contain `--`, so we stick to letters
+                    bytes.extend_from_slice(b"<!-- ");
+                    for _ in 0..rand.below(TEXT_MAX_LEN) {
+                        bytes.push(*rand.choose(b"abcdefghijklmnopqrstuvwxyz "));
+                    }
+                    bytes.extend_from_slice(b" -->");
+                }
+                2 | 3 => Self::push_text(rand, bytes),
+                _ => self.push_element(rand, bytes, depth + 1),
+            }
+        }
+        bytes.extend_from_slice(b"</");
+        bytes.extend_from_slice(&name);
+        bytes.push(b'>');
+    }
+}
+
+impl<R> Generator<BytesInput, R> for XmlGenerator
+where
+    R: Rand,
+{
+    fn generate(&mut self, rand: &mut R) -> Result<BytesInput, Error> {
+        let mut bytes = vec![];
+        if rand.below(2) == 0 {
+            bytes.extend_from_slice(b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        }
+        self.push_element(rand, &mut bytes, 0);
+        Ok(BytesInput::new(bytes))
+    }
+
+    /// Generates a single empty element
+    fn generate_dummy(&self) -> BytesInput {
+        BytesInput::new(b"<a/>".to_vec())
+    }
+}
+
+/// Generates random numbers as ASCII text: integers, decimals and floats in scientific notation
+#[derive(Clone, Debug)]
+pub struct NumericTextGenerator {
+    max_digits: usize,
+}
+
+impl NumericTextGenerator {
+    /// Creates a new [`NumericTextGenerator`], generating up to `max_digits` digits for the integer and the fractional part.
+    #[must_use]
+    pub fn new(max_digits: usize) -> Self {
+        Self { max_digits }
+    }
+}
+
+impl<R> Generator<BytesInput, R> for NumericTextGenerator
+where
+    R: Rand,
+{
+    fn generate(&mut self, rand: &mut R) -> Result<BytesInput, Error> {
+        let max_digits = self.max_digits.max(1) as u64;
+        let mut bytes = vec![];
+        match rand.below(3) {
+            0 => bytes.push(b'-'),
+            1 => bytes.push(b'+'),
+            _ => (),
+        }
+        let no_leading_zero = rand.below(4) != 0;
+        push_digits(rand, &mut bytes, max_digits, no_leading_zero);
+        if rand.below(2) == 0 {
+            bytes.push(b'.');
+            push_digits(rand, &mut bytes, max_digits, false);
+        }
+        if rand.below(4) == 0 {
+            bytes.push(*rand.choose(b"eE"));
+            if rand.below(2) == 0 {
+                bytes.push(*rand.choose(b"+-"));
+            }
+            push_digits(rand, &mut bytes, 3, false);
+        }
+        Ok(BytesInput::new(bytes))
+    }
+
+    /// Generates `0`
+    fn generate_dummy(&self) -> BytesInput {
+        BytesInput::new(b"0".to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bolts::rands::StdRand,
+        generators::{Generator, JsonGenerator, NumericTextGenerator, XmlGenerator},
+        inputs::HasBytesVec,
+    };
+
+    #[test]
+    fn test_text_generators() {
+        let mut rand = StdRand::with_seed(1337);
+
+        let mut json = JsonGenerator::new(4, 4);
+        for _ in 0..64 {
+            let input = json.generate(&mut rand).unwrap();
+            serde_json::from_slice::<serde_json::Value>(input.bytes()).unwrap();
+        }
+
+        let mut xml = XmlGenerator::new(4, 4);
+        for _ in 0..64 {
+            let input = xml.generate(&mut rand).unwrap();
+            let text = core::str::from_utf8(input.bytes()).unwrap();
+            // Every element we open must be closed
+            let opened = text.matches('<').count() - text.matches("</").count();
+            let closed = text.matches("</").count()
+                + text.matches("/>").count()
+                + text.matches("?>").count();
+            assert_eq!(opened - text.matches("<!").count(), closed);
+        }
+
+        let mut numbers = NumericTextGenerator::new(8);
+        for _ in 0..64 {
+            let input = numbers.generate(&mut rand).unwrap();
+            let text = core::str::from_utf8(input.bytes()).unwrap();
+            text.parse::<f64>().unwrap();
+        }
+    }
+}
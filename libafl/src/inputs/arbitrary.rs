@@ -0,0 +1,185 @@
+//! The `ArbitraryInput` bridges LibAFL and the [`arbitrary`](https://docs.rs/arbitrary) crate.
+//! It is stored, mutated and written to disk as raw bytes, like a [`crate::inputs::BytesInput`],
+//! while harnesses get a typed view through [`ArbitraryInput::value`], just like `cargo-fuzz` targets do.
+
+use ahash::AHasher;
+use alloc::{string::String, vec::Vec};
+use arbitrary::{Arbitrary, Unstructured};
+use core::{fmt, hash::Hasher, marker::PhantomData};
+use serde::{Deserialize, Serialize};
+use std::{fs::File, io::Write, path::Path};
+
+#[cfg(feature = "llmp_compression")]
+use crate::{
+    bolts::compress::GzipCompressor,
+    inputs::{read_file_compressed, write_file_compressed},
+};
+use crate::{
+    bolts::ownedref::OwnedSlice,
+    inputs::{read_file, HasBytesVec, HasLen, HasTargetBytes, Input},
+    Error,
+};
+#[cfg(feature = "llmp_compression")]
+use std::path::PathBuf;
+
+/// An input made of the raw bytes consumed by [`Arbitrary`] to build a `T`
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ArbitraryInput<T>
+where
+    T: for<'a> Arbitrary<'a>,
+{
+    bytes: Vec<u8>,
+    phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> Input for ArbitraryInput<T>
+where
+    T: for<'a> Arbitrary<'a>,
+{
+    /// Write the raw bytes to the file, so that the corpus stays compatible with `cargo-fuzz`
+    fn to_file<P>(&self, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut file = File::create(path)?;
+        file.write_all(&self.bytes)?;
+        Ok(())
+    }
+
+    /// Load the raw bytes from a file, for example from a `cargo-fuzz` corpus
+    fn from_file<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self::new(read_file(path)?))
+    }
+
+    /// Write the raw bytes to the file, gzip-compressed if they are at least as large as the threshold of the `compressor`
+    #[cfg(feature = "llmp_compression")]
+    fn to_file_compressed<P>(&self, path: P, compressor: &GzipCompressor) -> Result<PathBuf, Error>
+    where
+        P: AsRef<Path>,
+    {
+        write_file_compressed(path, &self.bytes, compressor)
+    }
+
+    /// Load the raw bytes from a file written gzip-compressed by [`Input::to_file_compressed`]
+    #[cfg(feature = "llmp_compression")]
+    fn from_file_compressed<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self::new(read_file_compressed(path)?))
+    }
+
+    /// Generate a name for this input
+    fn generate_name(&self, _idx: usize) -> String {
+        let mut hasher = AHasher::new_with_keys(0, 0);
+        hasher.write(&self.bytes);
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+impl<T> Clone for ArbitraryInput<T>
+where
+    T: for<'a> Arbitrary<'a>,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.bytes.clone())
+    }
+}
+
+impl<T> fmt::Debug for ArbitraryInput<T>
+where
+    T: for<'a> Arbitrary<'a>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArbitraryInput")
+            .field("bytes", &self.bytes)
+            .finish()
+    }
+}
+
+impl<T> Default for ArbitraryInput<T>
+where
+    T: for<'a> Arbitrary<'a>,
+{
+    fn default() -> Self {
+        Self::new(vec![])
+    }
+}
+
+impl<T> HasBytesVec for ArbitraryInput<T>
+where
+    T: for<'a> Arbitrary<'a>,
+{
+    #[inline]
+    fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    #[inline]
+    fn bytes_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.bytes
+    }
+}
+
+impl<T> HasTargetBytes for ArbitraryInput<T>
+where
+    T: for<'a> Arbitrary<'a>,
+{
+    #[inline]
+    fn target_bytes(&self) -> OwnedSlice<u8> {
+        OwnedSlice::Ref(&self.bytes)
+    }
+}
+
+impl<T> HasLen for ArbitraryInput<T>
+where
+    T: for<'a> Arbitrary<'a>,
+{
+    #[inline]
+    fn len(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
+impl<T> From<Vec<u8>> for ArbitraryInput<T>
+where
+    T: for<'a> Arbitrary<'a>,
+{
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::new(bytes)
+    }
+}
+
+impl<T> From<&[u8]> for ArbitraryInput<T>
+where
+    T: for<'a> Arbitrary<'a>,
+{
+    fn from(bytes: &[u8]) -> Self {
+        Self::new(bytes.to_vec())
+    }
+}
+
+impl<T> ArbitraryInput<T>
+where
+    T: for<'a> Arbitrary<'a>,
+{
+    /// Creates a new input from the given raw bytes
+    #[must_use]
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self {
+            bytes,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Builds the typed value from the raw bytes, the same way `cargo-fuzz`'s `fuzz_target!` does.
+    /// Fails if the bytes are not enough (or not valid) to build a `T`.
+    pub fn value(&self) -> Result<T, Error> {
+        T::arbitrary_take_rest(Unstructured::new(&self.bytes))
+            .map_err(|err| Error::IllegalArgument(format!("Arbitrary failed: {}", err)))
+    }
+}
@@ -8,18 +8,21 @@ use alloc::{borrow::ToOwned, rc::Rc, string::String, vec::Vec};
 use core::{cell::RefCell, convert::From};
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "std")]
-use std::{
-    fs::File,
-    io::{Read, Write},
-    path::Path,
-};
+use std::{fs::File, io::Write, path::Path};
 
-#[cfg(feature = "std")]
-use crate::Error;
+#[cfg(all(feature = "std", feature = "llmp_compression"))]
+use crate::{
+    bolts::compress::GzipCompressor,
+    inputs::{read_file_compressed, write_file_compressed},
+};
 use crate::{
     bolts::ownedref::OwnedSlice,
     inputs::{HasBytesVec, HasLen, HasTargetBytes, Input},
 };
+#[cfg(feature = "std")]
+use crate::{inputs::read_file, Error};
+#[cfg(all(feature = "std", feature = "llmp_compression"))]
+use std::path::PathBuf;
 
 /// A bytes input is the basic input
 #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
@@ -46,10 +49,25 @@ impl Input for BytesInput {
     where
         P: AsRef<Path>,
     {
-        let mut file = File::open(path)?;
-        let mut bytes: Vec<u8> = vec![];
-        file.read_to_end(&mut bytes)?;
-        Ok(BytesInput::new(bytes))
+        Ok(BytesInput::new(read_file(path)?))
+    }
+
+    /// Write the raw bytes to the file, gzip-compressed if they are at least as large as the threshold of the `compressor`
+    #[cfg(all(feature = "std", feature = "llmp_compression"))]
+    fn to_file_compressed<P>(&self, path: P, compressor: &GzipCompressor) -> Result<PathBuf, Error>
+    where
+        P: AsRef<Path>,
+    {
+        write_file_compressed(path, &self.bytes, compressor)
+    }
+
+    /// Load the raw bytes from a file written gzip-compressed by [`Input::to_file_compressed`]
+    #[cfg(all(feature = "std", feature = "llmp_compression"))]
+    fn from_file_compressed<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(BytesInput::new(read_file_compressed(path)?))
     }
 
     /// Generate a name for this input
@@ -126,4 +144,49 @@ mod tests {
         assert_eq!(rand.between(10, 10), 10);
         assert!(rand.between(11, 20) > 10);
     }
+
+    #[cfg(all(feature = "std", feature = "llmp_compression"))]
+    #[test]
+    fn test_compressed_file() {
+        use crate::{
+            bolts::compress::GzipCompressor,
+            inputs::{BytesInput, HasBytesVec, Input, COMPRESSED_SUFFIX},
+        };
+
+        let dir = std::env::temp_dir();
+        let large_path = dir.join(format!(
+            "libafl_test_compressed_input_{}",
+            std::process::id()
+        ));
+        let small_path = dir.join(format!("libafl_test_plain_input_{}", std::process::id()));
+        let compressor = GzipCompressor::new(64);
+
+        // Large inputs get compressed, to a file with the compressed suffix
+        let large = BytesInput::new(vec![b'A'; 4096]);
+        let compressed = large.to_file_compressed(&large_path, &compressor).unwrap();
+        assert!(compressed.to_str().unwrap().ends_with(COMPRESSED_SUFFIX));
+        assert!(std::fs::metadata(&compressed).unwrap().len() < 4096);
+        assert_eq!(
+            BytesInput::from_file_compressed(&compressed).unwrap(),
+            large
+        );
+
+        // Small ones stay as they are
+        let small = BytesInput::new(vec![1, 2, 3]);
+        let plain = small.to_file_compressed(&small_path, &compressor).unwrap();
+        assert_eq!(plain, small_path);
+        assert_eq!(BytesInput::from_file(&plain).unwrap(), small);
+
+        // Seeds that happen to be gzip files are loaded byte-exact
+        let gzip_seed = std::fs::read(&compressed).unwrap();
+        assert!(GzipCompressor::is_gzip(&gzip_seed));
+        std::fs::write(&small_path, &gzip_seed).unwrap();
+        assert_eq!(
+            BytesInput::from_file(&small_path).unwrap().bytes(),
+            &gzip_seed[..]
+        );
+
+        std::fs::remove_file(compressed).unwrap();
+        std::fs::remove_file(small_path).unwrap();
+    }
 }
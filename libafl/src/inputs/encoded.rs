@@ -0,0 +1,309 @@
+//! The `EncodedInput` is a compact sequence of token codes.
+//! Bytes are turned into codes by an [`InputEncoder`], using a [`Tokenizer`], and back into bytes by an [`InputDecoder`].
+
+use ahash::AHasher;
+use alloc::{string::String, vec::Vec};
+use core::hash::Hasher;
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    inputs::{HasLen, Input},
+    mutators::Tokens,
+    Error,
+};
+
+/// Splits bytes into tokens. Concatenating the tokens must give back the original bytes.
+pub trait Tokenizer {
+    /// Tokenize the given bytes
+    fn tokenize(&self, bytes: &[u8]) -> Result<Vec<Vec<u8>>, Error>;
+}
+
+/// Encodes bytes into an [`EncodedInput`]
+pub trait InputEncoder<T>
+where
+    T: Tokenizer,
+{
+    /// Encode bytes, using the given tokenizer
+    fn encode(&mut self, bytes: &[u8], tokenizer: &T) -> Result<EncodedInput, Error>;
+}
+
+/// Decodes an [`EncodedInput`] back to bytes
+pub trait InputDecoder {
+    /// Decode the input, appending the bytes to `bytes`
+    fn decode(&self, input: &EncodedInput, bytes: &mut Vec<u8>) -> Result<(), Error>;
+}
+
+/// The class of a byte, for the [`NaiveTokenizer`]
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ByteClass {
+    Word,
+    Space,
+    Other,
+}
+
+impl ByteClass {
+    fn of(b: u8) -> Self {
+        if b.is_ascii_alphanumeric() || b == b'_' {
+            Self::Word
+        } else if b.is_ascii_whitespace() {
+            Self::Space
+        } else {
+            Self::Other
+        }
+    }
+}
+
+/// Splits bytes into words (alphanumeric and `_`), runs of whitespace, and single other bytes
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NaiveTokenizer {}
+
+impl NaiveTokenizer {
+    /// Creates a new [`NaiveTokenizer`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// The length of the naive token at the start of `bytes`, at least 1 if `bytes` is not empty
+    fn next_len(bytes: &[u8]) -> usize {
+        match bytes.first() {
+            None => 0,
+            Some(first) => match ByteClass::of(*first) {
+                ByteClass::Other => 1,
+                class => bytes
+                    .iter()
+                    .position(|b| ByteClass::of(*b) != class)
+                    .unwrap_or(bytes.len()),
+            },
+        }
+    }
+}
+
+impl Tokenizer for NaiveTokenizer {
+    fn tokenize(&self, bytes: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+        let mut tokens = vec![];
+        let mut off = 0;
+        while off < bytes.len() {
+            let len = Self::next_len(&bytes[off..]);
+            tokens.push(bytes[off..off + len].to_vec());
+            off += len;
+        }
+        Ok(tokens)
+    }
+}
+
+/// Splits bytes greedily into the longest matching dictionary tokens,
+/// falling back to the [`NaiveTokenizer`] where no dictionary token matches
+#[derive(Clone, Debug, Default)]
+pub struct DictionaryTokenizer {
+    /// The dictionary, longest tokens first
+    tokens: Vec<Vec<u8>>,
+}
+
+impl DictionaryTokenizer {
+    /// Creates a new [`DictionaryTokenizer`] from a list of tokens
+    #[must_use]
+    pub fn new(mut tokens: Vec<Vec<u8>>) -> Self {
+        tokens.retain(|t| !t.is_empty());
+        tokens.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        tokens.dedup();
+        Self { tokens }
+    }
+}
+
+impl From<&Tokens> for DictionaryTokenizer {
+    fn from(tokens: &Tokens) -> Self {
+        Self::new(tokens.tokens().to_vec())
+    }
+}
+
+impl Tokenizer for DictionaryTokenizer {
+    fn tokenize(&self, bytes: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+        let mut tokens = vec![];
+        let mut off = 0;
+        while off < bytes.len() {
+            let rest = &bytes[off..];
+            let len = self
+                .tokens
+                .iter()
+                .find(|t| rest.starts_with(t))
+                .map_or_else(|| NaiveTokenizer::next_len(rest), Vec::len);
+            tokens.push(rest[..len].to_vec());
+            off += len;
+        }
+        Ok(tokens)
+    }
+}
+
+/// Maps tokens to codes and back. Unknown tokens get a new code while encoding, so the dictionary is learned from the inputs.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TokenEncoderDecoder {
+    codes: HashMap<Vec<u8>, u32>,
+    tokens: Vec<Vec<u8>>,
+}
+
+impl<T> InputEncoder<T> for TokenEncoderDecoder
+where
+    T: Tokenizer,
+{
+    fn encode(&mut self, bytes: &[u8], tokenizer: &T) -> Result<EncodedInput, Error> {
+        let codes = tokenizer
+            .tokenize(bytes)?
+            .into_iter()
+            .map(|token| self.add_token(token))
+            .collect();
+        Ok(EncodedInput::new(codes))
+    }
+}
+
+impl InputDecoder for TokenEncoderDecoder {
+    fn decode(&self, input: &EncodedInput, bytes: &mut Vec<u8>) -> Result<(), Error> {
+        for code in input.codes() {
+            let token = self
+                .token(*code)
+                .ok_or_else(|| Error::KeyNotFound(format!("Unknown token code {}", code)))?;
+            bytes.extend_from_slice(token);
+        }
+        Ok(())
+    }
+}
+
+impl From<&Tokens> for TokenEncoderDecoder {
+    /// Creates a codec knowing all the given tokens, with codes in the order of the tokens
+    fn from(tokens: &Tokens) -> Self {
+        let mut codec = Self::new();
+        for token in tokens.tokens() {
+            codec.add_token(token.clone());
+        }
+        codec
+    }
+}
+
+impl TokenEncoderDecoder {
+    /// Creates a new, empty, [`TokenEncoderDecoder`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the code of the given token, adding it to the dictionary if needed
+    pub fn add_token(&mut self, token: Vec<u8>) -> u32 {
+        if let Some(code) = self.codes.get(&token) {
+            return *code;
+        }
+        let code = self.tokens.len() as u32;
+        self.tokens.push(token.clone());
+        self.codes.insert(token, code);
+        code
+    }
+
+    /// The number of known tokens. Codes are always below this number.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// Returns `true` if no token is known yet
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Gets the token with the given code
+    #[must_use]
+    pub fn token(&self, code: u32) -> Option<&[u8]> {
+        self.tokens.get(code as usize).map(Vec::as_slice)
+    }
+
+    /// Gets the code of the given token, if known
+    #[must_use]
+    pub fn code(&self, token: &[u8]) -> Option<u32> {
+        self.codes.get(token).copied()
+    }
+}
+
+/// An input made of token codes
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct EncodedInput {
+    codes: Vec<u32>,
+}
+
+impl Input for EncodedInput {
+    /// Generate a name for this input
+    fn generate_name(&self, _idx: usize) -> String {
+        let mut hasher = AHasher::new_with_keys(0, 0);
+        for code in &self.codes {
+            hasher.write(&code.to_le_bytes());
+        }
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+impl HasLen for EncodedInput {
+    /// The number of codes
+    #[inline]
+    fn len(&self) -> usize {
+        self.codes.len()
+    }
+}
+
+impl From<Vec<u32>> for EncodedInput {
+    fn from(codes: Vec<u32>) -> Self {
+        Self::new(codes)
+    }
+}
+
+impl EncodedInput {
+    /// Creates a new encoded input from a list of codes
+    #[must_use]
+    pub fn new(codes: Vec<u32>) -> Self {
+        Self { codes }
+    }
+
+    /// The codes of this input
+    #[must_use]
+    pub fn codes(&self) -> &[u32] {
+        &self.codes
+    }
+
+    /// The codes of this input (mut)
+    pub fn codes_mut(&mut self) -> &mut Vec<u32> {
+        &mut self.codes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        inputs::encoded::{
+            DictionaryTokenizer, InputDecoder, InputEncoder, NaiveTokenizer, TokenEncoderDecoder,
+        },
+        mutators::Tokens,
+    };
+
+    #[test]
+    fn test_encoding_round_trip() {
+        let mut codec = TokenEncoderDecoder::new();
+        let tokenizer = NaiveTokenizer::new();
+        let bytes = b"if (a_1 >= 42) {\n  a_1 = a_1 + 1;\n}";
+        let input = codec.encode(bytes, &tokenizer).unwrap();
+        assert_eq!(input.codes()[..4], [0, 1, 2, 3]);
+        assert_eq!(input.codes()[3], input.codes()[13]);
+
+        let mut decoded = vec![];
+        codec.decode(&input, &mut decoded).unwrap();
+        assert_eq!(&decoded[..], &bytes[..]);
+
+        // A dictionary keeps multi-byte operators together
+        let tokens = Tokens::new(vec![b">=".to_vec()]);
+        let mut codec = TokenEncoderDecoder::from(&tokens);
+        let input = codec
+            .encode(b"a>=b", &DictionaryTokenizer::from(&tokens))
+            .unwrap();
+        assert_eq!(input.codes(), [1, 0, 2]);
+        decoded.clear();
+        codec.decode(&input, &mut decoded).unwrap();
+        assert_eq!(decoded, b"a>=b");
+    }
+}
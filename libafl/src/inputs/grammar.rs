@@ -0,0 +1,545 @@
+//! The `GrammarInput` is a derivation tree over a context-free [`Grammar`], in the spirit of Nautilus.
+//! The tree only stores rule ids, the bytes for the target are produced by [`GrammarInput::unparse`].
+
+use ahash::AHasher;
+use alloc::{
+    borrow::ToOwned,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::hash::Hasher;
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+use std::{fs, path::Path};
+
+use crate::{
+    bolts::rands::Rand,
+    inputs::{HasLen, Input},
+    Error,
+};
+
+/// A symbol on the right-hand side of a [`GrammarRule`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum GrammarSymbol {
+    /// Raw bytes, emitted as they are
+    Terminal(Vec<u8>),
+    /// The id of a nonterminal, expanded by one of its rules
+    NonTerminal(usize),
+}
+
+/// A production rule of a [`Grammar`]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GrammarRule {
+    nonterm: usize,
+    symbols: Vec<GrammarSymbol>,
+}
+
+impl GrammarRule {
+    /// The nonterminal this rule expands
+    #[must_use]
+    pub fn nonterm(&self) -> usize {
+        self.nonterm
+    }
+
+    /// The symbols this rule expands to
+    #[must_use]
+    pub fn symbols(&self) -> &[GrammarSymbol] {
+        &self.symbols
+    }
+
+    /// The nonterminals referenced by this rule, in order
+    pub fn nonterm_children(&self) -> impl Iterator<Item = usize> + '_ {
+        self.symbols.iter().filter_map(|s| match s {
+            GrammarSymbol::NonTerminal(nt) => Some(*nt),
+            GrammarSymbol::Terminal(_) => None,
+        })
+    }
+}
+
+/// A context-free grammar.
+/// Rules are written Nautilus-style: the right-hand side is a byte string in which
+/// `{NAME}` references the nonterminal `NAME`, e.g. `("EXPR", "{EXPR} + {NUM}")`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Grammar {
+    nonterms: Vec<String>,
+    nonterm_ids: HashMap<String, usize>,
+    rules: Vec<GrammarRule>,
+    rules_of: Vec<Vec<usize>>,
+    rule_min_depth: Vec<usize>,
+    nonterm_min_depth: Vec<usize>,
+    start: Option<usize>,
+}
+
+impl Grammar {
+    /// Creates a new, empty, [`Grammar`]. Add rules using [`Grammar::add_rule`], then call [`Grammar::finalize`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates and finalizes a [`Grammar`] from a list of `(nonterminal, format)` rules.
+    /// The start symbol is `START`, if present, else the nonterminal of the first rule.
+    pub fn from_rules<N, F>(rules: &[(N, F)]) -> Result<Self, Error>
+    where
+        N: AsRef<str>,
+        F: AsRef<[u8]>,
+    {
+        let mut grammar = Self::new();
+        for (nonterm, format) in rules {
+            grammar.add_rule(nonterm.as_ref(), format.as_ref());
+        }
+        grammar.finalize()?;
+        Ok(grammar)
+    }
+
+    /// Loads a [`Grammar`] from a JSON file containing a list of `["NONTERM", "format"]` pairs.
+    #[cfg(feature = "std")]
+    pub fn from_file<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let rules: Vec<(String, String)> = serde_json::from_str(&fs::read_to_string(path)?)?;
+        Self::from_rules(&rules)
+    }
+
+    fn nonterm_id(&mut self, name: &str) -> usize {
+        if let Some(id) = self.nonterm_ids.get(name) {
+            return *id;
+        }
+        let id = self.nonterms.len();
+        self.nonterms.push(name.to_owned());
+        self.nonterm_ids.insert(name.to_owned(), id);
+        self.rules_of.push(vec![]);
+        id
+    }
+
+    /// Adds a rule expanding `nonterm` to `format`, returning the id of the rule.
+    /// Every `{NAME}` in `format` is a reference to the nonterminal `NAME`.
+    pub fn add_rule(&mut self, nonterm: &str, format: &[u8]) -> usize {
+        let nt = self.nonterm_id(nonterm);
+        let mut symbols = vec![];
+        let mut terminal = vec![];
+        let mut i = 0;
+        while i < format.len() {
+            if format[i] == b'{' {
+                let name_len = format[i + 1..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_alphanumeric() || **c == b'_')
+                    .count();
+                let end = i + 1 + name_len;
+                if name_len > 0 && end < format.len() && format[end] == b'}' {
+                    if !terminal.is_empty() {
+                        symbols.push(GrammarSymbol::Terminal(terminal));
+                        terminal = vec![];
+                    }
+                    let name = String::from_utf8_lossy(&format[i + 1..end]).to_string();
+                    symbols.push(GrammarSymbol::NonTerminal(self.nonterm_id(&name)));
+                    i = end + 1;
+                    continue;
+                }
+            }
+            terminal.push(format[i]);
+            i += 1;
+        }
+        if !terminal.is_empty() {
+            symbols.push(GrammarSymbol::Terminal(terminal));
+        }
+
+        let id = self.rules.len();
+        self.rules.push(GrammarRule {
+            nonterm: nt,
+            symbols,
+        });
+        self.rules_of[nt].push(id);
+        id
+    }
+
+    /// Computes the minimal derivation depth of each rule and sets the start symbol.
+    /// Fails if a nonterminal has no rules or can never derive a terminal string.
+    pub fn finalize(&mut self) -> Result<(), Error> {
+        if self.rules.is_empty() {
+            return Err(Error::Empty("Grammar rules".to_owned()));
+        }
+        if let Some((nt, _)) = self.rules_of.iter().enumerate().find(|(_, r)| r.is_empty()) {
+            return Err(Error::IllegalArgument(format!(
+                "Nonterminal {} has no rules",
+                self.nonterms[nt]
+            )));
+        }
+
+        self.rule_min_depth = vec![usize::MAX; self.rules.len()];
+        self.nonterm_min_depth = vec![usize::MAX; self.nonterms.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (id, rule) in self.rules.iter().enumerate() {
+                let depth = rule
+                    .nonterm_children()
+                    .map(|nt| self.nonterm_min_depth[nt])
+                    .max()
+                    .unwrap_or(0)
+                    .saturating_add(1);
+                if depth < self.rule_min_depth[id] {
+                    self.rule_min_depth[id] = depth;
+                    changed = true;
+                }
+                if depth < self.nonterm_min_depth[rule.nonterm] {
+                    self.nonterm_min_depth[rule.nonterm] = depth;
+                    changed = true;
+                }
+            }
+        }
+        if let Some(nt) = self.nonterm_min_depth.iter().position(|d| *d == usize::MAX) {
+            return Err(Error::IllegalArgument(format!(
+                "Nonterminal {} never terminates",
+                self.nonterms[nt]
+            )));
+        }
+
+        self.start = Some(
+            self.nonterm_ids
+                .get("START")
+                .copied()
+                .unwrap_or(self.rules[0].nonterm),
+        );
+        Ok(())
+    }
+
+    /// The start nonterminal
+    #[must_use]
+    pub fn start(&self) -> usize {
+        self.start.expect("Grammar::finalize was not called")
+    }
+
+    /// The name of a nonterminal
+    #[must_use]
+    pub fn nonterm_name(&self, nonterm: usize) -> &str {
+        &self.nonterms[nonterm]
+    }
+
+    /// Gets a rule by id
+    #[must_use]
+    pub fn rule(&self, rule: usize) -> &GrammarRule {
+        &self.rules[rule]
+    }
+
+    /// All rules of this grammar
+    #[must_use]
+    pub fn rules(&self) -> &[GrammarRule] {
+        &self.rules
+    }
+
+    /// The nonterminal expanded by the root of the given node
+    #[must_use]
+    pub fn nonterm_of(&self, node: &GrammarNode) -> usize {
+        self.rules[node.rule].nonterm
+    }
+
+    /// Generates a random derivation of `nonterm`, trying not to exceed `max_depth`.
+    /// If no derivation fits, the shallowest possible one is picked.
+    pub fn generate<R>(&self, rand: &mut R, nonterm: usize, max_depth: usize) -> GrammarNode
+    where
+        R: Rand,
+    {
+        let rules = &self.rules_of[nonterm];
+        let fitting: Vec<usize> = rules
+            .iter()
+            .copied()
+            .filter(|r| self.rule_min_depth[*r] <= max_depth)
+            .collect();
+        let rule = if fitting.is_empty() {
+            self.shallowest_rule(nonterm)
+        } else {
+            *rand.choose(&fitting)
+        };
+        let children = self.rules[rule]
+            .nonterm_children()
+            .map(|nt| self.generate(rand, nt, max_depth.saturating_sub(1)))
+            .collect();
+        GrammarNode { rule, children }
+    }
+
+    /// Generates the shallowest derivation of `nonterm`, without any randomness
+    #[must_use]
+    pub fn generate_minimal(&self, nonterm: usize) -> GrammarNode {
+        let rule = self.shallowest_rule(nonterm);
+        let children = self.rules[rule]
+            .nonterm_children()
+            .map(|nt| self.generate_minimal(nt))
+            .collect();
+        GrammarNode { rule, children }
+    }
+
+    fn shallowest_rule(&self, nonterm: usize) -> usize {
+        *self.rules_of[nonterm]
+            .iter()
+            .min_by_key(|r| self.rule_min_depth[**r])
+            .unwrap()
+    }
+}
+
+/// A node of a derivation tree.
+/// The children are the derivations of the nonterminals of `rule`, in order.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GrammarNode {
+    rule: usize,
+    children: Vec<GrammarNode>,
+}
+
+impl GrammarNode {
+    /// Creates a new node, the `children` must match the nonterminals of `rule`
+    #[must_use]
+    pub fn new(rule: usize, children: Vec<GrammarNode>) -> Self {
+        Self { rule, children }
+    }
+
+    /// The rule applied at this node
+    #[must_use]
+    pub fn rule(&self) -> usize {
+        self.rule
+    }
+
+    /// The subtrees of this node
+    #[must_use]
+    pub fn children(&self) -> &[GrammarNode] {
+        &self.children
+    }
+
+    /// The number of nodes in this subtree
+    #[must_use]
+    pub fn size(&self) -> usize {
+        1 + self.children.iter().map(GrammarNode::size).sum::<usize>()
+    }
+
+    /// The depth of this subtree, a leaf has depth 1
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        1 + self
+            .children
+            .iter()
+            .map(GrammarNode::depth)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Gets the node at the given pre-order index
+    #[must_use]
+    pub fn get(&self, mut idx: usize) -> Option<&GrammarNode> {
+        let mut node = self;
+        loop {
+            if idx == 0 {
+                return Some(node);
+            }
+            idx -= 1;
+            let mut next = None;
+            for child in &node.children {
+                let size = child.size();
+                if idx < size {
+                    next = Some(child);
+                    break;
+                }
+                idx -= size;
+            }
+            node = next?;
+        }
+    }
+
+    /// Gets the node at the given pre-order index (mut)
+    pub fn get_mut(&mut self, mut idx: usize) -> Option<&mut GrammarNode> {
+        let mut node = self;
+        loop {
+            if idx == 0 {
+                return Some(node);
+            }
+            idx -= 1;
+            let mut next = None;
+            for child in &mut node.children {
+                let size = child.size();
+                if idx < size {
+                    next = Some(child);
+                    break;
+                }
+                idx -= size;
+            }
+            node = next?;
+        }
+    }
+
+    /// The depth of the node at the given pre-order index, the root has depth 0
+    #[must_use]
+    pub fn depth_of(&self, mut idx: usize) -> Option<usize> {
+        let mut node = self;
+        let mut depth = 0;
+        loop {
+            if idx == 0 {
+                return Some(depth);
+            }
+            idx -= 1;
+            depth += 1;
+            let mut next = None;
+            for child in &node.children {
+                let size = child.size();
+                if idx < size {
+                    next = Some(child);
+                    break;
+                }
+                idx -= size;
+            }
+            node = next?;
+        }
+    }
+
+    /// Visits all nodes in pre-order
+    pub fn for_each<F>(&self, f: &mut F)
+    where
+        F: FnMut(&GrammarNode),
+    {
+        f(self);
+        for child in &self.children {
+            child.for_each(f);
+        }
+    }
+
+    /// Appends the bytes derived by this subtree to `bytes`
+    pub fn unparse(&self, grammar: &Grammar, bytes: &mut Vec<u8>) {
+        let mut children = self.children.iter();
+        for symbol in grammar.rule(self.rule).symbols() {
+            match symbol {
+                GrammarSymbol::Terminal(t) => bytes.extend_from_slice(t),
+                GrammarSymbol::NonTerminal(_) => {
+                    if let Some(child) = children.next() {
+                        child.unparse(grammar, bytes);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// An input made of a derivation tree over a [`Grammar`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct GrammarInput {
+    root: GrammarNode,
+}
+
+impl Input for GrammarInput {
+    /// Generate a name for this input
+    fn generate_name(&self, _idx: usize) -> String {
+        let mut hasher = AHasher::new_with_keys(0, 0);
+        self.root.for_each(&mut |n| hasher.write_usize(n.rule));
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+impl HasLen for GrammarInput {
+    /// The number of nodes in the tree
+    #[inline]
+    fn len(&self) -> usize {
+        self.root.size()
+    }
+}
+
+impl From<GrammarNode> for GrammarInput {
+    fn from(root: GrammarNode) -> Self {
+        Self::new(root)
+    }
+}
+
+impl GrammarInput {
+    /// Creates a new grammar input from a derivation tree
+    #[must_use]
+    pub fn new(root: GrammarNode) -> Self {
+        Self { root }
+    }
+
+    /// The root of the derivation tree
+    #[must_use]
+    pub fn root(&self) -> &GrammarNode {
+        &self.root
+    }
+
+    /// The root of the derivation tree (mut)
+    pub fn root_mut(&mut self) -> &mut GrammarNode {
+        &mut self.root
+    }
+
+    /// Writes the bytes derived by this input to `bytes`, clearing it first.
+    /// Harnesses call this to get the bytes for the target.
+    pub fn unparse(&self, grammar: &Grammar, bytes: &mut Vec<u8>) {
+        bytes.clear();
+        self.root.unparse(grammar, bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bolts::rands::StdRand,
+        inputs::{
+            grammar::{Grammar, GrammarInput, GrammarSymbol},
+            HasLen,
+        },
+    };
+
+    /// Parses `bytes` back, checking that `nonterm` derives them
+    fn derives(grammar: &Grammar, nonterm: usize, bytes: &[u8]) -> bool {
+        grammar
+            .rules()
+            .iter()
+            .filter(|rule| rule.nonterm() == nonterm)
+            .any(|rule| matches(grammar, rule.symbols(), bytes))
+    }
+
+    fn matches(grammar: &Grammar, symbols: &[GrammarSymbol], bytes: &[u8]) -> bool {
+        match symbols.split_first() {
+            None => bytes.is_empty(),
+            Some((GrammarSymbol::Terminal(t), rest)) => {
+                bytes.starts_with(t) && matches(grammar, rest, &bytes[t.len()..])
+            }
+            // Match the rest first, so that left recursions only see shorter inputs
+            Some((GrammarSymbol::NonTerminal(nt), rest)) => (0..=bytes.len()).any(|split| {
+                matches(grammar, rest, &bytes[split..]) && derives(grammar, *nt, &bytes[..split])
+            }),
+        }
+    }
+
+    #[test]
+    fn test_grammar_unparse() {
+        let grammar = Grammar::from_rules(&[
+            ("START", "{EXPR}"),
+            ("EXPR", "{EXPR} + {NUM}"),
+            ("EXPR", "({EXPR})"),
+            ("EXPR", "{NUM}"),
+            ("NUM", "1"),
+            ("NUM", "{not a ref}"),
+        ])
+        .unwrap();
+        assert_eq!(grammar.nonterm_name(grammar.start()), "START");
+
+        let minimal = GrammarInput::new(grammar.generate_minimal(grammar.start()));
+        let mut bytes = vec![];
+        minimal.unparse(&grammar, &mut bytes);
+        assert_eq!(bytes, b"1");
+        assert!(!derives(&grammar, grammar.start(), b"(1"));
+        assert!(!derives(&grammar, grammar.start(), b"1 + "));
+
+        let mut rand = StdRand::with_seed(0);
+        for _ in 0..100 {
+            let input = GrammarInput::new(grammar.generate(&mut rand, grammar.start(), 6));
+            assert!(input.root().depth() <= 6);
+            assert_eq!(
+                input.root().get(input.len() - 1).unwrap().children().len(),
+                0
+            );
+            input.unparse(&grammar, &mut bytes);
+            assert!(derives(&grammar, grammar.start(), &bytes));
+        }
+    }
+
+    #[test]
+    fn test_grammar_nonterminating() {
+        assert!(Grammar::from_rules(&[("START", "{A}"), ("A", "a{A}")]).is_err());
+        assert!(Grammar::from_rules(&[("START", "{B}")]).is_err());
+    }
+}
@@ -3,6 +3,23 @@
 pub mod bytes;
 pub use bytes::BytesInput;
 
+pub mod grammar;
+pub use grammar::GrammarInput;
+
+pub mod token;
+pub use token::{TokenInput, TokenRenderExecutor};
+
+pub mod multi;
+pub use multi::MultipartInput;
+
+pub mod encoded;
+pub use encoded::EncodedInput;
+
+#[cfg(feature = "arbitrary_input")]
+pub mod arbitrary;
+#[cfg(feature = "arbitrary_input")]
+pub use self::arbitrary::ArbitraryInput;
+
 use alloc::{
     string::{String, ToString},
     vec::Vec,
@@ -12,13 +29,69 @@ use core::{clone::Clone, fmt::Debug};
 use std::{
     fs::File,
     io::{Read, Write},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
 use serde::{Deserialize, Serialize};
 
+#[cfg(all(feature = "std", feature = "llmp_compression"))]
+use crate::bolts::compress::GzipCompressor;
 use crate::{bolts::ownedref::OwnedSlice, Error};
 
+/// The suffix appended to the name of the input files written gzip-compressed by [`Input::to_file_compressed`].
+/// Only files stored by a corpus with this suffix are decompressed when loaded, [`Input::from_file`] stays byte-exact.
+#[cfg(all(feature = "std", feature = "llmp_compression"))]
+pub const COMPRESSED_SUFFIX: &str = ".gz";
+
+/// Reads the contents of an input file, as they are
+#[cfg(feature = "std")]
+pub(crate) fn read_file<P>(path: P) -> Result<Vec<u8>, Error>
+where
+    P: AsRef<Path>,
+{
+    let mut file = File::open(path)?;
+    let mut bytes: Vec<u8> = vec![];
+    file.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Reads the contents of an input file written gzip-compressed by [`Input::to_file_compressed`]
+#[cfg(all(feature = "std", feature = "llmp_compression"))]
+pub(crate) fn read_file_compressed<P>(path: P) -> Result<Vec<u8>, Error>
+where
+    P: AsRef<Path>,
+{
+    GzipCompressor::new(0).decompress_gzip(&read_file(path)?)
+}
+
+/// Writes the contents of an input file, gzip-compressed if they are at least as large as the threshold of the `compressor`.
+/// Compressed contents are written to `path` with the [`COMPRESSED_SUFFIX`] appended.
+/// Returns the path of the written file.
+#[cfg(all(feature = "std", feature = "llmp_compression"))]
+pub(crate) fn write_file_compressed<P>(
+    path: P,
+    bytes: &[u8],
+    compressor: &GzipCompressor,
+) -> Result<PathBuf, Error>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    match compressor.compress_gzip(bytes)? {
+        Some(compressed) => {
+            let mut name = path.as_os_str().to_owned();
+            name.push(COMPRESSED_SUFFIX);
+            let path = PathBuf::from(name);
+            File::create(&path)?.write_all(&compressed)?;
+            Ok(path)
+        }
+        None => {
+            File::create(path)?.write_all(bytes)?;
+            Ok(path.to_path_buf())
+        }
+    }
+}
+
 /// An input for the target
 pub trait Input: Clone + serde::Serialize + serde::de::DeserializeOwned + Debug {
     #[cfg(feature = "std")]
@@ -45,12 +118,31 @@ pub trait Input: Clone + serde::Serialize + serde::de::DeserializeOwned + Debug
     where
         P: AsRef<Path>,
     {
-        let mut file = File::open(path)?;
-        let mut bytes: Vec<u8> = vec![];
-        file.read_to_end(&mut bytes)?;
+        let bytes = read_file(path)?;
         Ok(postcard::from_bytes(&bytes)?)
     }
 
+    /// Write this input to the file, gzip-compressed if it is at least as large as the threshold of the `compressor`.
+    /// Compressed inputs get the [`COMPRESSED_SUFFIX`] appended to their path, and are loaded by [`Input::from_file_compressed`].
+    /// Returns the path of the written file.
+    #[cfg(all(feature = "std", feature = "llmp_compression"))]
+    fn to_file_compressed<P>(&self, path: P, compressor: &GzipCompressor) -> Result<PathBuf, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let serialized = postcard::to_allocvec(self)?;
+        write_file_compressed(path, &serialized, compressor)
+    }
+
+    /// Load the contents of this input from a file written gzip-compressed by [`Input::to_file_compressed`]
+    #[cfg(all(feature = "std", feature = "llmp_compression"))]
+    fn from_file_compressed<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(postcard::from_bytes(&read_file_compressed(path)?)?)
+    }
+
     /// Write this input to the file
     #[cfg(not(feature = "std"))]
     fn from_file<P>(_path: P) -> Result<Self, Error> {
@@ -83,6 +175,13 @@ pub trait HasTargetBytes {
     fn target_bytes(&self) -> OwnedSlice<u8>;
 }
 
+/// Can be represented as an ordered list of named byte buffers,
+/// for targets consuming more than one buffer per execution.
+pub trait HasTargetBytesParts {
+    /// The name and target bytes of each part, in order
+    fn target_bytes_parts(&self) -> Vec<(&str, OwnedSlice<u8>)>;
+}
+
 /// Contains an internal bytes Vector
 pub trait HasBytesVec {
     /// The internal bytes map
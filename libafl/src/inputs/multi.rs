@@ -0,0 +1,138 @@
+//! The `MultipartInput` is an ordered list of named [`BytesInput`] parts,
+//! for targets consuming multiple buffers, such as a config file and a payload, or a sequence of packets.
+
+use ahash::AHasher;
+use alloc::{string::String, vec::Vec};
+use core::hash::Hasher;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bolts::ownedref::OwnedSlice,
+    inputs::{BytesInput, HasBytesVec, HasLen, HasTargetBytes, HasTargetBytesParts, Input},
+};
+
+/// An input made of multiple, named, [`BytesInput`] parts
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MultipartInput {
+    parts: Vec<BytesInput>,
+    names: Vec<String>,
+}
+
+impl Input for MultipartInput {
+    /// Generate a name for this input
+    fn generate_name(&self, _idx: usize) -> String {
+        let mut hasher = AHasher::new_with_keys(0, 0);
+        for (name, part) in self.iter() {
+            hasher.write(name.as_bytes());
+            hasher.write(part.bytes());
+        }
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+impl HasLen for MultipartInput {
+    /// The number of parts
+    #[inline]
+    fn len(&self) -> usize {
+        self.parts.len()
+    }
+}
+
+impl HasTargetBytesParts for MultipartInput {
+    fn target_bytes_parts(&self) -> Vec<(&str, OwnedSlice<u8>)> {
+        self.iter()
+            .map(|(name, part)| (name, part.target_bytes()))
+            .collect()
+    }
+}
+
+impl MultipartInput {
+    /// Creates a new, empty, multipart input
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new multipart input from a list of `(name, part)` pairs
+    #[must_use]
+    pub fn with_parts<N>(parts: Vec<(N, BytesInput)>) -> Self
+    where
+        N: Into<String>,
+    {
+        let mut input = Self::new();
+        for (name, part) in parts {
+            input.add_part(name, part);
+        }
+        input
+    }
+
+    /// Appends a part with the given name
+    pub fn add_part<N>(&mut self, name: N, part: BytesInput)
+    where
+        N: Into<String>,
+    {
+        self.names.push(name.into());
+        self.parts.push(part);
+    }
+
+    /// Inserts a part with the given name at position `idx`
+    pub fn insert_part<N>(&mut self, idx: usize, name: N, part: BytesInput)
+    where
+        N: Into<String>,
+    {
+        self.names.insert(idx, name.into());
+        self.parts.insert(idx, part);
+    }
+
+    /// Removes the part at position `idx`, returning its name and contents
+    pub fn remove_part(&mut self, idx: usize) -> Option<(String, BytesInput)> {
+        if idx >= self.parts.len() {
+            None
+        } else {
+            Some((self.names.remove(idx), self.parts.remove(idx)))
+        }
+    }
+
+    /// Swaps the parts at the given positions, together with their names
+    pub fn swap_parts(&mut self, a: usize, b: usize) {
+        self.names.swap(a, b);
+        self.parts.swap(a, b);
+    }
+
+    /// The parts of this input
+    #[must_use]
+    pub fn parts(&self) -> &[BytesInput] {
+        &self.parts
+    }
+
+    /// The parts of this input (mut)
+    pub fn parts_mut(&mut self) -> &mut [BytesInput] {
+        &mut self.parts
+    }
+
+    /// The names of the parts of this input
+    #[must_use]
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Gets the first part with the given name
+    #[must_use]
+    pub fn part_by_name(&self, name: &str) -> Option<&BytesInput> {
+        self.names
+            .iter()
+            .position(|n| n == name)
+            .map(|idx| &self.parts[idx])
+    }
+
+    /// Iterates over `(name, part)` pairs, in order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &BytesInput)> {
+        self.names.iter().map(String::as_str).zip(self.parts.iter())
+    }
+
+    /// The total number of bytes in all parts
+    #[must_use]
+    pub fn bytes_len(&self) -> usize {
+        self.parts.iter().map(HasLen::len).sum()
+    }
+}
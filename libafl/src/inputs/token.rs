@@ -0,0 +1,275 @@
+//! The `TokenInput` is a sequence of tokens taken from a user-provided [`TokenTable`], in the spirit of Gramatron.
+//! It only stores token ids, and is rendered to bytes when it gets executed:
+//! by a [`TokenRenderExecutor`] wrapping an executor of [`BytesInput`]`s`, e.g. a forkserver,
+//! or in an in-process harness, after binding it to its table with [`TokenInput::bind`].
+
+use ahash::AHasher;
+use alloc::{string::String, vec::Vec};
+use core::hash::Hasher;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bolts::ownedref::OwnedSlice,
+    executors::{Executor, ExitKind, HasExecHooksTuple, HasObservers, HasObserversHooks},
+    inputs::{BytesInput, HasLen, HasTargetBytes, Input},
+    mutators::Tokens,
+    observers::ObserversTuple,
+    Error,
+};
+
+/// A table of the tokens (lexemes) understood by the target, each one identified by its index
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TokenTable {
+    tokens: Vec<Vec<u8>>,
+}
+
+impl TokenTable {
+    /// Creates a new [`TokenTable`] from a list of tokens
+    #[must_use]
+    pub fn new(tokens: Vec<Vec<u8>>) -> Self {
+        Self { tokens }
+    }
+
+    /// The number of tokens in this table
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// Returns `true` if this table contains no tokens
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Gets the bytes of the token with the given id
+    #[must_use]
+    pub fn get(&self, id: usize) -> Option<&[u8]> {
+        self.tokens.get(id).map(Vec::as_slice)
+    }
+
+    /// Creates a [`TokenInput`] from a list of token ids, or `None` if an id is not in this table
+    #[must_use]
+    pub fn input_from_ids(&self, ids: &[usize]) -> Option<TokenInput> {
+        if ids.iter().all(|id| *id < self.len()) {
+            Some(TokenInput::new(ids.to_vec()))
+        } else {
+            None
+        }
+    }
+
+    /// The number of bytes the given ids render to
+    #[must_use]
+    pub fn rendered_len(&self, ids: &[usize]) -> usize {
+        ids.iter()
+            .filter_map(|id| self.get(*id))
+            .map(<[u8]>::len)
+            .sum()
+    }
+
+    /// Concatenates the bytes of the given tokens, skipping the ids not in this table
+    #[must_use]
+    pub fn render(&self, ids: &[usize]) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.rendered_len(ids));
+        for bytes_of in ids.iter().filter_map(|id| self.get(*id)) {
+            bytes.extend_from_slice(bytes_of);
+        }
+        bytes
+    }
+}
+
+impl From<&Tokens> for TokenTable {
+    fn from(tokens: &Tokens) -> Self {
+        Self::new(tokens.tokens().to_vec())
+    }
+}
+
+/// An input made of a sequence of token ids.
+/// Only the ids are stored, the bytes are looked up in the [`TokenTable`] when the input is rendered.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TokenInput {
+    ids: Vec<usize>,
+}
+
+impl Input for TokenInput {
+    /// Generate a name for this input
+    fn generate_name(&self, _idx: usize) -> String {
+        let mut hasher = AHasher::new_with_keys(0, 0);
+        for id in &self.ids {
+            hasher.write_usize(*id);
+        }
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+impl HasLen for TokenInput {
+    /// The number of tokens
+    #[inline]
+    fn len(&self) -> usize {
+        self.ids.len()
+    }
+}
+
+impl From<Vec<usize>> for TokenInput {
+    fn from(ids: Vec<usize>) -> Self {
+        Self::new(ids)
+    }
+}
+
+impl TokenInput {
+    /// Creates a new token input from a list of token ids
+    #[must_use]
+    pub fn new(ids: Vec<usize>) -> Self {
+        Self { ids }
+    }
+
+    /// The token ids of this input
+    #[must_use]
+    pub fn ids(&self) -> &[usize] {
+        &self.ids
+    }
+
+    /// The token ids of this input (mut)
+    pub fn ids_mut(&mut self) -> &mut Vec<usize> {
+        &mut self.ids
+    }
+
+    /// Binds this input to its [`TokenTable`], to render it through [`HasTargetBytes`].
+    /// Harnesses call this to get the bytes for the target.
+    #[must_use]
+    pub fn bind<'a>(&'a self, table: &'a TokenTable) -> BoundTokenInput<'a> {
+        BoundTokenInput { input: self, table }
+    }
+}
+
+/// A [`TokenInput`] together with the [`TokenTable`] its ids refer to
+#[derive(Clone, Copy, Debug)]
+pub struct BoundTokenInput<'a> {
+    input: &'a TokenInput,
+    table: &'a TokenTable,
+}
+
+impl<'a> HasTargetBytes for BoundTokenInput<'a> {
+    /// Renders the tokens to bytes
+    fn target_bytes(&self) -> OwnedSlice<u8> {
+        OwnedSlice::Owned(self.table.render(self.input.ids()))
+    }
+}
+
+impl<'a> HasLen for BoundTokenInput<'a> {
+    /// The number of bytes the tokens render to
+    #[inline]
+    fn len(&self) -> usize {
+        self.table.rendered_len(self.input.ids())
+    }
+}
+
+/// An executor wrapper rendering each [`TokenInput`] through its [`TokenTable`],
+/// and running the wrapped executor, e.g. a forkserver, on the resulting [`BytesInput`].
+/// The observers see the [`TokenInput`].
+#[derive(Debug)]
+pub struct TokenRenderExecutor<E> {
+    executor: E,
+    table: TokenTable,
+}
+
+impl<E> TokenRenderExecutor<E> {
+    /// Creates a new [`TokenRenderExecutor`], rendering the inputs of `executor` with `table`
+    pub fn new(executor: E, table: TokenTable) -> Self {
+        Self { executor, table }
+    }
+
+    /// The table the inputs are rendered with
+    #[must_use]
+    pub fn table(&self) -> &TokenTable {
+        &self.table
+    }
+
+    /// The wrapped executor
+    pub fn inner(&mut self) -> &mut E {
+        &mut self.executor
+    }
+}
+
+impl<E, EM, S, Z> Executor<EM, TokenInput, S, Z> for TokenRenderExecutor<E>
+where
+    E: Executor<EM, BytesInput, S, Z>,
+{
+    fn run_target(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut S,
+        mgr: &mut EM,
+        input: &TokenInput,
+    ) -> Result<ExitKind, Error> {
+        let rendered = BytesInput::new(self.table.render(input.ids()));
+        self.executor.run_target(fuzzer, state, mgr, &rendered)
+    }
+}
+
+impl<E, OT> HasObservers<OT> for TokenRenderExecutor<E>
+where
+    E: HasObservers<OT>,
+    OT: ObserversTuple,
+{
+    #[inline]
+    fn observers(&self) -> &OT {
+        self.executor.observers()
+    }
+
+    #[inline]
+    fn observers_mut(&mut self) -> &mut OT {
+        self.executor.observers_mut()
+    }
+}
+
+impl<E, EM, OT, S, Z> HasObserversHooks<EM, TokenInput, OT, S, Z> for TokenRenderExecutor<E>
+where
+    E: HasObservers<OT>,
+    OT: ObserversTuple + HasExecHooksTuple<EM, TokenInput, S, Z>,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::{
+        executors::{Executor, ExitKind},
+        inputs::{
+            token::{TokenRenderExecutor, TokenTable},
+            BytesInput, HasBytesVec, TokenInput,
+        },
+        Error,
+    };
+
+    /// Records the bytes it is run on
+    #[derive(Default)]
+    struct RecordingExecutor {
+        executed: Vec<Vec<u8>>,
+    }
+
+    impl Executor<(), BytesInput, (), ()> for RecordingExecutor {
+        fn run_target(
+            &mut self,
+            _fuzzer: &mut (),
+            _state: &mut (),
+            _mgr: &mut (),
+            input: &BytesInput,
+        ) -> Result<ExitKind, Error> {
+            self.executed.push(input.bytes().to_vec());
+            Ok(ExitKind::Ok)
+        }
+    }
+
+    #[test]
+    fn test_token_render_executor() {
+        let table = TokenTable::new(vec![b"let".to_vec(), b" ".to_vec(), b"x".to_vec()]);
+        let mut executor = TokenRenderExecutor::new(RecordingExecutor::default(), table);
+        let input = TokenInput::new(vec![0, 1, 2, 1, 2]);
+        executor
+            .run_target(&mut (), &mut (), &mut (), &input)
+            .unwrap();
+        assert_eq!(executor.inner().executed, vec![b"let x x".to_vec()]);
+    }
+}
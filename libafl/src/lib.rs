@@ -117,6 +117,14 @@ impl From<serde_json::Error> for Error {
     }
 }
 
+/// Stringify the python error, including its traceback
+#[cfg(feature = "python")]
+impl From<pyo3::PyErr> for Error {
+    fn from(err: pyo3::PyErr) -> Self {
+        Self::Unknown(format!("Python error: {:?}", err))
+    }
+}
+
 #[cfg(unix)]
 impl From<nix::Error> for Error {
     fn from(err: nix::Error) -> Self {
@@ -0,0 +1,577 @@
+//! A [`Mutator`] loading a custom mutator written for the AFL++ custom mutator API from a shared object.
+//! The `afl_custom_init`, `afl_custom_fuzz`, `afl_custom_post_process` and `afl_custom_deinit` entry points are supported.
+//! The post processor is applied before the executions by an [`AflCustomPostProcessExecutor`].
+
+use alloc::{rc::Rc, string::String, vec::Vec};
+use core::{marker::PhantomData, ptr, slice};
+use libc::{c_char, c_uint, c_void, size_t};
+use std::ffi::{CStr, CString};
+
+use crate::{
+    bolts::{rands::Rand, tuples::Named},
+    corpus::Corpus,
+    executors::{Executor, ExitKind, HasExecHooksTuple, HasObservers, HasObserversHooks},
+    inputs::{HasBytesVec, Input},
+    mutators::{MutationResult, Mutator},
+    observers::ObserversTuple,
+    state::{HasCorpus, HasMaxSize, HasRand},
+    Error,
+};
+
+/// `void *afl_custom_init(afl_state_t *afl, unsigned int seed)`
+type AflCustomInitFn = unsafe extern "C" fn(*mut c_void, c_uint) -> *mut c_void;
+/// `size_t afl_custom_fuzz(void *data, uint8_t *buf, size_t buf_size, uint8_t **out_buf,
+///                         uint8_t *add_buf, size_t add_buf_size, size_t max_size)`
+type AflCustomFuzzFn = unsafe extern "C" fn(
+    *mut c_void,
+    *mut u8,
+    size_t,
+    *mut *mut u8,
+    *mut u8,
+    size_t,
+    size_t,
+) -> size_t;
+/// `size_t afl_custom_post_process(void *data, uint8_t *buf, size_t buf_size, uint8_t **out_buf)`
+type AflCustomPostProcessFn =
+    unsafe extern "C" fn(*mut c_void, *mut u8, size_t, *mut *mut u8) -> size_t;
+/// `void afl_custom_deinit(void *data)`
+type AflCustomDeinitFn = unsafe extern "C" fn(*mut c_void);
+
+/// Returns the last `dlerror`, if any
+fn dlerror_string() -> String {
+    unsafe {
+        let err = libc::dlerror();
+        if err.is_null() {
+            "unknown error".into()
+        } else {
+            CStr::from_ptr(err).to_string_lossy().into_owned()
+        }
+    }
+}
+
+/// The entry points of a loaded AFL++ custom mutator, shared by the mutator and its post processor
+struct AflCustomLibrary {
+    path: String,
+    handle: *mut c_void,
+    data: *mut c_void,
+    fuzz: Option<AflCustomFuzzFn>,
+    post_process: Option<AflCustomPostProcessFn>,
+    deinit: Option<AflCustomDeinitFn>,
+}
+
+impl AflCustomLibrary {
+    /// Loads the AFL++ custom mutator at `path`, initializing it with `seed`
+    fn load(path: &str, seed: c_uint) -> Result<Self, Error> {
+        let c_path = CString::new(path)
+            .map_err(|_| Error::IllegalArgument(format!("Invalid path {}", path)))?;
+        let handle = unsafe { libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW) };
+        if handle.is_null() {
+            return Err(Error::IllegalArgument(format!(
+                "Cannot load the custom mutator {}: {}",
+                path,
+                dlerror_string()
+            )));
+        }
+
+        let symbol = |name: &[u8]| unsafe { libc::dlsym(handle, name.as_ptr() as *const c_char) };
+        let init = symbol(b"afl_custom_init\0");
+        let fuzz = symbol(b"afl_custom_fuzz\0");
+        let post_process = symbol(b"afl_custom_post_process\0");
+        let deinit = symbol(b"afl_custom_deinit\0");
+        if fuzz.is_null() && post_process.is_null() {
+            unsafe {
+                libc::dlclose(handle);
+            }
+            return Err(Error::IllegalArgument(format!(
+                "The custom mutator {} exports neither afl_custom_fuzz nor afl_custom_post_process",
+                path
+            )));
+        }
+
+        let data = if init.is_null() {
+            ptr::null_mut()
+        } else {
+            let init: AflCustomInitFn = unsafe { core::mem::transmute(init) };
+            let data = unsafe { init(ptr::null_mut(), seed) };
+            if data.is_null() {
+                unsafe {
+                    libc::dlclose(handle);
+                }
+                return Err(Error::IllegalState(format!(
+                    "The initialization of the custom mutator {} failed",
+                    path
+                )));
+            }
+            data
+        };
+
+        unsafe {
+            Ok(Self {
+                path: path.into(),
+                handle,
+                data,
+                fuzz: (!fuzz.is_null()).then(|| core::mem::transmute(fuzz)),
+                post_process: (!post_process.is_null()).then(|| core::mem::transmute(post_process)),
+                deinit: (!deinit.is_null()).then(|| core::mem::transmute(deinit)),
+            })
+        }
+    }
+
+    /// Runs `afl_custom_post_process` on a copy of `input`, if exported.
+    /// Returns an empty buffer if the post processor asks to skip the execution.
+    fn post_process(&self, input: &[u8]) -> Vec<u8> {
+        match self.post_process {
+            None => input.to_vec(),
+            Some(post_process) => {
+                let mut buf = input.to_vec();
+                let mut out_buf: *mut u8 = ptr::null_mut();
+                let len =
+                    unsafe { post_process(self.data, buf.as_mut_ptr(), buf.len(), &mut out_buf) };
+                if out_buf.is_null() {
+                    vec![]
+                } else {
+                    // The output buffer is owned by the custom mutator, and may be the input buffer itself
+                    unsafe { slice::from_raw_parts(out_buf, len) }.to_vec()
+                }
+            }
+        }
+    }
+}
+
+impl Drop for AflCustomLibrary {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(deinit) = self.deinit {
+                deinit(self.data);
+            }
+            libc::dlclose(self.handle);
+        }
+    }
+}
+
+/// A [`Mutator`] calling `afl_custom_fuzz` of an AFL++ custom mutator, loaded with `dlopen`,
+/// using a random corpus entry as the splicing buffer.
+/// `afl_custom_post_process` is not applied by the mutator, the mutated input is the one stored in the corpus.
+/// As AFL++ does, the post processor only rewrites the bytes given to the target:
+/// wrap the executor in an [`AflCustomPostProcessExecutor`], built from [`AflCustomMutator::post_processor`].
+/// At least one of the two entry points must be exported.
+/// The custom mutator gets a `NULL` AFL++ state in `afl_custom_init`, so it must not access it.
+pub struct AflCustomMutator<C, I, R, S>
+where
+    C: Corpus<I>,
+    I: Input + HasBytesVec,
+    R: Rand,
+    S: HasRand<R> + HasCorpus<C, I> + HasMaxSize,
+{
+    library: Rc<AflCustomLibrary>,
+    phantom: PhantomData<(C, I, R, S)>,
+}
+
+impl<C, I, R, S> core::fmt::Debug for AflCustomMutator<C, I, R, S>
+where
+    C: Corpus<I>,
+    I: Input + HasBytesVec,
+    R: Rand,
+    S: HasRand<R> + HasCorpus<C, I> + HasMaxSize,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("AflCustomMutator")
+            .field("path", &self.library.path)
+            .field("fuzz", &self.library.fuzz.is_some())
+            .field("post_process", &self.library.post_process.is_some())
+            .finish()
+    }
+}
+
+impl<C, I, R, S> Mutator<I, S> for AflCustomMutator<C, I, R, S>
+where
+    C: Corpus<I>,
+    I: Input + HasBytesVec,
+    R: Rand,
+    S: HasRand<R> + HasCorpus<C, I> + HasMaxSize,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let fuzz = match self.library.fuzz {
+            Some(fuzz) => fuzz,
+            // A post processor only, there is nothing to mutate
+            None => return Ok(MutationResult::Skipped),
+        };
+        let max_size = state.max_size();
+        let count = state.corpus().count();
+        let mut add_buf = if count == 0 {
+            vec![]
+        } else {
+            let nth = state.rand_mut().below(count as u64) as usize;
+            let idx = state
+                .corpus()
+                .nth(nth)
+                .ok_or_else(|| Error::Empty("No entries in corpus".into()))?;
+            let mut other_testcase = state.corpus().get(idx)?.borrow_mut();
+            other_testcase.load_input()?.bytes().to_vec()
+        };
+
+        let mut out_buf: *mut u8 = ptr::null_mut();
+        let buf = input.bytes_mut();
+        let len = unsafe {
+            fuzz(
+                self.library.data,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut out_buf,
+                add_buf.as_mut_ptr(),
+                add_buf.len(),
+                max_size,
+            )
+        };
+        // The custom mutator may give up on this input
+        if out_buf.is_null() || len == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        // The output buffer is owned by the custom mutator, and may be the input buffer itself
+        let mutated = unsafe { slice::from_raw_parts(out_buf, len.min(max_size)) }.to_vec();
+        *input.bytes_mut() = mutated;
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<C, I, R, S> Named for AflCustomMutator<C, I, R, S>
+where
+    C: Corpus<I>,
+    I: Input + HasBytesVec,
+    R: Rand,
+    S: HasRand<R> + HasCorpus<C, I> + HasMaxSize,
+{
+    fn name(&self) -> &str {
+        "AflCustomMutator"
+    }
+}
+
+impl<C, I, R, S> AflCustomMutator<C, I, R, S>
+where
+    C: Corpus<I>,
+    I: Input + HasBytesVec,
+    R: Rand,
+    S: HasRand<R> + HasCorpus<C, I> + HasMaxSize,
+{
+    /// Loads the AFL++ custom mutator at `path`, initializing it with a seed from the rand of the state
+    pub fn new(state: &mut S, path: &str) -> Result<Self, Error> {
+        let seed = state.rand_mut().next() as c_uint;
+        Ok(Self {
+            library: Rc::new(AflCustomLibrary::load(path, seed)?),
+            phantom: PhantomData,
+        })
+    }
+
+    /// The path of the loaded custom mutator
+    #[must_use]
+    pub fn path(&self) -> &str {
+        &self.library.path
+    }
+
+    /// Runs only `afl_custom_post_process` on `input`, if exported, as AFL++ does before every execution.
+    /// Returns the input unchanged if the custom mutator has no post processor.
+    pub fn post_process(&mut self, input: &[u8]) -> Vec<u8> {
+        self.library.post_process(input)
+    }
+
+    /// The post processor of the loaded custom mutator, sharing its state, to apply before the executions
+    #[must_use]
+    pub fn post_processor(&self) -> AflCustomPostProcessor {
+        AflCustomPostProcessor {
+            library: self.library.clone(),
+        }
+    }
+}
+
+/// The `afl_custom_post_process` entry point of an [`AflCustomMutator`]
+#[derive(Clone)]
+pub struct AflCustomPostProcessor {
+    library: Rc<AflCustomLibrary>,
+}
+
+impl core::fmt::Debug for AflCustomPostProcessor {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("AflCustomPostProcessor")
+            .field("path", &self.library.path)
+            .field("post_process", &self.library.post_process.is_some())
+            .finish()
+    }
+}
+
+impl AflCustomPostProcessor {
+    /// Runs `afl_custom_post_process` on `input`, if exported.
+    /// Returns the input unchanged if the custom mutator has no post processor,
+    /// and an empty buffer if the post processor asks to skip the execution.
+    #[must_use]
+    pub fn post_process(&self, input: &[u8]) -> Vec<u8> {
+        self.library.post_process(input)
+    }
+}
+
+/// An executor wrapper running the target on the post processed input,
+/// leaving the input of the fuzzer, that may be added to the corpus, unchanged.
+/// An input that the post processor reduces to nothing is not executed.
+#[derive(Debug)]
+pub struct AflCustomPostProcessExecutor<E> {
+    executor: E,
+    post_processor: AflCustomPostProcessor,
+}
+
+impl<E> AflCustomPostProcessExecutor<E> {
+    /// Creates a new [`AflCustomPostProcessExecutor`], post processing the inputs of `executor`
+    pub fn new(executor: E, post_processor: AflCustomPostProcessor) -> Self {
+        Self {
+            executor,
+            post_processor,
+        }
+    }
+
+    /// The wrapped executor
+    pub fn inner(&mut self) -> &mut E {
+        &mut self.executor
+    }
+}
+
+impl<E, EM, I, S, Z> Executor<EM, I, S, Z> for AflCustomPostProcessExecutor<E>
+where
+    E: Executor<EM, I, S, Z>,
+    I: Input + HasBytesVec,
+{
+    fn run_target(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut S,
+        mgr: &mut EM,
+        input: &I,
+    ) -> Result<ExitKind, Error> {
+        let processed = self.post_processor.post_process(input.bytes());
+        if processed.is_empty() {
+            return Ok(ExitKind::Ok);
+        }
+        let mut processed_input = input.clone();
+        *processed_input.bytes_mut() = processed;
+        self.executor
+            .run_target(fuzzer, state, mgr, &processed_input)
+    }
+}
+
+impl<E, OT> HasObservers<OT> for AflCustomPostProcessExecutor<E>
+where
+    E: HasObservers<OT>,
+    OT: ObserversTuple,
+{
+    #[inline]
+    fn observers(&self) -> &OT {
+        self.executor.observers()
+    }
+
+    #[inline]
+    fn observers_mut(&mut self) -> &mut OT {
+        self.executor.observers_mut()
+    }
+}
+
+impl<E, EM, I, OT, S, Z> HasObserversHooks<EM, I, OT, S, Z> for AflCustomPostProcessExecutor<E>
+where
+    E: HasObservers<OT>,
+    I: Input,
+    OT: ObserversTuple + HasExecHooksTuple<EM, I, S, Z>,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+        process::Command,
+    };
+
+    use crate::{
+        bolts::{rands::StdRand, tuples::tuple_list},
+        corpus::{InMemoryCorpus, RandCorpusScheduler},
+        events::SimpleEventManager,
+        executors::{Executor, ExitKind, InProcessExecutor},
+        inputs::{BytesInput, HasBytesVec},
+        mutators::{
+            afl_custom::{AflCustomMutator, AflCustomPostProcessExecutor},
+            MutationResult, Mutator,
+        },
+        state::StdState,
+        stats::SimpleStats,
+        StdFuzzer,
+    };
+
+    /// Appends an `M` to the input, giving up on the inputs starting with `e`,
+    /// and uppercases it in the post processor, skipping the inputs starting with `s`
+    const CUSTOM_MUTATOR_SRC: &str = r#"
+#include <stdint.h>
+#include <stdlib.h>
+#include <string.h>
+
+typedef struct { uint8_t buf[256]; } state_t;
+
+void *afl_custom_init(void *afl, unsigned int seed) {
+  (void)afl;
+  (void)seed;
+  return calloc(1, sizeof(state_t));
+}
+
+size_t afl_custom_fuzz(void *data, uint8_t *buf, size_t buf_size, uint8_t **out_buf,
+                       uint8_t *add_buf, size_t add_buf_size, size_t max_size) {
+  (void)add_buf;
+  (void)add_buf_size;
+  (void)max_size;
+  state_t *state = data;
+  *out_buf = state->buf;
+  if (buf_size > 0 && buf[0] == 'e') return 0;
+  size_t len = buf_size < 255 ? buf_size : 255;
+  memcpy(state->buf, buf, len);
+  state->buf[len] = 'M';
+  *out_buf = state->buf;
+  return len + 1;
+}
+
+size_t afl_custom_post_process(void *data, uint8_t *buf, size_t buf_size, uint8_t **out_buf) {
+  (void)data;
+  if (buf_size > 0 && buf[0] == 's') {
+    *out_buf = NULL;
+    return 0;
+  }
+  for (size_t i = 0; i < buf_size; i++) {
+    if (buf[i] >= 'a' && buf[i] <= 'z') buf[i] -= 'a' - 'A';
+  }
+  *out_buf = buf;
+  return buf_size;
+}
+
+void afl_custom_deinit(void *data) { free(data); }
+"#;
+
+    /// A loadable library, that isn't a custom mutator
+    const NOT_A_MUTATOR_SRC: &str = r#"
+int not_a_custom_mutator(void) { return 0; }
+"#;
+
+    /// Compiles `src` to the shared library `name` in `dir`
+    fn build_library(dir: &Path, name: &str, src: &str) -> PathBuf {
+        fs::create_dir_all(dir).unwrap();
+        let src_path = dir.join(format!("{}.c", name));
+        let lib = dir.join(format!("{}.so", name));
+        fs::write(&src_path, src).unwrap();
+        let status = Command::new("cc")
+            .args(&["-shared", "-fPIC", "-o"])
+            .arg(&lib)
+            .arg(&src_path)
+            .status()
+            .expect("A C compiler is needed to build the custom mutator");
+        assert!(status.success());
+        lib
+    }
+
+    type TestState =
+        StdState<InMemoryCorpus<BytesInput>, (), BytesInput, StdRand, InMemoryCorpus<BytesInput>>;
+
+    fn new_state() -> TestState {
+        StdState::new(
+            StdRand::with_seed(1337),
+            InMemoryCorpus::new(),
+            InMemoryCorpus::new(),
+            (),
+        )
+    }
+
+    #[test]
+    fn test_afl_custom_mutator_load_errors() {
+        let dir = std::env::temp_dir().join(format!(
+            "libafl_test_afl_custom_load_errors_{}",
+            std::process::id()
+        ));
+        let lib = build_library(&dir, "not_a_mutator", NOT_A_MUTATOR_SRC);
+
+        let mut state = new_state();
+        assert!(AflCustomMutator::new(&mut state, "./does_not_exist.so").is_err());
+        // The library is loadable, but it isn't a custom mutator
+        assert!(AflCustomMutator::new(&mut state, lib.to_str().unwrap()).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[allow(clippy::similar_names)]
+    fn test_afl_custom_post_process() {
+        let dir = std::env::temp_dir().join(format!(
+            "libafl_test_afl_custom_post_process_{}",
+            std::process::id()
+        ));
+        let lib = build_library(&dir, "custom_mutator", CUSTOM_MUTATOR_SRC);
+
+        let mut state = new_state();
+        let mut mutator = AflCustomMutator::new(&mut state, lib.to_str().unwrap()).unwrap();
+
+        // The mutated input is not post processed, it is the one that goes to the corpus
+        let mut input = BytesInput::new(b"ab".to_vec());
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input, 0).unwrap(),
+            MutationResult::Mutated
+        );
+        assert_eq!(input.bytes(), b"abM");
+        // A custom mutator returning no bytes skips the mutation
+        let mut empty = BytesInput::new(b"empty".to_vec());
+        assert_eq!(
+            mutator.mutate(&mut state, &mut empty, 0).unwrap(),
+            MutationResult::Skipped
+        );
+        assert_eq!(empty.bytes(), b"empty");
+
+        let stats = SimpleStats::new(|s| {
+            println!("{}", s);
+        });
+        let mut mgr = SimpleEventManager::new(stats);
+        let mut fuzzer: StdFuzzer<
+            InMemoryCorpus<BytesInput>,
+            _,
+            (),
+            BytesInput,
+            (),
+            (),
+            TestState,
+            InMemoryCorpus<BytesInput>,
+        > = StdFuzzer::new(RandCorpusScheduler::new(), (), ());
+        let mut executed = vec![];
+        let mut harness = |input: &BytesInput| {
+            executed.push(input.bytes().to_vec());
+            ExitKind::Ok
+        };
+        let executor = InProcessExecutor::new(
+            &mut harness,
+            tuple_list!(),
+            &mut fuzzer,
+            &mut state,
+            &mut mgr,
+        )
+        .unwrap();
+        let mut executor = AflCustomPostProcessExecutor::new(executor, mutator.post_processor());
+
+        // Only the target sees the post processed input
+        executor
+            .run_target(&mut fuzzer, &mut state, &mut mgr, &input)
+            .unwrap();
+        assert_eq!(input.bytes(), b"abM");
+        // The post processor skips this one
+        let skipped = BytesInput::new(b"skip".to_vec());
+        executor
+            .run_target(&mut fuzzer, &mut state, &mut mgr, &skipped)
+            .unwrap();
+        drop(executor);
+        assert_eq!(executed, vec![b"ABM".to_vec()]);
+
+        drop(mutator);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,180 @@
+//! A wrapper [`Mutator`] enforcing size bounds on the inputs produced by any other mutator,
+//! so that targets with hard length constraints don't waste executions.
+
+use core::marker::PhantomData;
+
+use crate::{
+    bolts::tuples::Named,
+    corpus::CorpusId,
+    inputs::{HasBytesVec, Input},
+    mutators::{MutationResult, Mutator},
+    state::HasMaxSize,
+    Error,
+};
+
+/// What to do with mutated inputs outside of the size bounds
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SizePolicy {
+    /// Truncate inputs larger than the max size, and pad inputs smaller than the min size with zeros
+    Truncate,
+    /// Reject inputs out of bounds, reporting them as [`MutationResult::Skipped`], so they don't get executed
+    Reject,
+}
+
+/// Wraps a [`Mutator`], guaranteeing that every input it produces is between `min_size` and the `max_size` of the state
+pub struct SizeBoundedMutator<I, M, S>
+where
+    I: Input + HasBytesVec,
+    M: Mutator<I, S>,
+    S: HasMaxSize,
+{
+    inner: M,
+    policy: SizePolicy,
+    min_size: usize,
+    phantom: PhantomData<(I, S)>,
+}
+
+impl<I, M, S> Mutator<I, S> for SizeBoundedMutator<I, M, S>
+where
+    I: Input + HasBytesVec,
+    M: Mutator<I, S>,
+    S: HasMaxSize,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let result = self.inner.mutate(state, input, stage_idx)?;
+        if result == MutationResult::Skipped {
+            return Ok(result);
+        }
+
+        let max_size = state.max_size();
+        let len = input.bytes().len();
+        if len <= max_size && len >= self.min_size {
+            return Ok(result);
+        }
+        match self.policy {
+            SizePolicy::Reject => Ok(MutationResult::Skipped),
+            SizePolicy::Truncate => {
+                if len > max_size {
+                    input.bytes_mut().truncate(max_size);
+                } else {
+                    input.bytes_mut().resize(self.min_size, 0);
+                }
+                Ok(result)
+            }
+        }
+    }
+
+    fn post_exec(
+        &mut self,
+        state: &mut S,
+        stage_idx: i32,
+        corpus_idx: Option<CorpusId>,
+    ) -> Result<(), Error> {
+        self.inner.post_exec(state, stage_idx, corpus_idx)
+    }
+}
+
+impl<I, M, S> Named for SizeBoundedMutator<I, M, S>
+where
+    I: Input + HasBytesVec,
+    M: Mutator<I, S> + Named,
+    S: HasMaxSize,
+{
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+}
+
+impl<I, M, S> SizeBoundedMutator<I, M, S>
+where
+    I: Input + HasBytesVec,
+    M: Mutator<I, S>,
+    S: HasMaxSize,
+{
+    /// Creates a new [`SizeBoundedMutator`], bounding the inputs produced by `inner` to the `max_size` of the state.
+    #[must_use]
+    pub fn new(inner: M, policy: SizePolicy) -> Self {
+        Self::with_min_size(inner, policy, 0)
+    }
+
+    /// Creates a new [`SizeBoundedMutator`], bounding the inputs produced by `inner`
+    /// to at least `min_size` bytes, and to the `max_size` of the state.
+    #[must_use]
+    pub fn with_min_size(inner: M, policy: SizePolicy, min_size: usize) -> Self {
+        Self {
+            inner,
+            policy,
+            min_size,
+            phantom: PhantomData,
+        }
+    }
+
+    /// The minimum size of the produced inputs
+    #[must_use]
+    pub fn min_size(&self) -> usize {
+        self.min_size
+    }
+
+    /// The wrapped mutator
+    #[must_use]
+    pub fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    /// The wrapped mutator (mut)
+    pub fn inner_mut(&mut self) -> &mut M {
+        &mut self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bolts::rands::StdRand,
+        corpus::InMemoryCorpus,
+        inputs::{BytesInput, HasBytesVec},
+        mutators::{
+            bounded::{SizeBoundedMutator, SizePolicy},
+            BitFlipMutator, MutationResult, Mutator,
+        },
+        state::{HasMaxSize, StdState},
+    };
+
+    #[test]
+    fn test_size_bounded_mutator() {
+        let mut state = StdState::new(
+            StdRand::with_seed(1337),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            (),
+        );
+        state.set_max_size(8);
+
+        let mut truncate =
+            SizeBoundedMutator::with_min_size(BitFlipMutator::new(), SizePolicy::Truncate, 4);
+        let mut reject =
+            SizeBoundedMutator::with_min_size(BitFlipMutator::new(), SizePolicy::Reject, 4);
+
+        for (bytes, len) in &[(vec![1_u8; 16], 8), (vec![1_u8; 2], 4), (vec![1_u8; 6], 6)] {
+            let mut input = BytesInput::new(bytes.clone());
+            assert_eq!(
+                truncate.mutate(&mut state, &mut input, 0).unwrap(),
+                MutationResult::Mutated
+            );
+            assert_eq!(input.bytes().len(), *len);
+
+            let mut input = BytesInput::new(bytes.clone());
+            let expected = if bytes.len() == *len {
+                MutationResult::Mutated
+            } else {
+                MutationResult::Skipped
+            };
+            assert_eq!(reject.mutate(&mut state, &mut input, 0).unwrap(), expected);
+        }
+    }
+}
@@ -0,0 +1,439 @@
+//! Crossover and line mutations for text protocols, that keep their framing.
+//! The crossovers only cut the inputs right after a delimiter byte, such as a line break or a whitespace,
+//! and the line mutations move whole lines around.
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use crate::{
+    bolts::{rands::Rand, tuples::Named},
+    corpus::Corpus,
+    inputs::{HasBytesVec, Input},
+    mutators::{MutationResult, Mutator},
+    state::{HasCorpus, HasMaxSize, HasRand},
+    Error,
+};
+
+/// The default delimiters of the crossovers: line breaks and whitespaces
+pub const DEFAULT_DELIMITERS: &[u8] = b"\n\r\t ";
+
+/// Returns the offsets at which `bytes` can be cut: its start, its end, and right after each delimiter
+#[must_use]
+pub fn cut_points(bytes: &[u8], delimiters: &[u8]) -> Vec<usize> {
+    let mut cuts = vec![0];
+    for (i, b) in bytes.iter().enumerate() {
+        if delimiters.contains(b) {
+            cuts.push(i + 1);
+        }
+    }
+    if *cuts.last().unwrap() != bytes.len() {
+        cuts.push(bytes.len());
+    }
+    cuts
+}
+
+/// Returns the bytes of a random corpus entry, other than the current one
+fn other_bytes<C, I, R, S>(state: &mut S) -> Result<Option<Vec<u8>>, Error>
+where
+    C: Corpus<I>,
+    I: Input + HasBytesVec,
+    R: Rand,
+    S: HasRand<R> + HasCorpus<C, I>,
+{
+    let count = state.corpus().count();
+    if count == 0 {
+        return Ok(None);
+    }
+    let nth = state.rand_mut().below(count as u64) as usize;
+    let idx = state
+        .corpus()
+        .nth(nth)
+        .ok_or_else(|| Error::Empty("No entries in corpus".into()))?;
+    if let Some(cur) = state.corpus().current() {
+        if idx == *cur {
+            return Ok(None);
+        }
+    }
+    let mut other_testcase = state.corpus().get(idx)?.borrow_mut();
+    Ok(Some(other_testcase.load_input()?.bytes().to_vec()))
+}
+
+/// Returns a random range between two different cut points
+fn random_segment<R: Rand>(rand: &mut R, cuts: &[usize]) -> Option<(usize, usize)> {
+    if cuts.len() < 2 {
+        return None;
+    }
+    let start = rand.below(cuts.len() as u64 - 1) as usize;
+    let end = rand.between(start as u64 + 1, cuts.len() as u64 - 1) as usize;
+    Some((cuts[start], cuts[end]))
+}
+
+/// Splits `bytes` into lines, each with its line break, if any
+fn split_lines(bytes: &[u8]) -> Vec<Vec<u8>> {
+    cut_points(bytes, b"\n")
+        .windows(2)
+        .map(|w| bytes[w[0]..w[1]].to_vec())
+        .collect()
+}
+
+/// Joins lines, adding the line break missing to the last line of the original input, if it moved
+fn join_lines(lines: &[Vec<u8>]) -> Vec<u8> {
+    let mut bytes = vec![];
+    for (i, line) in lines.iter().enumerate() {
+        bytes.extend_from_slice(line);
+        if i + 1 < lines.len() && line.last() != Some(&b'\n') {
+            bytes.push(b'\n');
+        }
+    }
+    bytes
+}
+
+macro_rules! delimited_crossover_impl {
+    ($name: ident, $doc: literal, $crossover: ident) => {
+        #[doc = $doc]
+        pub struct $name<C, I, R, S>
+        where
+            C: Corpus<I>,
+            I: Input + HasBytesVec,
+            R: Rand,
+            S: HasRand<R> + HasCorpus<C, I> + HasMaxSize,
+        {
+            delimiters: Vec<u8>,
+            phantom: PhantomData<(C, I, R, S)>,
+        }
+
+        impl<C, I, R, S> Mutator<I, S> for $name<C, I, R, S>
+        where
+            C: Corpus<I>,
+            I: Input + HasBytesVec,
+            R: Rand,
+            S: HasRand<R> + HasCorpus<C, I> + HasMaxSize,
+        {
+            fn mutate(
+                &mut self,
+                state: &mut S,
+                input: &mut I,
+                _stage_idx: i32,
+            ) -> Result<MutationResult, Error> {
+                let other = match other_bytes(state)? {
+                    Some(other) => other,
+                    None => return Ok(MutationResult::Skipped),
+                };
+                let input_cuts = cut_points(input.bytes(), &self.delimiters);
+                let other_cuts = cut_points(&other, &self.delimiters);
+                let mutated = match $crossover(
+                    state.rand_mut(),
+                    input.bytes(),
+                    &input_cuts,
+                    &other,
+                    &other_cuts,
+                ) {
+                    Some(mutated) => mutated,
+                    None => return Ok(MutationResult::Skipped),
+                };
+                if mutated.len() > state.max_size() || mutated == input.bytes() {
+                    return Ok(MutationResult::Skipped);
+                }
+                *input.bytes_mut() = mutated;
+                Ok(MutationResult::Mutated)
+            }
+        }
+
+        impl<C, I, R, S> Named for $name<C, I, R, S>
+        where
+            C: Corpus<I>,
+            I: Input + HasBytesVec,
+            R: Rand,
+            S: HasRand<R> + HasCorpus<C, I> + HasMaxSize,
+        {
+            fn name(&self) -> &str {
+                stringify!($name)
+            }
+        }
+
+        impl<C, I, R, S> $name<C, I, R, S>
+        where
+            C: Corpus<I>,
+            I: Input + HasBytesVec,
+            R: Rand,
+            S: HasRand<R> + HasCorpus<C, I> + HasMaxSize,
+        {
+            /// Creates a new mutator, cutting at the [`DEFAULT_DELIMITERS`].
+            #[must_use]
+            pub fn new() -> Self {
+                Self::with_delimiters(DEFAULT_DELIMITERS)
+            }
+
+            /// Creates a new mutator, cutting only right after the given delimiter bytes.
+            #[must_use]
+            pub fn with_delimiters(delimiters: &[u8]) -> Self {
+                Self {
+                    delimiters: delimiters.to_vec(),
+                    phantom: PhantomData,
+                }
+            }
+
+            /// The delimiters after which the inputs are cut
+            #[must_use]
+            pub fn delimiters(&self) -> &[u8] {
+                &self.delimiters
+            }
+        }
+
+        impl<C, I, R, S> Default for $name<C, I, R, S>
+        where
+            C: Corpus<I>,
+            I: Input + HasBytesVec,
+            R: Rand,
+            S: HasRand<R> + HasCorpus<C, I> + HasMaxSize,
+        {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+    };
+}
+
+/// Inserts a segment of `other` at a cut point of `input`
+fn crossover_insert<R: Rand>(
+    rand: &mut R,
+    input: &[u8],
+    input_cuts: &[usize],
+    other: &[u8],
+    other_cuts: &[usize],
+) -> Option<Vec<u8>> {
+    let (from, to) = random_segment(rand, other_cuts)?;
+    let at = *rand.choose(input_cuts);
+    let mut mutated = input.to_vec();
+    mutated.splice(at..at, other[from..to].iter().copied());
+    Some(mutated)
+}
+
+/// Replaces a segment of `input` with a segment of `other`
+fn crossover_replace<R: Rand>(
+    rand: &mut R,
+    input: &[u8],
+    input_cuts: &[usize],
+    other: &[u8],
+    other_cuts: &[usize],
+) -> Option<Vec<u8>> {
+    let (from, to) = random_segment(rand, other_cuts)?;
+    let (start, end) = random_segment(rand, input_cuts)?;
+    let mut mutated = input.to_vec();
+    mutated.splice(start..end, other[from..to].iter().copied());
+    Some(mutated)
+}
+
+/// Joins the head of `input` to the tail of `other`, at cut points
+fn crossover_splice<R: Rand>(
+    rand: &mut R,
+    input: &[u8],
+    input_cuts: &[usize],
+    other: &[u8],
+    other_cuts: &[usize],
+) -> Option<Vec<u8>> {
+    let at = *rand.choose(input_cuts);
+    let from = *rand.choose(other_cuts);
+    let mut mutated = input[..at].to_vec();
+    mutated.extend_from_slice(&other[from..]);
+    Some(mutated)
+}
+
+delimited_crossover_impl!(
+    DelimitedCrossoverInsertMutator,
+    "Inserts a segment of another corpus entry, cut at delimiters, after a delimiter of the input.",
+    crossover_insert
+);
+delimited_crossover_impl!(
+    DelimitedCrossoverReplaceMutator,
+    "Replaces a segment of the input with a segment of another corpus entry, both cut at delimiters.",
+    crossover_replace
+);
+delimited_crossover_impl!(
+    DelimitedSpliceMutator,
+    "Splices the input with another corpus entry, keeping the input up to a delimiter and the other entry after a delimiter.",
+    crossover_splice
+);
+
+macro_rules! line_mutator_impl {
+    ($name: ident, $doc: literal, $mutate_lines: ident) => {
+        #[doc = $doc]
+        #[derive(Default)]
+        pub struct $name<I, R, S>
+        where
+            I: Input + HasBytesVec,
+            R: Rand,
+            S: HasRand<R> + HasMaxSize,
+        {
+            phantom: PhantomData<(I, R, S)>,
+        }
+
+        impl<I, R, S> Mutator<I, S> for $name<I, R, S>
+        where
+            I: Input + HasBytesVec,
+            R: Rand,
+            S: HasRand<R> + HasMaxSize,
+        {
+            fn mutate(
+                &mut self,
+                state: &mut S,
+                input: &mut I,
+                _stage_idx: i32,
+            ) -> Result<MutationResult, Error> {
+                let mut lines = split_lines(input.bytes());
+                if lines.len() < 2 || !$mutate_lines(state.rand_mut(), &mut lines) {
+                    return Ok(MutationResult::Skipped);
+                }
+                let mutated = join_lines(&lines);
+                if mutated.len() > state.max_size() || mutated == input.bytes() {
+                    return Ok(MutationResult::Skipped);
+                }
+                *input.bytes_mut() = mutated;
+                Ok(MutationResult::Mutated)
+            }
+        }
+
+        impl<I, R, S> Named for $name<I, R, S>
+        where
+            I: Input + HasBytesVec,
+            R: Rand,
+            S: HasRand<R> + HasMaxSize,
+        {
+            fn name(&self) -> &str {
+                stringify!($name)
+            }
+        }
+
+        impl<I, R, S> $name<I, R, S>
+        where
+            I: Input + HasBytesVec,
+            R: Rand,
+            S: HasRand<R> + HasMaxSize,
+        {
+            /// Creates a new line mutator.
+            #[must_use]
+            pub fn new() -> Self {
+                Self {
+                    phantom: PhantomData,
+                }
+            }
+        }
+    };
+}
+
+/// Shuffles the lines
+fn shuffle_lines<R: Rand>(rand: &mut R, lines: &mut [Vec<u8>]) -> bool {
+    for i in (1..lines.len()).rev() {
+        let j = rand.below(i as u64 + 1) as usize;
+        lines.swap(i, j);
+    }
+    true
+}
+
+/// Duplicates a random line, inserting the copy at a random position
+fn duplicate_line<R: Rand>(rand: &mut R, lines: &mut Vec<Vec<u8>>) -> bool {
+    let line = rand.choose(lines.iter()).clone();
+    let at = rand.below(lines.len() as u64 + 1) as usize;
+    lines.insert(at, line);
+    true
+}
+
+/// Deletes a random line
+fn delete_line<R: Rand>(rand: &mut R, lines: &mut Vec<Vec<u8>>) -> bool {
+    let idx = rand.below(lines.len() as u64) as usize;
+    lines.remove(idx);
+    true
+}
+
+line_mutator_impl!(
+    LineShuffleMutator,
+    "Shuffles the lines of the input.",
+    shuffle_lines
+);
+line_mutator_impl!(
+    LineDuplicateMutator,
+    "Duplicates a random line of the input.",
+    duplicate_line
+);
+line_mutator_impl!(
+    LineDeleteMutator,
+    "Deletes a random line of the input.",
+    delete_line
+);
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bolts::rands::StdRand,
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        inputs::{BytesInput, HasBytesVec},
+        mutators::{
+            delimited::{
+                cut_points, split_lines, DelimitedCrossoverReplaceMutator, LineDeleteMutator,
+                LineDuplicateMutator, LineShuffleMutator,
+            },
+            MutationResult, Mutator,
+        },
+        state::{HasCorpus, StdState},
+    };
+
+    #[test]
+    fn test_delimited_mutations() {
+        assert_eq!(cut_points(b"GET / HTTP", b" "), vec![0, 4, 6, 10]);
+        assert_eq!(cut_points(b"a\n", b"\n"), vec![0, 2]);
+
+        let mut state = StdState::new(
+            StdRand::with_seed(1337),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            (),
+        );
+        state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(b"x=1;y=2;z=3;".to_vec())))
+            .unwrap();
+
+        let mut crossover = DelimitedCrossoverReplaceMutator::with_delimiters(b";");
+        let mut shuffle = LineShuffleMutator::new();
+        let mut duplicate = LineDuplicateMutator::new();
+        let mut delete = LineDeleteMutator::new();
+        for _ in 0..32 {
+            let mut input = BytesInput::new(b"a=1;b=2;".to_vec());
+            if crossover.mutate(&mut state, &mut input, 0).unwrap() == MutationResult::Mutated {
+                // The framing is preserved
+                assert!(input.bytes().ends_with(b";"));
+                assert!(input
+                    .bytes()
+                    .split(|b| *b == b';')
+                    .all(|s| s.is_empty() || (s.len() == 3 && s[1] == b'=')));
+            }
+
+            let text = b"one\ntwo\nthree";
+            let mut inputs = vec![BytesInput::new(text.to_vec()); 3];
+            let results = [
+                shuffle.mutate(&mut state, &mut inputs[0], 0).unwrap(),
+                duplicate.mutate(&mut state, &mut inputs[1], 0).unwrap(),
+                delete.mutate(&mut state, &mut inputs[2], 0).unwrap(),
+            ];
+            // A shuffle may give back the same order, which is not a mutation
+            assert_eq!(
+                results[0] == MutationResult::Mutated,
+                inputs[0].bytes() != text
+            );
+            assert!(results[1..].iter().all(|r| *r == MutationResult::Mutated));
+            // Only whole lines are moved
+            for input in &inputs {
+                for line in split_lines(input.bytes()) {
+                    let line = line.strip_suffix(b"\n").unwrap_or(&line);
+                    assert!([&b"one"[..], b"two", b"three"].contains(&line));
+                }
+            }
+
+            let mut same = BytesInput::new(b"x\nx\nx\n".to_vec());
+            assert_eq!(
+                shuffle.mutate(&mut state, &mut same, 0).unwrap(),
+                MutationResult::Skipped
+            );
+        }
+    }
+}
@@ -0,0 +1,583 @@
+//! Mutations for [`EncodedInput`]s, operating on token codes instead of bytes.
+
+use alloc::vec::Vec;
+use core::{cmp::min, marker::PhantomData};
+
+use crate::{
+    bolts::{
+        rands::Rand,
+        tuples::{tuple_list, tuple_list_type, Named},
+    },
+    corpus::Corpus,
+    inputs::{
+        encoded::{EncodedInput, TokenEncoderDecoder},
+        HasLen,
+    },
+    mutators::{MutationResult, Mutator},
+    state::{HasCorpus, HasMaxSize, HasRand},
+    Error,
+};
+
+/// The maximum number of codes in a run touched by a single mutation
+const MAX_CODE_RUN: u64 = 8;
+
+/// Picks a random run of codes in an input of `len` codes, `len` must be at least 1.
+/// Returns the start and the length of the run.
+fn rand_run<R>(rand: &mut R, len: usize) -> (usize, usize)
+where
+    R: Rand,
+{
+    let start = rand.below(len as u64) as usize;
+    let max_run = min((len - start) as u64, MAX_CODE_RUN);
+    (start, 1 + rand.below(max_run) as usize)
+}
+
+/// Replaces a random code with another code known to the [`TokenEncoderDecoder`]
+pub struct EncodedRandMutator<'a, R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    codec: &'a TokenEncoderDecoder,
+    phantom: PhantomData<(R, S)>,
+}
+
+impl<'a, R, S> Mutator<EncodedInput, S> for EncodedRandMutator<'a, R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut EncodedInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.is_empty() || self.codec.len() < 2 {
+            return Ok(MutationResult::Skipped);
+        }
+        let idx = state.rand_mut().below(input.len() as u64) as usize;
+        let code = state.rand_mut().below(self.codec.len() as u64) as u32;
+        let old = &mut input.codes_mut()[idx];
+        if *old == code {
+            return Ok(MutationResult::Skipped);
+        }
+        *old = code;
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<'a, R, S> Named for EncodedRandMutator<'a, R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    fn name(&self) -> &str {
+        "EncodedRandMutator"
+    }
+}
+
+impl<'a, R, S> EncodedRandMutator<'a, R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    /// Creates a new [`EncodedRandMutator`], picking codes known to the given codec.
+    #[must_use]
+    pub fn new(codec: &'a TokenEncoderDecoder) -> Self {
+        Self {
+            codec,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Inserts a run of random codes known to the [`TokenEncoderDecoder`] at a random position
+pub struct EncodedInsertMutator<'a, R, S>
+where
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    codec: &'a TokenEncoderDecoder,
+    phantom: PhantomData<(R, S)>,
+}
+
+impl<'a, R, S> Mutator<EncodedInput, S> for EncodedInsertMutator<'a, R, S>
+where
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut EncodedInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let max_size = state.max_size();
+        if self.codec.is_empty() || input.len() >= max_size {
+            return Ok(MutationResult::Skipped);
+        }
+        let off = state.rand_mut().below((input.len() + 1) as u64) as usize;
+        let run = min(
+            1 + state.rand_mut().below(MAX_CODE_RUN) as usize,
+            max_size - input.len(),
+        );
+        let codes: Vec<u32> = (0..run)
+            .map(|_| state.rand_mut().below(self.codec.len() as u64) as u32)
+            .collect();
+        input.codes_mut().splice(off..off, codes);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<'a, R, S> Named for EncodedInsertMutator<'a, R, S>
+where
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    fn name(&self) -> &str {
+        "EncodedInsertMutator"
+    }
+}
+
+impl<'a, R, S> EncodedInsertMutator<'a, R, S>
+where
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    /// Creates a new [`EncodedInsertMutator`], inserting codes known to the given codec.
+    #[must_use]
+    pub fn new(codec: &'a TokenEncoderDecoder) -> Self {
+        Self {
+            codec,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Deletes a random run of codes
+#[derive(Default)]
+pub struct EncodedDeleteMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    phantom: PhantomData<(R, S)>,
+}
+
+impl<R, S> Mutator<EncodedInput, S> for EncodedDeleteMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut EncodedInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.len() <= 1 {
+            return Ok(MutationResult::Skipped);
+        }
+        let (start, run) = rand_run(state.rand_mut(), input.len());
+        input.codes_mut().drain(start..start + run);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<R, S> Named for EncodedDeleteMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    fn name(&self) -> &str {
+        "EncodedDeleteMutator"
+    }
+}
+
+impl<R, S> EncodedDeleteMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    /// Creates a new [`EncodedDeleteMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Copies a random run of codes over another position of the same input
+#[derive(Default)]
+pub struct EncodedCopyMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    phantom: PhantomData<(R, S)>,
+}
+
+impl<R, S> Mutator<EncodedInput, S> for EncodedCopyMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut EncodedInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.len() < 2 {
+            return Ok(MutationResult::Skipped);
+        }
+        let (from, run) = rand_run(state.rand_mut(), input.len());
+        let to = state.rand_mut().below((input.len() - run + 1) as u64) as usize;
+        if from == to {
+            return Ok(MutationResult::Skipped);
+        }
+        input.codes_mut().copy_within(from..from + run, to);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<R, S> Named for EncodedCopyMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    fn name(&self) -> &str {
+        "EncodedCopyMutator"
+    }
+}
+
+impl<R, S> EncodedCopyMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    /// Creates a new [`EncodedCopyMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Duplicates a random run of codes, inserting the copy at a random position
+#[derive(Default)]
+pub struct EncodedInsertCopyMutator<R, S>
+where
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    phantom: PhantomData<(R, S)>,
+}
+
+impl<R, S> Mutator<EncodedInput, S> for EncodedInsertCopyMutator<R, S>
+where
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut EncodedInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let (from, run) = rand_run(state.rand_mut(), input.len());
+        if input.len() + run > state.max_size() {
+            return Ok(MutationResult::Skipped);
+        }
+        let to = state.rand_mut().below((input.len() + 1) as u64) as usize;
+        let copy = input.codes()[from..from + run].to_vec();
+        input.codes_mut().splice(to..to, copy);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<R, S> Named for EncodedInsertCopyMutator<R, S>
+where
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    fn name(&self) -> &str {
+        "EncodedInsertCopyMutator"
+    }
+}
+
+impl<R, S> EncodedInsertCopyMutator<R, S>
+where
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    /// Creates a new [`EncodedInsertCopyMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Inserts a run of codes taken from another testcase
+#[derive(Default)]
+pub struct EncodedCrossoverInsertMutator<C, R, S>
+where
+    C: Corpus<EncodedInput>,
+    S: HasRand<R> + HasCorpus<C, EncodedInput> + HasMaxSize,
+    R: Rand,
+{
+    phantom: PhantomData<(C, R, S)>,
+}
+
+impl<C, R, S> Mutator<EncodedInput, S> for EncodedCrossoverInsertMutator<C, R, S>
+where
+    C: Corpus<EncodedInput>,
+    S: HasRand<R> + HasCorpus<C, EncodedInput> + HasMaxSize,
+    R: Rand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut EncodedInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        // We don't want to use the testcase we're already using for splicing
+        let count = state.corpus().count();
+        if count == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let nth = state.rand_mut().below(count as u64) as usize;
+        let idx = state
+            .corpus()
+            .nth(nth)
+            .ok_or_else(|| Error::Empty("No entries in corpus".into()))?;
+        if let Some(cur) = state.corpus().current() {
+            if idx == *cur {
+                return Ok(MutationResult::Skipped);
+            }
+        }
+
+        let other_len = state
+            .corpus()
+            .get(idx)?
+            .borrow_mut()
+            .load_input()?
+            .codes()
+            .len();
+        if other_len == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let (from, run) = rand_run(state.rand_mut(), other_len);
+        if input.len() + run > state.max_size() {
+            return Ok(MutationResult::Skipped);
+        }
+        let to = state.rand_mut().below((input.len() + 1) as u64) as usize;
+
+        let mut other_testcase = state.corpus().get(idx)?.borrow_mut();
+        let other = other_testcase.load_input()?;
+        input
+            .codes_mut()
+            .splice(to..to, other.codes()[from..from + run].iter().copied());
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<C, R, S> Named for EncodedCrossoverInsertMutator<C, R, S>
+where
+    C: Corpus<EncodedInput>,
+    S: HasRand<R> + HasCorpus<C, EncodedInput> + HasMaxSize,
+    R: Rand,
+{
+    fn name(&self) -> &str {
+        "EncodedCrossoverInsertMutator"
+    }
+}
+
+impl<C, R, S> EncodedCrossoverInsertMutator<C, R, S>
+where
+    C: Corpus<EncodedInput>,
+    S: HasRand<R> + HasCorpus<C, EncodedInput> + HasMaxSize,
+    R: Rand,
+{
+    /// Creates a new [`EncodedCrossoverInsertMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Overwrites a run of codes with codes taken from another testcase
+#[derive(Default)]
+pub struct EncodedCrossoverReplaceMutator<C, R, S>
+where
+    C: Corpus<EncodedInput>,
+    S: HasRand<R> + HasCorpus<C, EncodedInput>,
+    R: Rand,
+{
+    phantom: PhantomData<(C, R, S)>,
+}
+
+impl<C, R, S> Mutator<EncodedInput, S> for EncodedCrossoverReplaceMutator<C, R, S>
+where
+    C: Corpus<EncodedInput>,
+    S: HasRand<R> + HasCorpus<C, EncodedInput>,
+    R: Rand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut EncodedInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        // We don't want to use the testcase we're already using for splicing
+        let count = state.corpus().count();
+        if count == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let nth = state.rand_mut().below(count as u64) as usize;
+        let idx = state
+            .corpus()
+            .nth(nth)
+            .ok_or_else(|| Error::Empty("No entries in corpus".into()))?;
+        if let Some(cur) = state.corpus().current() {
+            if idx == *cur {
+                return Ok(MutationResult::Skipped);
+            }
+        }
+
+        let other_len = state
+            .corpus()
+            .get(idx)?
+            .borrow_mut()
+            .load_input()?
+            .codes()
+            .len();
+        if other_len == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let (from, run) = rand_run(state.rand_mut(), min(other_len, input.len()));
+        let to = state.rand_mut().below((input.len() - run + 1) as u64) as usize;
+
+        let mut other_testcase = state.corpus().get(idx)?.borrow_mut();
+        let other = other_testcase.load_input()?;
+        input.codes_mut()[to..to + run].copy_from_slice(&other.codes()[from..from + run]);
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<C, R, S> Named for EncodedCrossoverReplaceMutator<C, R, S>
+where
+    C: Corpus<EncodedInput>,
+    S: HasRand<R> + HasCorpus<C, EncodedInput>,
+    R: Rand,
+{
+    fn name(&self) -> &str {
+        "EncodedCrossoverReplaceMutator"
+    }
+}
+
+impl<C, R, S> EncodedCrossoverReplaceMutator<C, R, S>
+where
+    C: Corpus<EncodedInput>,
+    S: HasRand<R> + HasCorpus<C, EncodedInput>,
+    R: Rand,
+{
+    /// Creates a new [`EncodedCrossoverReplaceMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Get the mutations for [`EncodedInput`]s over the codes of the given [`TokenEncoderDecoder`]
+#[must_use]
+pub fn encoded_mutations<'a, C, R, S>(
+    codec: &'a TokenEncoderDecoder,
+) -> tuple_list_type!(
+       EncodedRandMutator<'a, R, S>,
+       EncodedInsertMutator<'a, R, S>,
+       EncodedDeleteMutator<R, S>,
+       EncodedCopyMutator<R, S>,
+       EncodedInsertCopyMutator<R, S>,
+       EncodedCrossoverInsertMutator<C, R, S>,
+       EncodedCrossoverReplaceMutator<C, R, S>,
+   )
+where
+    C: Corpus<EncodedInput>,
+    S: HasRand<R> + HasCorpus<C, EncodedInput> + HasMaxSize,
+    R: Rand,
+{
+    tuple_list!(
+        EncodedRandMutator::new(codec),
+        EncodedInsertMutator::new(codec),
+        EncodedDeleteMutator::new(),
+        EncodedCopyMutator::new(),
+        EncodedInsertCopyMutator::new(),
+        EncodedCrossoverInsertMutator::new(),
+        EncodedCrossoverReplaceMutator::new(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bolts::{rands::StdRand, tuples::HasLen as _},
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        inputs::encoded::{
+            EncodedInput, InputDecoder, InputEncoder, NaiveTokenizer, TokenEncoderDecoder,
+        },
+        mutators::{encoded_mutations::encoded_mutations, MutatorsTuple},
+        state::StdState,
+    };
+
+    #[test]
+    fn test_encoded_mutators() {
+        let mut codec = TokenEncoderDecoder::new();
+        let tokenizer = NaiveTokenizer::new();
+        let mut corpus: InMemoryCorpus<EncodedInput> = InMemoryCorpus::new();
+        for seed in &[&b"let x = 1;"[..], b"print(x + y);", b"while (true) {}"] {
+            corpus
+                .add(Testcase::new(codec.encode(seed, &tokenizer).unwrap()))
+                .unwrap();
+        }
+        let input = corpus
+            .get(corpus.first().unwrap())
+            .unwrap()
+            .borrow()
+            .input()
+            .clone()
+            .unwrap();
+        let mut state = StdState::new(StdRand::with_seed(1337), corpus, InMemoryCorpus::new(), ());
+
+        let mut mutations = encoded_mutations(&codec);
+        let mut bytes = vec![];
+        for idx in 0..mutations.len() {
+            for _ in 0..32 {
+                let mut mutant = input.clone();
+                mutations
+                    .get_and_mutate(idx, &mut state, &mut mutant, 0)
+                    .unwrap();
+                // Mutants must only use known codes, so they can always be decoded
+                bytes.clear();
+                codec.decode(&mutant, &mut bytes).unwrap();
+            }
+        }
+    }
+}
@@ -0,0 +1,363 @@
+//! Nautilus-style mutations for [`GrammarInput`]s, operating on derivation trees instead of bytes.
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use crate::{
+    bolts::{
+        rands::Rand,
+        tuples::{tuple_list, tuple_list_type, Named},
+    },
+    corpus::Corpus,
+    inputs::{
+        grammar::{Grammar, GrammarInput, GrammarNode},
+        HasLen,
+    },
+    mutators::{MutationResult, Mutator},
+    state::{HasCorpus, HasRand},
+    Error,
+};
+
+/// The maximum number of additional nestings added by a [`GrammarRecursionMutator`]
+const MAX_RECURSIONS: u64 = 4;
+
+/// Replaces a random subtree with a newly generated derivation of the same nonterminal
+pub struct GrammarRandomMutator<'a, R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    grammar: &'a Grammar,
+    max_depth: usize,
+    phantom: PhantomData<(R, S)>,
+}
+
+impl<'a, R, S> Mutator<GrammarInput, S> for GrammarRandomMutator<'a, R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut GrammarInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let idx = state.rand_mut().below(input.len() as u64) as usize;
+        let depth = input.root().depth_of(idx).unwrap();
+        let node = input.root_mut().get_mut(idx).unwrap();
+        let nonterm = self.grammar.nonterm_of(node);
+        *node = self.grammar.generate(
+            state.rand_mut(),
+            nonterm,
+            self.max_depth.saturating_sub(depth),
+        );
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<'a, R, S> Named for GrammarRandomMutator<'a, R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    fn name(&self) -> &str {
+        "GrammarRandomMutator"
+    }
+}
+
+impl<'a, R, S> GrammarRandomMutator<'a, R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    /// Creates a new [`GrammarRandomMutator`], keeping trees below `max_depth` where the grammar allows it.
+    #[must_use]
+    pub fn new(grammar: &'a Grammar, max_depth: usize) -> Self {
+        Self {
+            grammar,
+            max_depth,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Replaces a random subtree with a subtree of the same nonterminal, taken from another testcase
+pub struct GrammarSpliceMutator<'a, C, R, S>
+where
+    C: Corpus<GrammarInput>,
+    S: HasRand<R> + HasCorpus<C, GrammarInput>,
+    R: Rand,
+{
+    grammar: &'a Grammar,
+    phantom: PhantomData<(C, R, S)>,
+}
+
+impl<'a, C, R, S> Mutator<GrammarInput, S> for GrammarSpliceMutator<'a, C, R, S>
+where
+    C: Corpus<GrammarInput>,
+    S: HasRand<R> + HasCorpus<C, GrammarInput>,
+    R: Rand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut GrammarInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        // We don't want to use the testcase we're already using for splicing
+        let count = state.corpus().count();
+        if count == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let nth = state.rand_mut().below(count as u64) as usize;
+        let idx = state
+            .corpus()
+            .nth(nth)
+            .ok_or_else(|| Error::Empty("No entries in corpus".into()))?;
+        if let Some(cur) = state.corpus().current() {
+            if idx == *cur {
+                return Ok(MutationResult::Skipped);
+            }
+        }
+
+        let target = state.rand_mut().below(input.len() as u64) as usize;
+        let nonterm = self.grammar.nonterm_of(input.root().get(target).unwrap());
+
+        let candidates = {
+            let mut other_testcase = state.corpus().get(idx)?.borrow_mut();
+            let other = other_testcase.load_input()?;
+            let mut candidates = vec![];
+            let mut other_idx = 0;
+            other.root().for_each(&mut |n| {
+                if self.grammar.nonterm_of(n) == nonterm {
+                    candidates.push(other_idx);
+                }
+                other_idx += 1;
+            });
+            candidates
+        };
+        if candidates.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let from = *state.rand_mut().choose(&candidates);
+
+        let mut other_testcase = state.corpus().get(idx)?.borrow_mut();
+        let other = other_testcase.load_input()?;
+        *input.root_mut().get_mut(target).unwrap() = other.root().get(from).unwrap().clone();
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<'a, C, R, S> Named for GrammarSpliceMutator<'a, C, R, S>
+where
+    C: Corpus<GrammarInput>,
+    S: HasRand<R> + HasCorpus<C, GrammarInput>,
+    R: Rand,
+{
+    fn name(&self) -> &str {
+        "GrammarSpliceMutator"
+    }
+}
+
+impl<'a, C, R, S> GrammarSpliceMutator<'a, C, R, S>
+where
+    C: Corpus<GrammarInput>,
+    S: HasRand<R> + HasCorpus<C, GrammarInput>,
+    R: Rand,
+{
+    /// Creates a new [`GrammarSpliceMutator`].
+    #[must_use]
+    pub fn new(grammar: &'a Grammar) -> Self {
+        Self {
+            grammar,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Collects all `(ancestor, descendant)` pre-order index pairs expanding the same nonterminal
+fn recursion_pairs(grammar: &Grammar, root: &GrammarNode) -> Vec<(usize, usize)> {
+    fn visit(
+        grammar: &Grammar,
+        node: &GrammarNode,
+        idx: &mut usize,
+        ancestors: &mut Vec<(usize, usize)>,
+        pairs: &mut Vec<(usize, usize)>,
+    ) {
+        let nonterm = grammar.nonterm_of(node);
+        let own_idx = *idx;
+        for (anc_nonterm, anc_idx) in ancestors.iter() {
+            if *anc_nonterm == nonterm {
+                pairs.push((*anc_idx, own_idx));
+            }
+        }
+        *idx += 1;
+        ancestors.push((nonterm, own_idx));
+        for child in node.children() {
+            visit(grammar, child, idx, ancestors, pairs);
+        }
+        ancestors.pop();
+    }
+
+    let mut pairs = vec![];
+    visit(grammar, root, &mut 0, &mut vec![], &mut pairs);
+    pairs
+}
+
+/// Finds a recursive derivation (a nonterminal deriving itself) and repeats it a random number of times
+pub struct GrammarRecursionMutator<'a, R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    grammar: &'a Grammar,
+    max_depth: usize,
+    phantom: PhantomData<(R, S)>,
+}
+
+impl<'a, R, S> Mutator<GrammarInput, S> for GrammarRecursionMutator<'a, R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut GrammarInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let pairs = recursion_pairs(self.grammar, input.root());
+        if pairs.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let (outer, inner) = *state.rand_mut().choose(&pairs);
+        let recursions = 1 + state.rand_mut().below(MAX_RECURSIONS);
+
+        // Each iteration wraps the derivation in one more copy of the recursive ancestor
+        let ancestor = input.root().get(outer).unwrap().clone();
+        let mut nested = ancestor.clone();
+        for _ in 0..recursions {
+            let mut wrapper = ancestor.clone();
+            *wrapper.get_mut(inner - outer).unwrap() = nested;
+            nested = wrapper;
+        }
+
+        if input.root().depth_of(outer).unwrap() + nested.depth() > self.max_depth {
+            return Ok(MutationResult::Skipped);
+        }
+        *input.root_mut().get_mut(outer).unwrap() = nested;
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<'a, R, S> Named for GrammarRecursionMutator<'a, R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    fn name(&self) -> &str {
+        "GrammarRecursionMutator"
+    }
+}
+
+impl<'a, R, S> GrammarRecursionMutator<'a, R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    /// Creates a new [`GrammarRecursionMutator`], skipping expansions deeper than `max_depth`.
+    #[must_use]
+    pub fn new(grammar: &'a Grammar, max_depth: usize) -> Self {
+        Self {
+            grammar,
+            max_depth,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Get the mutations for [`GrammarInput`]s over the given [`Grammar`]
+#[must_use]
+pub fn grammar_mutations<'a, C, R, S>(
+    grammar: &'a Grammar,
+    max_depth: usize,
+) -> tuple_list_type!(
+    GrammarRandomMutator<'a, R, S>,
+    GrammarSpliceMutator<'a, C, R, S>,
+    GrammarRecursionMutator<'a, R, S>,
+)
+where
+    C: Corpus<GrammarInput>,
+    S: HasRand<R> + HasCorpus<C, GrammarInput>,
+    R: Rand,
+{
+    tuple_list!(
+        GrammarRandomMutator::new(grammar, max_depth),
+        GrammarSpliceMutator::new(grammar),
+        GrammarRecursionMutator::new(grammar, max_depth),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bolts::rands::StdRand,
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        generators::{Generator, GrammarGenerator},
+        inputs::grammar::{Grammar, GrammarInput},
+        mutators::{
+            grammar::{GrammarRandomMutator, GrammarRecursionMutator, GrammarSpliceMutator},
+            MutationResult, Mutator,
+        },
+        state::{HasRand, StdState},
+    };
+
+    #[test]
+    fn test_grammar_mutators() {
+        let grammar = Grammar::from_rules(&[
+            ("START", "{EXPR}"),
+            ("EXPR", "{EXPR} + {EXPR}"),
+            ("EXPR", "({EXPR})"),
+            ("EXPR", "{NUM}"),
+            ("NUM", "1"),
+            ("NUM", "2"),
+        ])
+        .unwrap();
+        let mut generator = GrammarGenerator::new(&grammar, 8);
+
+        let mut rand = StdRand::with_seed(1337);
+        let mut corpus: InMemoryCorpus<GrammarInput> = InMemoryCorpus::new();
+        for _ in 0..4 {
+            corpus
+                .add(Testcase::new(generator.generate(&mut rand).unwrap()))
+                .unwrap();
+        }
+        let mut state = StdState::new(rand, corpus, InMemoryCorpus::new(), ());
+
+        let mut random = GrammarRandomMutator::new(&grammar, 8);
+        let mut splice = GrammarSpliceMutator::new(&grammar);
+        let mut recursion = GrammarRecursionMutator::new(&grammar, 12);
+
+        let mut bytes = vec![];
+        let mut input = generator.generate(state.rand_mut()).unwrap();
+        let mut recursed = false;
+        for i in 0..50 {
+            random.mutate(&mut state, &mut input, i).unwrap();
+            splice.mutate(&mut state, &mut input, i).unwrap();
+            let before = input.root().size();
+            if recursion.mutate(&mut state, &mut input, i).unwrap() == MutationResult::Mutated {
+                assert!(input.root().size() > before);
+                recursed = true;
+            }
+
+            // Whatever happened, the tree must still derive a valid expression
+            input.unparse(&grammar, &mut bytes);
+            assert!(!bytes.is_empty());
+            assert!(bytes.iter().all(|b| b" +()12".contains(b)));
+        }
+        assert!(recursed);
+    }
+}
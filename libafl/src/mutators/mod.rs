@@ -6,9 +6,38 @@ pub mod mutations;
 pub use mutations::*;
 pub mod token_mutations;
 pub use token_mutations::*;
+pub mod grammar;
+pub use grammar::*;
+pub mod token_input;
+pub use token_input::*;
+pub mod multi;
+pub use multi::*;
+pub mod structured;
+pub use structured::*;
+pub mod encoded_mutations;
+pub use encoded_mutations::*;
+pub mod bounded;
+pub use bounded::*;
+pub mod mopt_mutator;
+pub use mopt_mutator::*;
+pub mod text_mutations;
+pub use text_mutations::*;
+pub mod delimited;
+pub use delimited::*;
+pub mod stacking;
+pub use stacking::*;
+#[cfg(all(feature = "std", unix))]
+pub mod afl_custom;
+#[cfg(all(feature = "std", unix))]
+pub use afl_custom::*;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "python")]
+pub use python::PythonMutator;
 
 use crate::{
     bolts::tuples::{HasLen, Named},
+    corpus::CorpusId,
     inputs::Input,
     Error,
 };
@@ -41,12 +70,14 @@ where
         stage_idx: i32,
     ) -> Result<MutationResult, Error>;
 
-    /// Post-process given the outcome of the execution
+    /// Post-process given the outcome of the execution.
+    /// Called after every call to [`Mutator::mutate`], also for [`MutationResult::Skipped`] mutations,
+    /// which are not executed: those never produce a `corpus_idx`.
     fn post_exec(
         &mut self,
         _state: &mut S,
         _stage_idx: i32,
-        _corpus_idx: Option<usize>,
+        _corpus_idx: Option<CorpusId>,
     ) -> Result<(), Error> {
         Ok(())
     }
@@ -70,7 +101,7 @@ where
         &mut self,
         state: &mut S,
         stage_idx: i32,
-        corpus_idx: Option<usize>,
+        corpus_idx: Option<CorpusId>,
     ) -> Result<(), Error>;
 
     /// Gets the [`Mutator`] at the given index and runs the `mutate` function on it.
@@ -88,7 +119,7 @@ where
         index: usize,
         state: &mut S,
         stage_idx: i32,
-        corpus_idx: Option<usize>,
+        corpus_idx: Option<CorpusId>,
     ) -> Result<(), Error>;
 }
 
@@ -109,7 +140,7 @@ where
         &mut self,
         _state: &mut S,
         _stage_idx: i32,
-        _corpus_idx: Option<usize>,
+        _corpus_idx: Option<CorpusId>,
     ) -> Result<(), Error> {
         Ok(())
     }
@@ -129,7 +160,7 @@ where
         _index: usize,
         _state: &mut S,
         _stage_idx: i32,
-        _corpus_idx: Option<usize>,
+        _corpus_idx: Option<CorpusId>,
     ) -> Result<(), Error> {
         Ok(())
     }
@@ -159,7 +190,7 @@ where
         &mut self,
         state: &mut S,
         stage_idx: i32,
-        corpus_idx: Option<usize>,
+        corpus_idx: Option<CorpusId>,
     ) -> Result<(), Error> {
         self.0.post_exec(state, stage_idx, corpus_idx)?;
         self.1.post_exec_all(state, stage_idx, corpus_idx)
@@ -184,7 +215,7 @@ where
         index: usize,
         state: &mut S,
         stage_idx: i32,
-        corpus_idx: Option<usize>,
+        corpus_idx: Option<CorpusId>,
     ) -> Result<(), Error> {
         if index == 0 {
             self.0.post_exec(state, stage_idx, corpus_idx)
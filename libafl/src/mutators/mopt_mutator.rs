@@ -0,0 +1,565 @@
+//! The `MOpt` mutation scheduler, selecting mutations with a particle swarm optimization.
+//! See the [MOpt paper](https://www.usenix.org/conference/usenixsecurity19/presentation/lyu) and
+//! [MOpt-AFL](https://github.com/puppet-meteor/MOpt-AFL) for details.
+
+use alloc::vec::Vec;
+use core::{
+    fmt::{self, Debug},
+    marker::PhantomData,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bolts::rands::{Rand, StdRand},
+    corpus::{Corpus, CorpusId},
+    inputs::Input,
+    mutators::{ComposedByMutations, MutationResult, Mutator, MutatorsTuple, ScheduledMutator},
+    state::{HasCorpus, HasMetadata, HasRand, HasSolutions},
+    Error,
+};
+
+/// The number of executions each swarm gets in the pilot fuzzing module
+pub const MOPT_PERIOD_PILOT: usize = 50_000;
+/// The number of executions in the core fuzzing module, before the swarms get updated
+pub const MOPT_PERIOD_CORE: usize = 500_000;
+/// The default number of swarms
+pub const MOPT_DEFAULT_SWARM_NUM: usize = 5;
+
+/// The bounds for the position of particles, i.e., the unnormalized probability of each operator
+const V_MIN: f64 = 0.05;
+const V_MAX: f64 = 1.0;
+
+/// The module of `MOpt` currently running
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MOptMode {
+    /// Each swarm is evaluated in turn, to find the most efficient one
+    Pilotfuzzing,
+    /// The best swarm is used to fuzz, gathering the global efficiency of the operators
+    Corefuzzing,
+}
+
+/// The state of the `MOpt` particle swarm, stored in the state metadata.
+/// Each swarm holds one particle per operator, whose position is the probability of selecting that operator.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MOpt {
+    /// Our own rand, as the metadata can't borrow the rand of the state
+    rand: StdRand,
+    /// The corpus entries and objectives found so far
+    total_finds: u64,
+    /// `total_finds` at the start of the current swarm evaluation
+    finds_until_last_swarm: u64,
+    /// The inertia weight, decreasing from `w_init` to `w_end` over `g_max` updates
+    w_init: f64,
+    w_end: f64,
+    w_now: f64,
+    g_now: usize,
+    g_max: usize,
+    operator_num: usize,
+    swarm_num: usize,
+    period_pilot: usize,
+    period_core: usize,
+    /// The executions so far in the current period
+    period_time: usize,
+    /// The swarm currently used
+    swarm_now: usize,
+    x_now: Vec<Vec<f64>>,
+    v_now: Vec<Vec<f64>>,
+    l_best: Vec<Vec<f64>>,
+    eff_best: Vec<Vec<f64>>,
+    g_best: Vec<f64>,
+    /// The cumulative selection probabilities of each swarm
+    probability_now: Vec<Vec<f64>>,
+    swarm_fitness: Vec<f64>,
+    pilot_operator_finds: Vec<Vec<u64>>,
+    pilot_operator_finds_v2: Vec<Vec<u64>>,
+    pilot_operator_cycles: Vec<Vec<u64>>,
+    pilot_operator_cycles_v2: Vec<Vec<u64>>,
+    core_operator_finds: Vec<u64>,
+    core_operator_finds_v2: Vec<u64>,
+    core_operator_cycles: Vec<u64>,
+    core_operator_cycles_v2: Vec<u64>,
+    mode: MOptMode,
+}
+
+crate::impl_serdeany!(MOpt);
+
+impl MOpt {
+    /// Creates a new [`MOpt`] for `operator_num` operators, with `swarm_num` swarms
+    pub fn new(operator_num: usize, swarm_num: usize, seed: u64) -> Result<Self, Error> {
+        if operator_num == 0 || swarm_num == 0 {
+            return Err(Error::IllegalArgument(
+                "MOpt needs at least one operator and one swarm".into(),
+            ));
+        }
+        let mut mopt = Self {
+            rand: StdRand::with_seed(seed),
+            total_finds: 0,
+            finds_until_last_swarm: 0,
+            w_init: 0.9,
+            w_end: 0.3,
+            w_now: 0.0,
+            g_now: 0,
+            g_max: 5000,
+            operator_num,
+            swarm_num,
+            period_pilot: MOPT_PERIOD_PILOT,
+            period_core: MOPT_PERIOD_CORE,
+            period_time: 0,
+            swarm_now: 0,
+            x_now: vec![vec![0.0; operator_num]; swarm_num],
+            v_now: vec![vec![0.0; operator_num]; swarm_num],
+            l_best: vec![vec![0.0; operator_num]; swarm_num],
+            eff_best: vec![vec![0.0; operator_num]; swarm_num],
+            g_best: vec![0.0; operator_num],
+            probability_now: vec![vec![0.0; operator_num]; swarm_num],
+            swarm_fitness: vec![0.0; swarm_num],
+            pilot_operator_finds: vec![vec![0; operator_num]; swarm_num],
+            pilot_operator_finds_v2: vec![vec![0; operator_num]; swarm_num],
+            pilot_operator_cycles: vec![vec![0; operator_num]; swarm_num],
+            pilot_operator_cycles_v2: vec![vec![0; operator_num]; swarm_num],
+            core_operator_finds: vec![0; operator_num],
+            core_operator_finds_v2: vec![0; operator_num],
+            core_operator_cycles: vec![0; operator_num],
+            core_operator_cycles_v2: vec![0; operator_num],
+            mode: MOptMode::Pilotfuzzing,
+        };
+        mopt.pso_initialize();
+        Ok(mopt)
+    }
+
+    /// The module currently running
+    #[must_use]
+    pub fn mode(&self) -> MOptMode {
+        self.mode
+    }
+
+    /// The number of operators scheduled by this [`MOpt`]
+    #[must_use]
+    pub fn operator_num(&self) -> usize {
+        self.operator_num
+    }
+
+    /// The swarm currently used to select operators
+    #[must_use]
+    pub fn swarm_now(&self) -> usize {
+        self.swarm_now
+    }
+
+    /// The probability of selecting each operator, in the current swarm
+    #[must_use]
+    pub fn probabilities(&self) -> Vec<f64> {
+        let cumulative = &self.probability_now[self.swarm_now];
+        (0..self.operator_num)
+            .map(|i| cumulative[i] - if i == 0 { 0.0 } else { cumulative[i - 1] })
+            .collect()
+    }
+
+    /// A random float in `[0, 1)`
+    fn rand_f64(&mut self) -> f64 {
+        (self.rand.next() >> 11) as f64 / (1_u64 << 53) as f64
+    }
+
+    /// Normalizes the positions of the particles of a swarm, and updates its selection probabilities
+    fn update_probabilities(&mut self, swarm: usize) {
+        let sum: f64 = self.x_now[swarm].iter().sum();
+        let mut cumulative = 0.0;
+        for i in 0..self.operator_num {
+            self.x_now[swarm][i] /= sum;
+            cumulative += self.x_now[swarm][i];
+            self.probability_now[swarm][i] = cumulative;
+        }
+    }
+
+    /// Places the particles of all swarms randomly
+    fn pso_initialize(&mut self) {
+        self.g_now = 0;
+        self.w_now = self.w_init;
+        for swarm in 0..self.swarm_num {
+            for i in 0..self.operator_num {
+                self.x_now[swarm][i] = 0.1 + 0.7 * self.rand_f64();
+                self.v_now[swarm][i] = 0.1;
+                self.l_best[swarm][i] = 0.5;
+                self.g_best[i] = 0.5;
+            }
+            self.update_probabilities(swarm);
+        }
+    }
+
+    /// Moves the particles of all swarms towards their local and the global best positions
+    fn pso_update(&mut self) {
+        self.g_now += 1;
+        if self.g_now > self.g_max {
+            self.g_now = 0;
+        }
+        self.w_now = (self.w_init - self.w_end) * (self.g_max - self.g_now) as f64
+            / self.g_max as f64
+            + self.w_end;
+        for swarm in 0..self.swarm_num {
+            for i in 0..self.operator_num {
+                let x = self.x_now[swarm][i];
+                let v = self.w_now * self.v_now[swarm][i]
+                    + self.rand_f64() * (self.l_best[swarm][i] - x)
+                    + self.rand_f64() * (self.g_best[i] - x);
+                self.v_now[swarm][i] = v;
+                self.x_now[swarm][i] = (x + v).clamp(V_MIN, V_MAX);
+            }
+            self.update_probabilities(swarm);
+        }
+    }
+
+    /// Selects the next operator, according to the probabilities of the current swarm
+    pub fn select_operator(&mut self) -> usize {
+        let total = self.probability_now[self.swarm_now][self.operator_num - 1];
+        let r = self.rand_f64() * total;
+        self.probability_now[self.swarm_now]
+            .iter()
+            .position(|p| r < *p)
+            .unwrap_or(self.operator_num - 1)
+    }
+
+    /// Records an execution of an input mutated with the given operators, and the number of new corpus entries and objectives it led to
+    pub fn record(&mut self, operators: &[usize], new_finds: u64) {
+        self.total_finds += new_finds;
+        match self.mode {
+            MOptMode::Pilotfuzzing => {
+                let swarm = self.swarm_now;
+                for op in operators {
+                    self.pilot_operator_cycles_v2[swarm][*op] += 1;
+                    if new_finds > 0 {
+                        self.pilot_operator_finds_v2[swarm][*op] += 1;
+                    }
+                }
+                self.period_time += 1;
+                if self.period_time >= self.period_pilot {
+                    self.end_pilot_period();
+                }
+            }
+            MOptMode::Corefuzzing => {
+                for op in operators {
+                    self.core_operator_cycles_v2[*op] += 1;
+                    if new_finds > 0 {
+                        self.core_operator_finds_v2[*op] += 1;
+                    }
+                }
+                self.period_time += 1;
+                if self.period_time >= self.period_core {
+                    self.end_core_period();
+                }
+            }
+        }
+    }
+
+    /// Rates the current swarm and its operators, then moves to the next swarm, or to core fuzzing
+    fn end_pilot_period(&mut self) {
+        let swarm = self.swarm_now;
+        self.period_time = 0;
+        self.swarm_fitness[swarm] =
+            (self.total_finds - self.finds_until_last_swarm) as f64 / self.period_pilot as f64;
+        self.finds_until_last_swarm = self.total_finds;
+
+        for i in 0..self.operator_num {
+            let cycles =
+                self.pilot_operator_cycles_v2[swarm][i] - self.pilot_operator_cycles[swarm][i];
+            if cycles > 0 {
+                let finds =
+                    self.pilot_operator_finds_v2[swarm][i] - self.pilot_operator_finds[swarm][i];
+                let eff = finds as f64 / cycles as f64;
+                if eff > self.eff_best[swarm][i] {
+                    self.eff_best[swarm][i] = eff;
+                    self.l_best[swarm][i] = self.x_now[swarm][i];
+                }
+            }
+            self.pilot_operator_finds[swarm][i] = self.pilot_operator_finds_v2[swarm][i];
+            self.pilot_operator_cycles[swarm][i] = self.pilot_operator_cycles_v2[swarm][i];
+        }
+
+        self.swarm_now += 1;
+        if self.swarm_now == self.swarm_num {
+            // All swarms got evaluated, fuzz with the fittest one
+            let mut best = 0;
+            for i in 1..self.swarm_num {
+                if self.swarm_fitness[i] > self.swarm_fitness[best] {
+                    best = i;
+                }
+            }
+            self.swarm_now = best;
+            self.mode = MOptMode::Corefuzzing;
+        }
+    }
+
+    /// Computes the global efficiency of each operator, moves the swarms, and goes back to pilot fuzzing
+    fn end_core_period(&mut self) {
+        self.period_time = 0;
+        let total: u64 = self.core_operator_finds_v2.iter().sum();
+        if total > 0 {
+            for i in 0..self.operator_num {
+                self.g_best[i] = self.core_operator_finds_v2[i] as f64 / total as f64;
+            }
+        }
+        self.core_operator_finds = self.core_operator_finds_v2.clone();
+        self.core_operator_cycles = self.core_operator_cycles_v2.clone();
+
+        self.pso_update();
+        self.swarm_now = 0;
+        self.finds_until_last_swarm = self.total_finds;
+        self.mode = MOptMode::Pilotfuzzing;
+    }
+}
+
+/// A [`ScheduledMutator`] using [`MOpt`] to select the mutations, instead of picking them uniformly at random
+pub struct StdMOptMutator<C, I, MT, R, S, SC>
+where
+    C: Corpus<I>,
+    I: Input,
+    MT: MutatorsTuple<I, S>,
+    R: Rand,
+    S: HasRand<R> + HasMetadata + HasCorpus<C, I> + HasSolutions<SC, I>,
+    SC: Corpus<I>,
+{
+    mutations: MT,
+    /// The number of corpus entries and objectives before the last mutation
+    finds_before: usize,
+    /// The mutations applied to the last input
+    mutation_log: Vec<usize>,
+    phantom: PhantomData<(C, I, R, S, SC)>,
+}
+
+impl<C, I, MT, R, S, SC> Debug for StdMOptMutator<C, I, MT, R, S, SC>
+where
+    C: Corpus<I>,
+    I: Input,
+    MT: MutatorsTuple<I, S>,
+    R: Rand,
+    S: HasRand<R> + HasMetadata + HasCorpus<C, I> + HasSolutions<SC, I>,
+    SC: Corpus<I>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "StdMOptMutator with {} mutations for Input type {}",
+            self.mutations.len(),
+            core::any::type_name::<I>()
+        )
+    }
+}
+
+impl<C, I, MT, R, S, SC> Mutator<I, S> for StdMOptMutator<C, I, MT, R, S, SC>
+where
+    C: Corpus<I>,
+    I: Input,
+    MT: MutatorsTuple<I, S>,
+    R: Rand,
+    S: HasRand<R> + HasMetadata + HasCorpus<C, I> + HasSolutions<SC, I>,
+    SC: Corpus<I>,
+{
+    #[inline]
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        self.finds_before = Self::finds(state);
+        self.scheduled_mutate(state, input, stage_idx)
+    }
+
+    fn post_exec(
+        &mut self,
+        state: &mut S,
+        _stage_idx: i32,
+        _corpus_idx: Option<CorpusId>,
+    ) -> Result<(), Error> {
+        let new_finds = Self::finds(state).saturating_sub(self.finds_before);
+        state
+            .metadata_mut()
+            .get_mut::<MOpt>()
+            .ok_or_else(|| Error::KeyNotFound("MOpt metadata not found".into()))?
+            .record(&self.mutation_log, new_finds as u64);
+        Ok(())
+    }
+}
+
+impl<C, I, MT, R, S, SC> ComposedByMutations<I, MT, S> for StdMOptMutator<C, I, MT, R, S, SC>
+where
+    C: Corpus<I>,
+    I: Input,
+    MT: MutatorsTuple<I, S>,
+    R: Rand,
+    S: HasRand<R> + HasMetadata + HasCorpus<C, I> + HasSolutions<SC, I>,
+    SC: Corpus<I>,
+{
+    /// Get the mutations
+    #[inline]
+    fn mutations(&self) -> &MT {
+        &self.mutations
+    }
+
+    // Get the mutations (mut)
+    #[inline]
+    fn mutations_mut(&mut self) -> &mut MT {
+        &mut self.mutations
+    }
+}
+
+impl<C, I, MT, R, S, SC> ScheduledMutator<I, MT, S> for StdMOptMutator<C, I, MT, R, S, SC>
+where
+    C: Corpus<I>,
+    I: Input,
+    MT: MutatorsTuple<I, S>,
+    R: Rand,
+    S: HasRand<R> + HasMetadata + HasCorpus<C, I> + HasSolutions<SC, I>,
+    SC: Corpus<I>,
+{
+    /// Compute the number of iterations used to apply stacked mutations
+    fn iterations(&self, state: &mut S, _: &I) -> u64 {
+        1 << (1 + state.rand_mut().below(6))
+    }
+
+    /// Get the next mutation to apply, as selected by [`MOpt`]
+    fn schedule(&self, state: &mut S, _: &I) -> usize {
+        state
+            .metadata_mut()
+            .get_mut::<MOpt>()
+            .expect("MOpt metadata not found, create the StdMOptMutator with new()")
+            .select_operator()
+    }
+
+    fn scheduled_mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let mut r = MutationResult::Skipped;
+        let num = self.iterations(state, input);
+        self.mutation_log.clear();
+        for _ in 0..num {
+            let idx = self.schedule(state, input);
+            self.mutation_log.push(idx);
+            let outcome = self
+                .mutations_mut()
+                .get_and_mutate(idx, state, input, stage_idx)?;
+            if outcome == MutationResult::Mutated {
+                r = MutationResult::Mutated;
+            }
+        }
+        Ok(r)
+    }
+}
+
+impl<C, I, MT, R, S, SC> StdMOptMutator<C, I, MT, R, S, SC>
+where
+    C: Corpus<I>,
+    I: Input,
+    MT: MutatorsTuple<I, S>,
+    R: Rand,
+    S: HasRand<R> + HasMetadata + HasCorpus<C, I> + HasSolutions<SC, I>,
+    SC: Corpus<I>,
+{
+    /// Create a new [`StdMOptMutator`] with `swarm_num` swarms, adding the [`MOpt`] metadata to the state.
+    /// A [`MOpt`] already in the state, e.g., after a restart, is kept.
+    pub fn new(state: &mut S, mutations: MT, swarm_num: usize) -> Result<Self, Error> {
+        match state.metadata().get::<MOpt>() {
+            Some(mopt) if mopt.operator_num() != mutations.len() => {
+                return Err(Error::IllegalState(format!(
+                    "The MOpt metadata in the state is for {} mutations, not {}",
+                    mopt.operator_num(),
+                    mutations.len()
+                )));
+            }
+            Some(_) => (),
+            None => {
+                let seed = state.rand_mut().next();
+                state.add_metadata(MOpt::new(mutations.len(), swarm_num, seed)?);
+            }
+        }
+        Ok(Self {
+            mutations,
+            finds_before: 0,
+            mutation_log: vec![],
+            phantom: PhantomData,
+        })
+    }
+
+    /// The number of corpus entries and objectives in the state
+    fn finds(state: &S) -> usize {
+        state.corpus().count() + state.solutions().count()
+    }
+}
+
+/// The name of the [`MOptMode`] currently running, for stats
+impl fmt::Display for MOptMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MOptMode::Pilotfuzzing => write!(f, "pilot"),
+            MOptMode::Corefuzzing => write!(f, "core"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bolts::rands::StdRand,
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        inputs::BytesInput,
+        mutators::{
+            havoc_mutations,
+            mopt_mutator::{MOpt, MOptMode, StdMOptMutator},
+            Mutator,
+        },
+        state::{HasCorpus, HasMetadata, StdState},
+    };
+
+    #[test]
+    fn test_mopt_swarms() {
+        let mut mopt = MOpt::new(4, 3, 1337).unwrap();
+        mopt.period_pilot = 100;
+        mopt.period_core = 300;
+        assert!((mopt.probabilities().iter().sum::<f64>() - 1.0).abs() < 1e-9);
+
+        // Operator 2 is the only one ever finding something, in the second swarm only
+        for swarm in 0..3 {
+            assert_eq!(mopt.mode(), MOptMode::Pilotfuzzing);
+            assert_eq!(mopt.swarm_now(), swarm);
+            for _ in 0..100 {
+                let op = mopt.select_operator();
+                let finds = u64::from(op == 2 && swarm == 1);
+                mopt.record(&[op], finds);
+            }
+        }
+        assert_eq!(mopt.mode(), MOptMode::Corefuzzing);
+        assert_eq!(mopt.swarm_now(), 1);
+
+        for _ in 0..300 {
+            let op = mopt.select_operator();
+            mopt.record(&[op], u64::from(op == 2));
+        }
+        assert_eq!(mopt.mode(), MOptMode::Pilotfuzzing);
+        assert!((mopt.g_best[2] - 1.0).abs() < 1e-9);
+        for swarm in 0..3 {
+            let sum: f64 = mopt.x_now[swarm].iter().sum();
+            assert!((sum - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_mopt_mutator() {
+        let mut corpus = InMemoryCorpus::new();
+        corpus
+            .add(Testcase::new(BytesInput::new(b"abcd".to_vec())))
+            .unwrap();
+        let mut state = StdState::new(StdRand::with_seed(0), corpus, InMemoryCorpus::new(), ());
+
+        let mut mutator = StdMOptMutator::new(&mut state, havoc_mutations(), 5).unwrap();
+        for i in 0..16 {
+            let mut input = BytesInput::new(b"abcd".to_vec());
+            mutator.mutate(&mut state, &mut input, i).unwrap();
+            if i % 4 == 0 {
+                state.corpus_mut().add(Testcase::new(input)).unwrap();
+            }
+            mutator.post_exec(&mut state, i, None).unwrap();
+        }
+        assert_eq!(state.metadata().get::<MOpt>().unwrap().total_finds, 4);
+    }
+}
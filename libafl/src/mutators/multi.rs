@@ -0,0 +1,452 @@
+//! Mutations for [`MultipartInput`]s, either delegating to a byte-level [`Mutator`] for a single part,
+//! or working on the list of parts itself.
+
+use alloc::string::String;
+use core::marker::PhantomData;
+
+use crate::{
+    bolts::{
+        rands::Rand,
+        tuples::{tuple_list, tuple_list_type, Named},
+    },
+    corpus::{Corpus, CorpusId},
+    inputs::{BytesInput, HasLen, MultipartInput},
+    mutators::{MutationResult, Mutator},
+    state::{HasCorpus, HasMaxSize, HasRand},
+    Error,
+};
+
+/// Applies the wrapped [`Mutator`] to a single, randomly chosen, part
+pub struct PartMutator<M, R, S>
+where
+    M: Mutator<BytesInput, S>,
+    S: HasRand<R>,
+    R: Rand,
+{
+    inner: M,
+    name: String,
+    phantom: PhantomData<(R, S)>,
+}
+
+impl<M, R, S> Mutator<MultipartInput, S> for PartMutator<M, R, S>
+where
+    M: Mutator<BytesInput, S>,
+    S: HasRand<R>,
+    R: Rand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut MultipartInput,
+        stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let idx = state.rand_mut().below(input.len() as u64) as usize;
+        self.inner
+            .mutate(state, &mut input.parts_mut()[idx], stage_idx)
+    }
+
+    fn post_exec(
+        &mut self,
+        state: &mut S,
+        stage_idx: i32,
+        corpus_idx: Option<CorpusId>,
+    ) -> Result<(), Error> {
+        self.inner.post_exec(state, stage_idx, corpus_idx)
+    }
+}
+
+impl<M, R, S> Named for PartMutator<M, R, S>
+where
+    M: Mutator<BytesInput, S>,
+    S: HasRand<R>,
+    R: Rand,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<M, R, S> PartMutator<M, R, S>
+where
+    M: Mutator<BytesInput, S> + Named,
+    S: HasRand<R>,
+    R: Rand,
+{
+    /// Creates a new [`PartMutator`], wrapping a byte-level [`Mutator`]
+    #[must_use]
+    pub fn new(inner: M) -> Self {
+        let name = format!("PartMutator<{}>", inner.name());
+        Self {
+            inner,
+            name,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Inserts a copy of a random part of a random testcase at a random position
+#[derive(Default)]
+pub struct PartInsertMutator<C, R, S>
+where
+    C: Corpus<MultipartInput>,
+    S: HasRand<R> + HasCorpus<C, MultipartInput> + HasMaxSize,
+    R: Rand,
+{
+    phantom: PhantomData<(C, R, S)>,
+}
+
+impl<C, R, S> Mutator<MultipartInput, S> for PartInsertMutator<C, R, S>
+where
+    C: Corpus<MultipartInput>,
+    S: HasRand<R> + HasCorpus<C, MultipartInput> + HasMaxSize,
+    R: Rand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut MultipartInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let count = state.corpus().count();
+        if count == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let nth = state.rand_mut().below(count as u64) as usize;
+        let idx = state
+            .corpus()
+            .nth(nth)
+            .ok_or_else(|| Error::Empty("No entries in corpus".into()))?;
+
+        let other_len = state.corpus().get(idx)?.borrow_mut().load_input()?.len();
+        if other_len == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let from = state.rand_mut().below(other_len as u64) as usize;
+        let to = state.rand_mut().below((input.len() + 1) as u64) as usize;
+
+        let mut other_testcase = state.corpus().get(idx)?.borrow_mut();
+        let other = other_testcase.load_input()?;
+        let part = &other.parts()[from];
+        if input.bytes_len() + part.len() > state.max_size() {
+            return Ok(MutationResult::Skipped);
+        }
+        input.insert_part(to, other.names()[from].clone(), part.clone());
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<C, R, S> Named for PartInsertMutator<C, R, S>
+where
+    C: Corpus<MultipartInput>,
+    S: HasRand<R> + HasCorpus<C, MultipartInput> + HasMaxSize,
+    R: Rand,
+{
+    fn name(&self) -> &str {
+        "PartInsertMutator"
+    }
+}
+
+impl<C, R, S> PartInsertMutator<C, R, S>
+where
+    C: Corpus<MultipartInput>,
+    S: HasRand<R> + HasCorpus<C, MultipartInput> + HasMaxSize,
+    R: Rand,
+{
+    /// Creates a new [`PartInsertMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Removes a random part, keeping at least one
+#[derive(Default)]
+pub struct PartRemoveMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    phantom: PhantomData<(R, S)>,
+}
+
+impl<R, S> Mutator<MultipartInput, S> for PartRemoveMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut MultipartInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.len() <= 1 {
+            return Ok(MutationResult::Skipped);
+        }
+        let idx = state.rand_mut().below(input.len() as u64) as usize;
+        input.remove_part(idx);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<R, S> Named for PartRemoveMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    fn name(&self) -> &str {
+        "PartRemoveMutator"
+    }
+}
+
+impl<R, S> PartRemoveMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    /// Creates a new [`PartRemoveMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Reorders the parts by swapping two of them
+#[derive(Default)]
+pub struct PartSwapMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    phantom: PhantomData<(R, S)>,
+}
+
+impl<R, S> Mutator<MultipartInput, S> for PartSwapMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut MultipartInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.len() < 2 {
+            return Ok(MutationResult::Skipped);
+        }
+        let a = state.rand_mut().below(input.len() as u64) as usize;
+        let b = state.rand_mut().below(input.len() as u64) as usize;
+        if a == b {
+            return Ok(MutationResult::Skipped);
+        }
+        input.swap_parts(a, b);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<R, S> Named for PartSwapMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    fn name(&self) -> &str {
+        "PartSwapMutator"
+    }
+}
+
+impl<R, S> PartSwapMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    /// Creates a new [`PartSwapMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Splices the list of parts with the one of another testcase:
+/// keeps the first parts of this input, and appends the last parts of the other.
+#[derive(Default)]
+pub struct PartSpliceMutator<C, R, S>
+where
+    C: Corpus<MultipartInput>,
+    S: HasRand<R> + HasCorpus<C, MultipartInput> + HasMaxSize,
+    R: Rand,
+{
+    phantom: PhantomData<(C, R, S)>,
+}
+
+impl<C, R, S> Mutator<MultipartInput, S> for PartSpliceMutator<C, R, S>
+where
+    C: Corpus<MultipartInput>,
+    S: HasRand<R> + HasCorpus<C, MultipartInput> + HasMaxSize,
+    R: Rand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut MultipartInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        // We don't want to use the testcase we're already using for splicing
+        let count = state.corpus().count();
+        if count == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let nth = state.rand_mut().below(count as u64) as usize;
+        let idx = state
+            .corpus()
+            .nth(nth)
+            .ok_or_else(|| Error::Empty("No entries in corpus".into()))?;
+        if let Some(cur) = state.corpus().current() {
+            if idx == *cur {
+                return Ok(MutationResult::Skipped);
+            }
+        }
+
+        let other_len = state.corpus().get(idx)?.borrow_mut().load_input()?.len();
+        if other_len == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let keep = state.rand_mut().below((input.len() + 1) as u64) as usize;
+        let from = state.rand_mut().below(other_len as u64) as usize;
+
+        let mut other_testcase = state.corpus().get(idx)?.borrow_mut();
+        let other = other_testcase.load_input()?;
+        let kept_size: usize = input.parts()[..keep].iter().map(HasLen::len).sum();
+        let added_size: usize = other.parts()[from..].iter().map(HasLen::len).sum();
+        if kept_size + added_size > state.max_size() {
+            return Ok(MutationResult::Skipped);
+        }
+
+        while input.len() > keep {
+            input.remove_part(keep);
+        }
+        for (name, part) in other.iter().skip(from) {
+            input.add_part(name, part.clone());
+        }
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<C, R, S> Named for PartSpliceMutator<C, R, S>
+where
+    C: Corpus<MultipartInput>,
+    S: HasRand<R> + HasCorpus<C, MultipartInput> + HasMaxSize,
+    R: Rand,
+{
+    fn name(&self) -> &str {
+        "PartSpliceMutator"
+    }
+}
+
+impl<C, R, S> PartSpliceMutator<C, R, S>
+where
+    C: Corpus<MultipartInput>,
+    S: HasRand<R> + HasCorpus<C, MultipartInput> + HasMaxSize,
+    R: Rand,
+{
+    /// Creates a new [`PartSpliceMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Get the mutations working on the list of parts of a [`MultipartInput`].
+/// Combine them with [`PartMutator`]s to also mutate the contents of the parts.
+#[must_use]
+pub fn multipart_mutations<C, R, S>() -> tuple_list_type!(
+       PartInsertMutator<C, R, S>,
+       PartRemoveMutator<R, S>,
+       PartSwapMutator<R, S>,
+       PartSpliceMutator<C, R, S>,
+   )
+where
+    C: Corpus<MultipartInput>,
+    S: HasRand<R> + HasCorpus<C, MultipartInput> + HasMaxSize,
+    R: Rand,
+{
+    tuple_list!(
+        PartInsertMutator::new(),
+        PartRemoveMutator::new(),
+        PartSwapMutator::new(),
+        PartSpliceMutator::new(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bolts::{
+            rands::StdRand,
+            tuples::{tuple_list, HasLen as _, Merge},
+        },
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        inputs::{BytesInput, HasLen, HasTargetBytesParts, MultipartInput},
+        mutators::{
+            multi::{multipart_mutations, PartMutator},
+            mutations::{ByteFlipMutator, BytesDeleteMutator},
+            MutatorsTuple,
+        },
+        state::StdState,
+    };
+
+    #[test]
+    fn test_multipart_mutators() {
+        let input = MultipartInput::with_parts(vec![
+            ("config", BytesInput::new(b"verbose=1".to_vec())),
+            ("payload", BytesInput::new(vec![0x41; 16])),
+        ]);
+        let parts = input.target_bytes_parts();
+        assert_eq!(parts[0].0, "config");
+        assert_eq!(parts[1].1.as_slice(), &[0x41; 16]);
+
+        let rand = StdRand::with_seed(1337);
+        let mut corpus = InMemoryCorpus::new();
+        corpus.add(Testcase::new(input.clone())).unwrap();
+        corpus
+            .add(Testcase::new(MultipartInput::with_parts(vec![(
+                "packet",
+                BytesInput::new(vec![0x42; 8]),
+            )])))
+            .unwrap();
+        let mut state = StdState::new(rand, corpus, InMemoryCorpus::new(), ());
+
+        let mut mutations = tuple_list!(
+            PartMutator::new(ByteFlipMutator::new()),
+            PartMutator::new(BytesDeleteMutator::new())
+        )
+        .merge(multipart_mutations());
+
+        for idx in 0..mutations.len() {
+            for _ in 0..16 {
+                let mut mutant = input.clone();
+                mutations
+                    .get_and_mutate(idx, &mut state, &mut mutant, 0)
+                    .unwrap();
+                assert_eq!(mutant.names().len(), mutant.len());
+                assert!(mutant.iter().all(|(name, part)| match name {
+                    "config" => part.len() <= 9,
+                    _ => part.len() <= 16,
+                }));
+            }
+        }
+    }
+}
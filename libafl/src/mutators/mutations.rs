@@ -59,13 +59,13 @@ fn buffer_set(data: &mut [u8], from: usize, len: usize, val: u8) {
 }
 
 /// The max value that will be added or subtracted during add mutations
-const ARITH_MAX: u64 = 35;
+pub(crate) const ARITH_MAX: u64 = 35;
 
-const INTERESTING_8: [i8; 9] = [-128, -1, 0, 1, 16, 32, 64, 100, 127];
-const INTERESTING_16: [i16; 19] = [
+pub(crate) const INTERESTING_8: [i8; 9] = [-128, -1, 0, 1, 16, 32, 64, 100, 127];
+pub(crate) const INTERESTING_16: [i16; 19] = [
     -128, -1, 0, 1, 16, 32, 64, 100, 127, -32768, -129, 128, 255, 256, 512, 1000, 1024, 4096, 32767,
 ];
-const INTERESTING_32: [i32; 27] = [
+pub(crate) const INTERESTING_32: [i32; 27] = [
     -128,
     -1,
     0,
@@ -1177,7 +1177,14 @@ where
 
         // We don't want to use the testcase we're already using for splicing
         let count = state.corpus().count();
-        let idx = state.rand_mut().below(count as u64) as usize;
+        if count == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let nth = state.rand_mut().below(count as u64) as usize;
+        let idx = state
+            .corpus()
+            .nth(nth)
+            .ok_or_else(|| Error::Empty("No entries in corpus".into()))?;
         if let Some(cur) = state.corpus().current() {
             if idx == *cur {
                 return Ok(MutationResult::Skipped);
@@ -1276,7 +1283,14 @@ where
 
         // We don't want to use the testcase we're already using for splicing
         let count = state.corpus().count();
-        let idx = state.rand_mut().below(count as u64) as usize;
+        if count == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let nth = state.rand_mut().below(count as u64) as usize;
+        let idx = state
+            .corpus()
+            .nth(nth)
+            .ok_or_else(|| Error::Empty("No entries in corpus".into()))?;
         if let Some(cur) = state.corpus().current() {
             if idx == *cur {
                 return Ok(MutationResult::Skipped);
@@ -1379,7 +1393,14 @@ where
     ) -> Result<MutationResult, Error> {
         // We don't want to use the testcase we're already using for splicing
         let count = state.corpus().count();
-        let idx = state.rand_mut().below(count as u64) as usize;
+        if count == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let nth = state.rand_mut().below(count as u64) as usize;
+        let idx = state
+            .corpus()
+            .nth(nth)
+            .ok_or_else(|| Error::Empty("No entries in corpus".into()))?;
         if let Some(cur) = state.corpus().current() {
             if idx == *cur {
                 return Ok(MutationResult::Skipped);
@@ -1575,4 +1596,28 @@ mod tests {
             inputs.append(&mut new_testcases);
         }
     }
+
+    #[test]
+    fn test_crossover_empty_corpus() {
+        let mut state = StdState::new(
+            StdRand::with_seed(1337),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            (),
+        );
+        let mut mutations = tuple_list!(
+            CrossoverInsertMutator::new(),
+            CrossoverReplaceMutator::new(),
+            SpliceMutator::new(),
+        );
+        for idx in 0..mutations.len() {
+            let mut input = BytesInput::new(vec![1; 16]);
+            assert_eq!(
+                mutations
+                    .get_and_mutate(idx, &mut state, &mut input, 0)
+                    .unwrap(),
+                MutationResult::Skipped
+            );
+        }
+    }
 }
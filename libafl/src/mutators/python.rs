@@ -0,0 +1,174 @@
+//! A [`Mutator`] implemented by a Python object, loaded from a script at runtime,
+//! so that mutation strategies can be tried without recompiling the fuzzer.
+
+use alloc::string::String;
+use core::marker::PhantomData;
+use pyo3::{prelude::*, types::PyBytes};
+
+use crate::{
+    bolts::{
+        python::{load_python_object, PythonMetadata, PythonRand},
+        rands::Rand,
+        tuples::Named,
+    },
+    corpus::CorpusId,
+    inputs::{HasBytesVec, Input},
+    mutators::{MutationResult, Mutator},
+    state::{HasMaxSize, HasMetadata, HasRand},
+    Error,
+};
+
+/// A [`Mutator`] calling the `mutate(self, buf, rand, meta)` method of a Python object.
+/// `buf` is the input as `bytes`, `rand` a [`PythonRand`] seeded from the rand of the state,
+/// and `meta` the `dict` of the [`PythonMetadata`] of the state, whose changes are kept.
+/// The other metadata of the state are not accessible to the script.
+/// The method returns the mutated `bytes`, truncated to the max size, or `None` to skip the mutation.
+/// If the object has a `post_exec(self, corpus_idx)` method, it is called after the execution,
+/// with the id of the new corpus entry, if any, or `None`.
+pub struct PythonMutator<I, R, S>
+where
+    I: Input + HasBytesVec,
+    R: Rand,
+    S: HasRand<R> + HasMetadata + HasMaxSize,
+{
+    name: String,
+    object: PyObject,
+    phantom: PhantomData<(I, R, S)>,
+}
+
+impl<I, R, S> Mutator<I, S> for PythonMutator<I, R, S>
+where
+    I: Input + HasBytesVec,
+    R: Rand,
+    S: HasRand<R> + HasMetadata + HasMaxSize,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if state.metadata().get::<PythonMetadata>().is_none() {
+            state.add_metadata(PythonMetadata::new());
+        }
+        let seed = state.rand_mut().next();
+        let max_size = state.max_size();
+        let meta = state.metadata_mut().get_mut::<PythonMetadata>().unwrap();
+
+        Python::with_gil(|py| {
+            let dict = meta.to_dict(py)?;
+            let rand = Py::new(py, PythonRand::with_seed(seed))?;
+            let ret = self.object.call_method1(
+                py,
+                "mutate",
+                (PyBytes::new(py, input.bytes()), rand, dict),
+            )?;
+            meta.update_from_dict(dict)?;
+
+            let ret = ret.as_ref(py);
+            if ret.is_none() {
+                return Ok(MutationResult::Skipped);
+            }
+            let mutated = ret.extract::<&[u8]>()?;
+            *input.bytes_mut() = mutated[..mutated.len().min(max_size)].to_vec();
+            Ok(MutationResult::Mutated)
+        })
+    }
+
+    fn post_exec(
+        &mut self,
+        _state: &mut S,
+        _stage_idx: i32,
+        corpus_idx: Option<CorpusId>,
+    ) -> Result<(), Error> {
+        Python::with_gil(|py| {
+            if self.object.as_ref(py).hasattr("post_exec")? {
+                self.object
+                    .call_method1(py, "post_exec", (corpus_idx.map(usize::from),))?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl<I, R, S> Named for PythonMutator<I, R, S>
+where
+    I: Input + HasBytesVec,
+    R: Rand,
+    S: HasRand<R> + HasMetadata + HasMaxSize,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<I, R, S> PythonMutator<I, R, S>
+where
+    I: Input + HasBytesVec,
+    R: Rand,
+    S: HasRand<R> + HasMetadata + HasMaxSize,
+{
+    /// Creates a new [`PythonMutator`], running the script at `path` and instantiating its class `class_name`
+    pub fn new(path: &str, class_name: &str) -> Result<Self, Error> {
+        Ok(Self {
+            name: format!("PythonMutator({})", class_name),
+            object: load_python_object(path, class_name)?,
+            phantom: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        bolts::{python::PythonMetadata, rands::StdRand},
+        corpus::InMemoryCorpus,
+        inputs::{BytesInput, HasBytesVec},
+        mutators::{python::PythonMutator, MutationResult, Mutator},
+        state::{HasMetadata, StdState},
+    };
+
+    #[test]
+    fn test_python_mutator() {
+        let dir =
+            std::env::temp_dir().join(format!("libafl_test_python_mutator_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("mutator.py");
+        fs::write(
+            &path,
+            "class Mutator:\n\
+             \x20   def mutate(self, buf, rand, meta):\n\
+             \x20       meta['calls'] = bytes([meta.get('calls', b'\\x00')[0] + 1])\n\
+             \x20       if not buf:\n\
+             \x20           return None\n\
+             \x20       return buf + bytes([rand.below(10) + ord('0')])\n",
+        )
+        .unwrap();
+
+        let mut state = StdState::new(
+            StdRand::with_seed(1337),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            (),
+        );
+        let mut mutator = PythonMutator::new(path.to_str().unwrap(), "Mutator").unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut input = BytesInput::new(b"abc".to_vec());
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input, 0).unwrap(),
+            MutationResult::Mutated
+        );
+        assert_eq!(&input.bytes()[..3], b"abc");
+        assert!(input.bytes()[3].is_ascii_digit());
+        let mut input = BytesInput::new(vec![]);
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input, 0).unwrap(),
+            MutationResult::Skipped
+        );
+        let meta = state.metadata().get::<PythonMetadata>().unwrap();
+        assert_eq!(meta.map["calls"], vec![2]);
+    }
+}
@@ -13,9 +13,17 @@ use crate::{
         tuples::{tuple_list, tuple_list_type, NamedTuple},
         AsSlice,
     },
-    corpus::Corpus,
+    corpus::{Corpus, CorpusId},
     inputs::{HasBytesVec, Input},
-    mutators::{MutationResult, Mutator, MutatorsTuple},
+    mutators::{
+        delimited::{
+            DelimitedCrossoverInsertMutator, DelimitedCrossoverReplaceMutator,
+            DelimitedSpliceMutator, LineDeleteMutator, LineDuplicateMutator, LineShuffleMutator,
+        },
+        stacking::{GeometricStacking, HavocStacking},
+        text_mutations::{TextNumberMutator, TextStringMutator},
+        MutationResult, Mutator, MutatorsTuple,
+    },
     state::{HasCorpus, HasMaxSize, HasMetadata, HasRand},
     Error,
 };
@@ -24,10 +32,13 @@ pub use crate::mutators::mutations::*;
 pub use crate::mutators::token_mutations::*;
 
 /// The metadata placed in a [`crate::corpus::Testcase`] by a [`LoggerScheduledMutator`].
-#[derive(Serialize, Deserialize)]
+/// It records the names of the mutations that produced the testcase, in the order they were applied.
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct LogMutationMetadata {
     /// A list of logs
     pub list: Vec<String>,
+    /// The index of the mutated corpus entry, if any
+    pub parent: Option<CorpusId>,
 }
 
 crate::impl_serdeany!(LogMutationMetadata);
@@ -41,8 +52,8 @@ impl AsSlice<String> for LogMutationMetadata {
 impl LogMutationMetadata {
     /// Creates new [`struct@LogMutationMetadata`].
     #[must_use]
-    pub fn new(list: Vec<String>) -> Self {
-        Self { list }
+    pub fn new(list: Vec<String>, parent: Option<CorpusId>) -> Self {
+        Self { list, parent }
     }
 }
 
@@ -95,23 +106,27 @@ where
 }
 
 /// A [`Mutator`] that schedules one of the embedded mutations on each call.
-pub struct StdScheduledMutator<I, MT, R, S>
+/// The number of stacked mutations is decided by a [`HavocStacking`] policy, a [`GeometricStacking`] by default.
+pub struct StdScheduledMutator<I, MT, R, S, P = GeometricStacking>
 where
     I: Input,
     MT: MutatorsTuple<I, S>,
     R: Rand,
     S: HasRand<R>,
+    P: HavocStacking<I, R, S>,
 {
     mutations: MT,
+    stacking: P,
     phantom: PhantomData<(I, R, S)>,
 }
 
-impl<I, MT, R, S> Debug for StdScheduledMutator<I, MT, R, S>
+impl<I, MT, R, S, P> Debug for StdScheduledMutator<I, MT, R, S, P>
 where
     I: Input,
     MT: MutatorsTuple<I, S>,
     R: Rand,
     S: HasRand<R>,
+    P: HavocStacking<I, R, S>,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -123,12 +138,13 @@ where
     }
 }
 
-impl<I, MT, R, S> Mutator<I, S> for StdScheduledMutator<I, MT, R, S>
+impl<I, MT, R, S, P> Mutator<I, S> for StdScheduledMutator<I, MT, R, S, P>
 where
     I: Input,
     MT: MutatorsTuple<I, S>,
     R: Rand,
     S: HasRand<R>,
+    P: HavocStacking<I, R, S>,
 {
     #[inline]
     fn mutate(
@@ -139,14 +155,25 @@ where
     ) -> Result<MutationResult, Error> {
         self.scheduled_mutate(state, input, stage_idx)
     }
+
+    #[inline]
+    fn post_exec(
+        &mut self,
+        state: &mut S,
+        _stage_idx: i32,
+        corpus_idx: Option<CorpusId>,
+    ) -> Result<(), Error> {
+        self.stacking.post_exec(state, corpus_idx)
+    }
 }
 
-impl<I, MT, R, S> ComposedByMutations<I, MT, S> for StdScheduledMutator<I, MT, R, S>
+impl<I, MT, R, S, P> ComposedByMutations<I, MT, S> for StdScheduledMutator<I, MT, R, S, P>
 where
     I: Input,
     MT: MutatorsTuple<I, S>,
     R: Rand,
     S: HasRand<R>,
+    P: HavocStacking<I, R, S>,
 {
     /// Get the mutations
     #[inline]
@@ -161,16 +188,17 @@ where
     }
 }
 
-impl<I, MT, R, S> ScheduledMutator<I, MT, S> for StdScheduledMutator<I, MT, R, S>
+impl<I, MT, R, S, P> ScheduledMutator<I, MT, S> for StdScheduledMutator<I, MT, R, S, P>
 where
     I: Input,
     MT: MutatorsTuple<I, S>,
     R: Rand,
     S: HasRand<R>,
+    P: HavocStacking<I, R, S>,
 {
     /// Compute the number of iterations used to apply stacked mutations
-    fn iterations(&self, state: &mut S, _: &I) -> u64 {
-        1 << (1 + state.rand_mut().below(6))
+    fn iterations(&self, state: &mut S, input: &I) -> u64 {
+        self.stacking.iterations(state, input)
     }
 
     /// Get the next mutation to apply
@@ -189,11 +217,38 @@ where
 {
     /// Create a new [`StdScheduledMutator`] instance specifying mutations
     pub fn new(mutations: MT) -> Self {
+        Self::with_stacking(mutations, GeometricStacking::default())
+    }
+}
+
+impl<I, MT, R, S, P> StdScheduledMutator<I, MT, R, S, P>
+where
+    I: Input,
+    MT: MutatorsTuple<I, S>,
+    R: Rand,
+    S: HasRand<R>,
+    P: HavocStacking<I, R, S>,
+{
+    /// Create a new [`StdScheduledMutator`] instance specifying mutations and the stacking policy
+    pub fn with_stacking(mutations: MT, stacking: P) -> Self {
         StdScheduledMutator {
             mutations,
+            stacking,
             phantom: PhantomData,
         }
     }
+
+    /// The stacking policy
+    #[inline]
+    pub fn stacking(&self) -> &P {
+        &self.stacking
+    }
+
+    /// The stacking policy (mut)
+    #[inline]
+    pub fn stacking_mut(&mut self) -> &mut P {
+        &mut self.stacking
+    }
 }
 
 /// Get the mutations that compose the Havoc mutator
@@ -275,14 +330,55 @@ where
     tuple_list!(TokenInsert::new(), TokenReplace::new(),)
 }
 
+/// Get the mutations rewriting the numbers and strings of text inputs
+#[must_use]
+pub fn text_mutations<I, R, S>(
+) -> tuple_list_type!(TextNumberMutator<I, R, S>, TextStringMutator<I, R, S>)
+where
+    I: Input + HasBytesVec,
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    tuple_list!(TextNumberMutator::new(), TextStringMutator::new(),)
+}
+
+/// Get the crossovers cutting at line breaks and whitespaces, and the line mutations, for text protocols
+#[must_use]
+pub fn delimited_mutations<C, I, R, S>() -> tuple_list_type!(
+       DelimitedCrossoverInsertMutator<C, I, R, S>,
+       DelimitedCrossoverReplaceMutator<C, I, R, S>,
+       DelimitedSpliceMutator<C, I, R, S>,
+       LineShuffleMutator<I, R, S>,
+       LineDuplicateMutator<I, R, S>,
+       LineDeleteMutator<I, R, S>,
+   )
+where
+    I: Input + HasBytesVec,
+    S: HasRand<R> + HasCorpus<C, I> + HasMaxSize,
+    C: Corpus<I>,
+    R: Rand,
+{
+    tuple_list!(
+        DelimitedCrossoverInsertMutator::new(),
+        DelimitedCrossoverReplaceMutator::new(),
+        DelimitedSpliceMutator::new(),
+        LineShuffleMutator::new(),
+        LineDuplicateMutator::new(),
+        LineDeleteMutator::new(),
+    )
+}
+
 /// A logging [`Mutator`] that wraps around a [`StdScheduledMutator`].
+/// The log of the last mutation is kept in the state as [`struct@LogMutationMetadata`], and added to the new corpus entry, if any.
+/// Use a [`crate::feedbacks::MutationLogFeedback`] in the feedback and in the objective,
+/// to also log objectives, and to attach the log before the testcase is stored, e.g. by an `OnDiskCorpus`.
 pub struct LoggerScheduledMutator<C, I, MT, R, S, SM>
 where
     C: Corpus<I>,
     I: Input,
     MT: MutatorsTuple<I, S> + NamedTuple,
     R: Rand,
-    S: HasRand<R> + HasCorpus<C, I>,
+    S: HasRand<R> + HasCorpus<C, I> + HasMetadata,
     SM: ScheduledMutator<I, MT, S>,
 {
     scheduled: SM,
@@ -296,7 +392,7 @@ where
     I: Input,
     MT: MutatorsTuple<I, S> + NamedTuple,
     R: Rand,
-    S: HasRand<R> + HasCorpus<C, I>,
+    S: HasRand<R> + HasCorpus<C, I> + HasMetadata,
     SM: ScheduledMutator<I, MT, S>,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -315,7 +411,7 @@ where
     I: Input,
     MT: MutatorsTuple<I, S> + NamedTuple,
     R: Rand,
-    S: HasRand<R> + HasCorpus<C, I>,
+    S: HasRand<R> + HasCorpus<C, I> + HasMetadata,
     SM: ScheduledMutator<I, MT, S>,
 {
     fn mutate(
@@ -330,22 +426,21 @@ where
     fn post_exec(
         &mut self,
         state: &mut S,
-        _stage_idx: i32,
-        corpus_idx: Option<usize>,
+        stage_idx: i32,
+        corpus_idx: Option<CorpusId>,
     ) -> Result<(), Error> {
         if let Some(idx) = corpus_idx {
-            let mut testcase = (*state.corpus_mut().get(idx)?).borrow_mut();
-            let mut log = Vec::<String>::new();
-            while let Some(idx) = self.mutation_log.pop() {
-                let name = String::from(self.scheduled.mutations().name(idx).unwrap()); // TODO maybe return an Error on None
-                log.push(name);
+            let mut testcase = state.corpus().get(idx)?.borrow_mut();
+            if testcase.metadata().get::<LogMutationMetadata>().is_none() {
+                if let Some(log) = state.metadata().get::<LogMutationMetadata>() {
+                    testcase.add_metadata(log.clone());
+                }
             }
-            let meta = LogMutationMetadata::new(log);
-            testcase.add_metadata(meta);
-        };
+        }
+        state.metadata_mut().remove::<LogMutationMetadata>();
         // Always reset the log for each run
         self.mutation_log.clear();
-        Ok(())
+        self.scheduled.post_exec(state, stage_idx, corpus_idx)
     }
 }
 
@@ -356,7 +451,7 @@ where
     I: Input,
     MT: MutatorsTuple<I, S> + NamedTuple,
     R: Rand,
-    S: HasRand<R> + HasCorpus<C, I>,
+    S: HasRand<R> + HasCorpus<C, I> + HasMetadata,
     SM: ScheduledMutator<I, MT, S>,
 {
     #[inline]
@@ -376,12 +471,12 @@ where
     I: Input,
     MT: MutatorsTuple<I, S> + NamedTuple,
     R: Rand,
-    S: HasRand<R> + HasCorpus<C, I>,
+    S: HasRand<R> + HasCorpus<C, I> + HasMetadata,
     SM: ScheduledMutator<I, MT, S>,
 {
-    /// Compute the number of iterations used to apply stacked mutations
-    fn iterations(&self, state: &mut S, _: &I) -> u64 {
-        1 << (1 + state.rand_mut().below(6))
+    /// Compute the number of iterations used to apply stacked mutations, as the wrapped mutator does
+    fn iterations(&self, state: &mut S, input: &I) -> u64 {
+        self.scheduled.iterations(state, input)
     }
 
     /// Get the next mutation to apply
@@ -411,6 +506,21 @@ where
                 r = MutationResult::Mutated;
             }
         }
+
+        if r == MutationResult::Mutated {
+            let mut list = Vec::with_capacity(self.mutation_log.len());
+            for idx in &self.mutation_log {
+                let name =
+                    self.scheduled.mutations().name(*idx).ok_or_else(|| {
+                        Error::KeyNotFound(format!("No mutation with index {}", idx))
+                    })?;
+                list.push(String::from(name));
+            }
+            let parent = *state.corpus().current();
+            state.add_metadata(LogMutationMetadata::new(list, parent));
+        } else {
+            state.metadata_mut().remove::<LogMutationMetadata>();
+        }
         Ok(r)
     }
 }
@@ -421,7 +531,7 @@ where
     I: Input,
     MT: MutatorsTuple<I, S> + NamedTuple,
     R: Rand,
-    S: HasRand<R> + HasCorpus<C, I>,
+    S: HasRand<R> + HasCorpus<C, I> + HasMetadata,
     SM: ScheduledMutator<I, MT, S>,
 {
     /// Create a new [`StdScheduledMutator`] instance without mutations and corpus
@@ -442,10 +552,12 @@ mod tests {
         inputs::{BytesInput, HasBytesVec},
         mutators::{
             mutations::SpliceMutator,
-            scheduled::{havoc_mutations, StdScheduledMutator},
+            scheduled::{
+                havoc_mutations, LogMutationMetadata, LoggerScheduledMutator, StdScheduledMutator,
+            },
             Mutator,
         },
-        state::StdState,
+        state::{HasCorpus, HasMetadata, StdState},
     };
 
     #[test]
@@ -456,7 +568,9 @@ mod tests {
         corpus.add(Testcase::new(vec![b'a', b'b', b'c'])).unwrap();
         corpus.add(Testcase::new(vec![b'd', b'e', b'f'])).unwrap();
 
-        let testcase = corpus.get(0).expect("Corpus did not contain entries");
+        let testcase = corpus
+            .get(corpus.first().unwrap())
+            .expect("Corpus did not contain entries");
         let mut input = testcase.borrow_mut().load_input().unwrap().clone();
 
         let mut state = StdState::new(rand, corpus, InMemoryCorpus::new(), ());
@@ -482,7 +596,9 @@ mod tests {
         corpus.add(Testcase::new(vec![b'a', b'b', b'c'])).unwrap();
         corpus.add(Testcase::new(vec![b'd', b'e', b'f'])).unwrap();
 
-        let testcase = corpus.get(0).expect("Corpus did not contain entries");
+        let testcase = corpus
+            .get(corpus.first().unwrap())
+            .expect("Corpus did not contain entries");
         let mut input = testcase.borrow_mut().load_input().unwrap().clone();
         let input_prior = input.clone();
 
@@ -506,4 +622,49 @@ mod tests {
             assert_ne!(equal_in_a_row, 5);
         }
     }
+
+    #[test]
+    fn test_logger() {
+        let mut corpus: InMemoryCorpus<BytesInput> = InMemoryCorpus::new();
+        let parent = corpus.add(Testcase::new(vec![b'a', b'b', b'c'])).unwrap();
+        *corpus.current_mut() = Some(parent);
+        let mut state = StdState::new(
+            StdRand::with_seed(0x1337),
+            corpus,
+            InMemoryCorpus::new(),
+            (),
+        );
+
+        let mut logger = LoggerScheduledMutator::new(StdScheduledMutator::new(havoc_mutations()));
+        let mut input = state
+            .corpus()
+            .get(parent)
+            .unwrap()
+            .borrow_mut()
+            .load_input()
+            .unwrap()
+            .clone();
+        logger.mutate(&mut state, &mut input, 0).unwrap();
+
+        let log = state
+            .metadata()
+            .get::<LogMutationMetadata>()
+            .unwrap()
+            .clone();
+        assert!(!log.list.is_empty());
+        assert_eq!(log.parent, Some(parent));
+
+        let idx = state.corpus_mut().add(Testcase::new(input)).unwrap();
+        logger.post_exec(&mut state, 0, Some(idx)).unwrap();
+        let testcase = state.corpus().get(idx).unwrap().borrow();
+        assert_eq!(
+            testcase
+                .metadata()
+                .get::<LogMutationMetadata>()
+                .unwrap()
+                .list,
+            log.list
+        );
+        assert!(state.metadata().get::<LogMutationMetadata>().is_none());
+    }
 }
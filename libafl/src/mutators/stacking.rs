@@ -0,0 +1,261 @@
+//! Havoc stacking policies, deciding how many mutations a [`crate::mutators::StdScheduledMutator`]
+//! stacks on each input: a fixed number, a random power of two, or a power of two adapting to the recent finds.
+
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bolts::rands::Rand,
+    corpus::CorpusId,
+    inputs::Input,
+    state::{HasMetadata, HasRand},
+    Error,
+};
+
+/// The default maximum stacking of the havoc mutations, `1 << 6`
+pub const DEFAULT_STACKING_MAX_LOG2: u64 = 6;
+/// The largest supported maximum stacking, `1 << 16`, larger ones are clamped to it
+pub const STACKING_MAX_LOG2: u64 = 16;
+/// The default number of recorded executions after which the [`AdaptiveStacking`] halves its statistics
+pub const DEFAULT_STACKING_WINDOW: u64 = 4096;
+/// One in this many choices of the [`AdaptiveStacking`] is random, to keep exploring
+const ADAPTIVE_STACKING_EXPLORE: u64 = 8;
+
+/// A policy for the number of stacked mutations
+pub trait HavocStacking<I, R, S>
+where
+    I: Input,
+    R: Rand,
+    S: HasRand<R>,
+{
+    /// The number of mutations to stack on the input
+    fn iterations(&self, state: &mut S, input: &I) -> u64;
+
+    /// Post-process given the index of the new corpus entry produced by the mutated input, if any
+    fn post_exec(&mut self, _state: &mut S, _corpus_idx: Option<CorpusId>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Always stacks the same number of mutations
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct FixedStacking {
+    iterations: u64,
+}
+
+impl<I, R, S> HavocStacking<I, R, S> for FixedStacking
+where
+    I: Input,
+    R: Rand,
+    S: HasRand<R>,
+{
+    fn iterations(&self, _state: &mut S, _input: &I) -> u64 {
+        self.iterations
+    }
+}
+
+impl FixedStacking {
+    /// Creates a new [`FixedStacking`], stacking `iterations` mutations, at least one
+    #[must_use]
+    pub fn new(iterations: u64) -> Self {
+        Self {
+            iterations: iterations.max(1),
+        }
+    }
+}
+
+/// Stacks a random power of two of mutations, from `2` to `1 << max_log2`, as AFL does.
+/// This is the default policy.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct GeometricStacking {
+    max_log2: u64,
+}
+
+impl<I, R, S> HavocStacking<I, R, S> for GeometricStacking
+where
+    I: Input,
+    R: Rand,
+    S: HasRand<R>,
+{
+    fn iterations(&self, state: &mut S, _input: &I) -> u64 {
+        1 << (1 + state.rand_mut().below(self.max_log2))
+    }
+}
+
+impl GeometricStacking {
+    /// Creates a new [`GeometricStacking`], stacking up to `1 << max_log2` mutations,
+    /// with `max_log2` at most [`STACKING_MAX_LOG2`]
+    #[must_use]
+    pub fn new(max_log2: u64) -> Self {
+        Self {
+            max_log2: max_log2.clamp(1, STACKING_MAX_LOG2),
+        }
+    }
+}
+
+impl Default for GeometricStacking {
+    fn default() -> Self {
+        Self::new(DEFAULT_STACKING_MAX_LOG2)
+    }
+}
+
+/// The statistics of the [`AdaptiveStacking`], for each power of two
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct AdaptiveStackingMetadata {
+    /// The number of executions stacking `1 << (i + 1)` mutations
+    pub tries: Vec<u64>,
+    /// The number of new corpus entries found stacking `1 << (i + 1)` mutations
+    pub finds: Vec<u64>,
+    /// The power of two chosen for the pending execution
+    pub last: Option<usize>,
+}
+
+crate::impl_serdeany!(AdaptiveStackingMetadata);
+
+/// Stacks the power of two of mutations with the best recent success rate, from `2` to `1 << max_log2`.
+/// The statistics are stored in the state as [`AdaptiveStackingMetadata`],
+/// and halved every `window` executions so that old finds weigh less.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct AdaptiveStacking {
+    max_log2: u64,
+    window: u64,
+}
+
+impl<I, R, S> HavocStacking<I, R, S> for AdaptiveStacking
+where
+    I: Input,
+    R: Rand,
+    S: HasRand<R> + HasMetadata,
+{
+    fn iterations(&self, state: &mut S, _input: &I) -> u64 {
+        let buckets = self.max_log2 as usize;
+        // Statistics left by a policy with a different max stacking are reset
+        if state
+            .metadata()
+            .get::<AdaptiveStackingMetadata>()
+            .map_or(true, |meta| meta.tries.len() != buckets)
+        {
+            state.add_metadata(AdaptiveStackingMetadata {
+                tries: vec![0; buckets],
+                finds: vec![0; buckets],
+                last: None,
+            });
+        }
+
+        let explore = state.rand_mut().below(ADAPTIVE_STACKING_EXPLORE) == 0;
+        let random = state.rand_mut().below(self.max_log2) as usize;
+        let meta = state
+            .metadata_mut()
+            .get_mut::<AdaptiveStackingMetadata>()
+            .unwrap();
+        let bucket = if explore {
+            random
+        } else {
+            // The success rate, with a uniform prior, so that untried buckets are tried
+            let rate = |i: usize| (meta.finds[i] + 1) as f64 / (meta.tries[i] + 2) as f64;
+            let best = (0..buckets).map(rate).fold(0.0, f64::max);
+            // Break ties with the random bucket, the first best one after it
+            (0..buckets)
+                .map(|i| (random + i) % buckets)
+                .find(|i| rate(*i) >= best)
+                .unwrap()
+        };
+        meta.last = Some(bucket);
+        1 << (bucket + 1)
+    }
+
+    fn post_exec(&mut self, state: &mut S, corpus_idx: Option<CorpusId>) -> Result<(), Error> {
+        if let Some(meta) = state.metadata_mut().get_mut::<AdaptiveStackingMetadata>() {
+            if let Some(last) = meta.last.take() {
+                meta.tries[last] += 1;
+                if corpus_idx.is_some() {
+                    meta.finds[last] += 1;
+                }
+                if meta.tries.iter().sum::<u64>() >= self.window {
+                    for count in meta.tries.iter_mut().chain(meta.finds.iter_mut()) {
+                        *count /= 2;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AdaptiveStacking {
+    /// Creates a new [`AdaptiveStacking`], stacking up to `1 << max_log2` mutations,
+    /// with `max_log2` at most [`STACKING_MAX_LOG2`], and halving its statistics every `window` executions
+    #[must_use]
+    pub fn new(max_log2: u64, window: u64) -> Self {
+        Self {
+            max_log2: max_log2.clamp(1, STACKING_MAX_LOG2),
+            window: window.max(1),
+        }
+    }
+}
+
+impl Default for AdaptiveStacking {
+    fn default() -> Self {
+        Self::new(DEFAULT_STACKING_MAX_LOG2, DEFAULT_STACKING_WINDOW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bolts::rands::StdRand,
+        corpus::{CorpusId, InMemoryCorpus},
+        inputs::BytesInput,
+        mutators::stacking::{
+            AdaptiveStacking, AdaptiveStackingMetadata, FixedStacking, GeometricStacking,
+            HavocStacking, STACKING_MAX_LOG2,
+        },
+        state::{HasMetadata, StdState},
+    };
+
+    #[test]
+    fn test_stacking() {
+        let mut state = StdState::new(
+            StdRand::with_seed(1337),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            (),
+        );
+        let input = BytesInput::new(vec![0; 16]);
+
+        assert_eq!(FixedStacking::new(3).iterations(&mut state, &input), 3);
+        assert_eq!(FixedStacking::new(0).iterations(&mut state, &input), 1);
+        for _ in 0..32 {
+            let iterations = GeometricStacking::new(3).iterations(&mut state, &input);
+            assert!([2, 4, 8].contains(&iterations));
+        }
+        // Huge maximums are clamped instead of overflowing
+        for _ in 0..32 {
+            let iterations = GeometricStacking::new(u64::MAX).iterations(&mut state, &input);
+            assert!(iterations >= 2 && iterations <= 1 << STACKING_MAX_LOG2);
+            let iterations = AdaptiveStacking::new(64, 1024).iterations(&mut state, &input);
+            assert!(iterations >= 2 && iterations <= 1 << STACKING_MAX_LOG2);
+        }
+
+        // Only stacking 8 mutations finds something, the policy learns it
+        let mut adaptive = AdaptiveStacking::new(4, 1024);
+        let mut eights = 0;
+        for i in 0..512 {
+            let iterations = adaptive.iterations(&mut state, &input);
+            if i >= 256 && iterations == 8 {
+                eights += 1;
+            }
+            let corpus_idx = if iterations == 8 {
+                Some(CorpusId(0))
+            } else {
+                None
+            };
+            HavocStacking::<BytesInput, _, _>::post_exec(&mut adaptive, &mut state, corpus_idx)
+                .unwrap();
+        }
+        assert!(eights > 192);
+        let meta = state.metadata().get::<AdaptiveStackingMetadata>().unwrap();
+        assert_eq!(meta.finds[0], 0);
+        assert!(meta.last.is_none());
+    }
+}
@@ -0,0 +1,368 @@
+//! Field-aware mutations for structured inputs, i.e., plain Rust types.
+//! The [`Mutate`] trait is implemented for primitives, `Vec<u8>`, `String`, `Option` and `Box`,
+//! and can be derived for your own structs and enums with `#[derive(Mutate)]` from `libafl_derive`.
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::{cmp::min, marker::PhantomData};
+
+use crate::{
+    bolts::{rands::Rand, tuples::Named},
+    inputs::Input,
+    mutators::{
+        mutations::{ARITH_MAX, INTERESTING_32},
+        MutationResult, Mutator, Tokens,
+    },
+    state::{HasMaxSize, HasMetadata, HasRand},
+    Error,
+};
+
+/// The maximum number of bytes inserted or deleted at once in a `Vec<u8>`
+const MAX_BYTES_RUN: u64 = 16;
+
+/// A value that knows how to mutate itself, one field at a time
+pub trait Mutate {
+    /// Mutate this value, returning [`MutationResult::Skipped`] if it did not change
+    fn mutate<R, S>(&mut self, state: &mut S) -> Result<MutationResult, Error>
+    where
+        S: HasRand<R> + HasMetadata + HasMaxSize,
+        R: Rand;
+}
+
+macro_rules! impl_mutate_int {
+    ($($t:ty),*) => {
+        $(
+            impl Mutate for $t {
+                fn mutate<R, S>(&mut self, state: &mut S) -> Result<MutationResult, Error>
+                where
+                    S: HasRand<R> + HasMetadata + HasMaxSize,
+                    R: Rand,
+                {
+                    let old = *self;
+                    let rand = state.rand_mut();
+                    *self = match rand.below(6) {
+                        0 => old ^ (1 << rand.below(<$t>::BITS.into())),
+                        1 => old.wrapping_add(rand.between(1, ARITH_MAX) as $t),
+                        2 => old.wrapping_sub(rand.between(1, ARITH_MAX) as $t),
+                        3 => match rand.below(INTERESTING_32.len() as u64 + 2) as usize {
+                            0 => <$t>::MIN,
+                            1 => <$t>::MAX,
+                            idx => INTERESTING_32[idx - 2] as $t,
+                        },
+                        4 => old.swap_bytes(),
+                        _ => rand.next() as $t,
+                    };
+                    if *self == old {
+                        Ok(MutationResult::Skipped)
+                    } else {
+                        Ok(MutationResult::Mutated)
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_mutate_int!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+macro_rules! impl_mutate_float {
+    ($($t:ty => $bits:ty),*) => {
+        $(
+            impl Mutate for $t {
+                fn mutate<R, S>(&mut self, state: &mut S) -> Result<MutationResult, Error>
+                where
+                    S: HasRand<R> + HasMetadata + HasMaxSize,
+                    R: Rand,
+                {
+                    let old = self.to_bits();
+                    let rand = state.rand_mut();
+                    *self = match rand.below(5) {
+                        0 => <$t>::from_bits(old ^ (1 << rand.below(<$bits>::BITS.into()))),
+                        1 => *self + (rand.between(1, ARITH_MAX) as $t),
+                        2 => *self - (rand.between(1, ARITH_MAX) as $t),
+                        3 => -*self,
+                        _ => *rand.choose(&[
+                            0.0,
+                            -0.0,
+                            1.0,
+                            -1.0,
+                            <$t>::EPSILON,
+                            <$t>::MIN_POSITIVE,
+                            <$t>::MIN,
+                            <$t>::MAX,
+                            <$t>::INFINITY,
+                            <$t>::NEG_INFINITY,
+                            <$t>::NAN,
+                        ]),
+                    };
+                    if self.to_bits() == old {
+                        Ok(MutationResult::Skipped)
+                    } else {
+                        Ok(MutationResult::Mutated)
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_mutate_float!(f32 => u32, f64 => u64);
+
+impl Mutate for bool {
+    fn mutate<R, S>(&mut self, _state: &mut S) -> Result<MutationResult, Error>
+    where
+        S: HasRand<R> + HasMetadata + HasMaxSize,
+        R: Rand,
+    {
+        *self = !*self;
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Mutate for char {
+    fn mutate<R, S>(&mut self, state: &mut S) -> Result<MutationResult, Error>
+    where
+        S: HasRand<R> + HasMetadata + HasMaxSize,
+        R: Rand,
+    {
+        let mut code = u32::from(*self);
+        if code.mutate(state)? == MutationResult::Skipped {
+            return Ok(MutationResult::Skipped);
+        }
+        match char::from_u32(code) {
+            Some(c) => {
+                *self = c;
+                Ok(MutationResult::Mutated)
+            }
+            None => Ok(MutationResult::Skipped),
+        }
+    }
+}
+
+impl Mutate for Vec<u8> {
+    fn mutate<R, S>(&mut self, state: &mut S) -> Result<MutationResult, Error>
+    where
+        S: HasRand<R> + HasMetadata + HasMaxSize,
+        R: Rand,
+    {
+        let len = self.len();
+        let max_size = state.max_size();
+        match state.rand_mut().below(6) {
+            // Grow the empty vector, whatever we picked
+            _ if len == 0 => {
+                let run = state.rand_mut().between(1, MAX_BYTES_RUN);
+                if run as usize > max_size {
+                    return Ok(MutationResult::Skipped);
+                }
+                for _ in 0..run {
+                    self.push(state.rand_mut().next() as u8);
+                }
+            }
+            0 => {
+                let idx = state.rand_mut().below(len as u64) as usize;
+                return self[idx].mutate(state);
+            }
+            1 => {
+                let off = state.rand_mut().below(len as u64 + 1) as usize;
+                let run = state.rand_mut().between(1, MAX_BYTES_RUN);
+                if len + run as usize > max_size {
+                    return Ok(MutationResult::Skipped);
+                }
+                let bytes: Vec<u8> = (0..run).map(|_| state.rand_mut().next() as u8).collect();
+                self.splice(off..off, bytes);
+            }
+            2 => {
+                let off = state.rand_mut().below(len as u64) as usize;
+                let run = 1 + state
+                    .rand_mut()
+                    .below(min((len - off) as u64, MAX_BYTES_RUN));
+                self.drain(off..off + run as usize);
+            }
+            3 => {
+                let from = state.rand_mut().below(len as u64) as usize;
+                let run = 1 + state
+                    .rand_mut()
+                    .below(min((len - from) as u64, MAX_BYTES_RUN));
+                if len + run as usize > max_size {
+                    return Ok(MutationResult::Skipped);
+                }
+                let to = state.rand_mut().below(len as u64 + 1) as usize;
+                let copy = self[from..from + run as usize].to_vec();
+                self.splice(to..to, copy);
+            }
+            4 => {
+                let a = state.rand_mut().below(len as u64) as usize;
+                let b = state.rand_mut().below(len as u64) as usize;
+                if self[a] == self[b] {
+                    return Ok(MutationResult::Skipped);
+                }
+                self.swap(a, b);
+            }
+            _ => {
+                // Insert a token, if the state has a dictionary
+                let tokens_len = match state.metadata().get::<Tokens>() {
+                    Some(tokens) if !tokens.tokens().is_empty() => tokens.tokens().len(),
+                    _ => return Ok(MutationResult::Skipped),
+                };
+                let token_idx = state.rand_mut().below(tokens_len as u64) as usize;
+                let token = state.metadata().get::<Tokens>().unwrap().tokens()[token_idx].clone();
+                if len + token.len() > max_size {
+                    return Ok(MutationResult::Skipped);
+                }
+                let off = state.rand_mut().below(len as u64 + 1) as usize;
+                self.splice(off..off, token);
+            }
+        }
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Mutate for String {
+    fn mutate<R, S>(&mut self, state: &mut S) -> Result<MutationResult, Error>
+    where
+        S: HasRand<R> + HasMetadata + HasMaxSize,
+        R: Rand,
+    {
+        let mut bytes = core::mem::take(self).into_bytes();
+        let res = bytes.mutate(state)?;
+        *self = String::from_utf8_lossy(&bytes).into_owned();
+        Ok(res)
+    }
+}
+
+impl<T> Mutate for Option<T>
+where
+    T: Mutate + Default,
+{
+    fn mutate<R, S>(&mut self, state: &mut S) -> Result<MutationResult, Error>
+    where
+        S: HasRand<R> + HasMetadata + HasMaxSize,
+        R: Rand,
+    {
+        match self {
+            None => {
+                let mut value = T::default();
+                value.mutate(state)?;
+                *self = Some(value);
+                Ok(MutationResult::Mutated)
+            }
+            Some(_) if state.rand_mut().below(8) == 0 => {
+                *self = None;
+                Ok(MutationResult::Mutated)
+            }
+            Some(value) => value.mutate(state),
+        }
+    }
+}
+
+impl<T> Mutate for Box<T>
+where
+    T: Mutate,
+{
+    fn mutate<R, S>(&mut self, state: &mut S) -> Result<MutationResult, Error>
+    where
+        S: HasRand<R> + HasMetadata + HasMaxSize,
+        R: Rand,
+    {
+        self.as_mut().mutate(state)
+    }
+}
+
+/// A [`Mutator`] for any [`Input`] implementing [`Mutate`], usually through `#[derive(Input, Mutate)]`
+#[derive(Default)]
+pub struct StructuredMutator<I, R, S>
+where
+    I: Input + Mutate,
+    S: HasRand<R> + HasMetadata + HasMaxSize,
+    R: Rand,
+{
+    phantom: PhantomData<(I, R, S)>,
+}
+
+impl<I, R, S> Mutator<I, S> for StructuredMutator<I, R, S>
+where
+    I: Input + Mutate,
+    S: HasRand<R> + HasMetadata + HasMaxSize,
+    R: Rand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        Mutate::mutate(input, state)
+    }
+}
+
+impl<I, R, S> Named for StructuredMutator<I, R, S>
+where
+    I: Input + Mutate,
+    S: HasRand<R> + HasMetadata + HasMaxSize,
+    R: Rand,
+{
+    fn name(&self) -> &str {
+        "StructuredMutator"
+    }
+}
+
+impl<I, R, S> StructuredMutator<I, R, S>
+where
+    I: Input + Mutate,
+    S: HasRand<R> + HasMetadata + HasMaxSize,
+    R: Rand,
+{
+    /// Creates a new [`StructuredMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bolts::rands::StdRand,
+        corpus::InMemoryCorpus,
+        inputs::BytesInput,
+        mutators::{structured::Mutate, MutationResult, Tokens},
+        state::{HasMaxSize, HasMetadata, StdState},
+    };
+
+    #[test]
+    fn test_mutate_primitives() {
+        let mut state = StdState::new(
+            StdRand::with_seed(1337),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            (),
+        );
+        state.add_metadata(Tokens::new(vec![b"MAGIC".to_vec()]));
+
+        let mut num = 0_u32;
+        let mut flag = false;
+        let mut bytes: Vec<u8> = vec![];
+        let mut name: Option<String> = None;
+        let mut seen_magic = false;
+        for _ in 0..256 {
+            let before = num;
+            if num.mutate(&mut state).unwrap() == MutationResult::Mutated {
+                assert_ne!(num, before);
+            }
+            assert_eq!(flag.mutate(&mut state).unwrap(), MutationResult::Mutated);
+            bytes.mutate(&mut state).unwrap();
+            seen_magic |= bytes.windows(5).any(|w| w == b"MAGIC");
+            name.mutate(&mut state).unwrap();
+        }
+        assert!(seen_magic);
+
+        // The vector never grows past the max size
+        state.set_max_size(8);
+        let mut bytes: Vec<u8> = vec![];
+        for _ in 0..256 {
+            bytes.mutate(&mut state).unwrap();
+            assert!(bytes.len() <= 8);
+        }
+    }
+}
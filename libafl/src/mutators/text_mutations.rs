@@ -0,0 +1,419 @@
+//! Mutations for text formats, such as JSON, HTTP or CSV.
+//! They locate the ASCII numbers and the quoted strings in the input, and rewrite their values,
+//! instead of mutating the raw bytes as the binary integer mutations do.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::marker::PhantomData;
+
+use crate::{
+    bolts::{rands::Rand, tuples::Named},
+    inputs::{HasBytesVec, Input},
+    mutators::{mutations::ARITH_MAX, MutationResult, Mutator},
+    state::{HasMaxSize, HasRand},
+    Error,
+};
+
+/// Boundary values for the numbers, as decimal strings, so that they don't overflow any integer type
+const TEXT_BOUNDARIES: [&str; 14] = [
+    "0",
+    "-1",
+    "1",
+    "127",
+    "128",
+    "255",
+    "65535",
+    "2147483647",
+    "2147483648",
+    "-2147483648",
+    "4294967295",
+    "9223372036854775807",
+    "9223372036854775808",
+    "18446744073709551615",
+];
+
+/// Unicode escapes inserted into strings
+const TEXT_UNICODE_ESCAPES: [&str; 6] = [
+    "\\u0000", "\\u00e9", "\\u2028", "\\ud800", "\\udfff", "\\uffff",
+];
+
+/// Special sequences inserted into strings
+const TEXT_SPECIAL: [&str; 7] = ["\\\"", "\\\\", "\\n", "%s", "%n", "'", "\r\n"];
+
+/// The maximum length of the long digit runs and repeated strings
+const TEXT_MAX_RUN: u64 = 64;
+
+/// An ASCII number found in a text input
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextNumber {
+    /// The offset of the first byte, the sign or the `0x` prefix included
+    pub start: usize,
+    /// The offset after the last digit
+    pub end: usize,
+    /// Whether the number is hexadecimal, with a `0x` prefix
+    pub hex: bool,
+}
+
+/// Finds the decimal numbers, with an optional minus sign, and the `0x`-prefixed hexadecimal numbers in `bytes`
+#[must_use]
+pub fn find_text_numbers(bytes: &[u8]) -> Vec<TextNumber> {
+    let mut numbers = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        if !bytes[i].is_ascii_digit() {
+            i += 1;
+            continue;
+        }
+        let mut start = i;
+        if bytes[i] == b'0'
+            && (bytes.get(i + 1) == Some(&b'x') || bytes.get(i + 1) == Some(&b'X'))
+            && bytes.get(i + 2).map_or(false, u8::is_ascii_hexdigit)
+        {
+            i += 2;
+            while i < bytes.len() && bytes[i].is_ascii_hexdigit() {
+                i += 1;
+            }
+            numbers.push(TextNumber {
+                start,
+                end: i,
+                hex: true,
+            });
+            continue;
+        }
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+        if start > 0 && bytes[start - 1] == b'-' {
+            start -= 1;
+        }
+        numbers.push(TextNumber {
+            start,
+            end: i,
+            hex: false,
+        });
+    }
+    numbers
+}
+
+/// Finds the content of the double-quoted strings in `bytes`, skipping escaped quotes.
+/// The returned ranges exclude the quotes. Unterminated strings are ignored.
+#[must_use]
+pub fn find_text_strings(bytes: &[u8]) -> Vec<(usize, usize)> {
+    let mut strings = vec![];
+    let mut start = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], start) {
+            (b'\\', Some(_)) => i += 1,
+            (b'"', Some(s)) => {
+                strings.push((s, i));
+                start = None;
+            }
+            (b'"', None) => start = Some(i + 1),
+            _ => {}
+        }
+        i += 1;
+    }
+    strings
+}
+
+/// Replaces `start..end` with `replacement`, if the result fits in `max_size`
+fn replace_text<I>(
+    input: &mut I,
+    start: usize,
+    end: usize,
+    replacement: &[u8],
+    max_size: usize,
+) -> MutationResult
+where
+    I: HasBytesVec,
+{
+    if input.bytes().len() - (end - start) + replacement.len() > max_size
+        || input.bytes()[start..end] == *replacement
+    {
+        return MutationResult::Skipped;
+    }
+    input
+        .bytes_mut()
+        .splice(start..end, replacement.iter().copied());
+    MutationResult::Mutated
+}
+
+/// A [`Mutator`] rewriting the value of a random ASCII number of the input:
+/// arithmetics, negation, boundary values, and very long digit runs.
+#[derive(Default)]
+pub struct TextNumberMutator<I, R, S>
+where
+    I: Input + HasBytesVec,
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    phantom: PhantomData<(I, R, S)>,
+}
+
+impl<I, R, S> Mutator<I, S> for TextNumberMutator<I, R, S>
+where
+    I: Input + HasBytesVec,
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let numbers = find_text_numbers(input.bytes());
+        if numbers.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let number = *state.rand_mut().choose(&numbers);
+        let text = String::from_utf8_lossy(&input.bytes()[number.start..number.end]).into_owned();
+
+        let value = if number.hex {
+            u64::from_str_radix(&text[2..], 16).ok().map(i128::from)
+        } else {
+            text.parse::<i128>().ok()
+        };
+        let replacement = match (state.rand_mut().below(4), value) {
+            (0, Some(value)) => {
+                let num = 1 + i128::from(state.rand_mut().below(ARITH_MAX));
+                if state.rand_mut().below(2) == 0 {
+                    value.saturating_add(num)
+                } else {
+                    value.saturating_sub(num)
+                }
+                .to_string()
+            }
+            (1, Some(value)) if !number.hex => value.saturating_neg().to_string(),
+            (2, _) => {
+                let len = 1 + state.rand_mut().below(TEXT_MAX_RUN) as usize;
+                let digit = if state.rand_mut().below(2) == 0 {
+                    "9"
+                } else {
+                    "0"
+                };
+                format!("1{}", digit.repeat(len))
+            }
+            _ => {
+                let boundary = *state.rand_mut().choose(&TEXT_BOUNDARIES);
+                boundary.into()
+            }
+        };
+        let replacement = if number.hex {
+            match replacement.parse::<i128>() {
+                // Keep the prefix of the original number
+                Ok(value) => format!("{}{:x}", &text[..2], value as u64),
+                Err(_) => format!("{}{}", &text[..2], "f".repeat(replacement.len())),
+            }
+        } else {
+            replacement
+        };
+
+        let max_size = state.max_size();
+        Ok(replace_text(
+            input,
+            number.start,
+            number.end,
+            replacement.as_bytes(),
+            max_size,
+        ))
+    }
+}
+
+impl<I, R, S> Named for TextNumberMutator<I, R, S>
+where
+    I: Input + HasBytesVec,
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    fn name(&self) -> &str {
+        "TextNumberMutator"
+    }
+}
+
+impl<I, R, S> TextNumberMutator<I, R, S>
+where
+    I: Input + HasBytesVec,
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    /// Creates a new [`TextNumberMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// A [`Mutator`] rewriting the content of a random quoted string of the input:
+/// case flips, unicode escapes, special sequences, emptying and repetition.
+#[derive(Default)]
+pub struct TextStringMutator<I, R, S>
+where
+    I: Input + HasBytesVec,
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    phantom: PhantomData<(I, R, S)>,
+}
+
+impl<I, R, S> Mutator<I, S> for TextStringMutator<I, R, S>
+where
+    I: Input + HasBytesVec,
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let strings = find_text_strings(input.bytes());
+        if strings.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let (start, end) = *state.rand_mut().choose(&strings);
+        let mut content = input.bytes()[start..end].to_vec();
+
+        match state.rand_mut().below(5) {
+            0 => {
+                // Flip the case of the whole string, or of one of its letters
+                if content.is_empty() {
+                    return Ok(MutationResult::Skipped);
+                }
+                if state.rand_mut().below(2) == 0 {
+                    for b in &mut content {
+                        *b = flip_case(*b);
+                    }
+                } else {
+                    let idx = state.rand_mut().below(content.len() as u64) as usize;
+                    content[idx] = flip_case(content[idx]);
+                }
+            }
+            1 => {
+                let escape = *state.rand_mut().choose(&TEXT_UNICODE_ESCAPES);
+                let idx = state.rand_mut().below(content.len() as u64 + 1) as usize;
+                content.splice(idx..idx, escape.bytes());
+            }
+            2 => {
+                let special = *state.rand_mut().choose(&TEXT_SPECIAL);
+                let idx = state.rand_mut().below(content.len() as u64 + 1) as usize;
+                content.splice(idx..idx, special.bytes());
+            }
+            3 => content.clear(),
+            _ => {
+                if content.is_empty() {
+                    content.push(b'A');
+                }
+                let times = 2 + state.rand_mut().below(TEXT_MAX_RUN) as usize;
+                content = content.repeat(times);
+            }
+        }
+
+        let max_size = state.max_size();
+        Ok(replace_text(input, start, end, &content, max_size))
+    }
+}
+
+/// Flips the case of an ASCII letter
+fn flip_case(b: u8) -> u8 {
+    if b.is_ascii_alphabetic() {
+        b ^ 0x20
+    } else {
+        b
+    }
+}
+
+impl<I, R, S> Named for TextStringMutator<I, R, S>
+where
+    I: Input + HasBytesVec,
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    fn name(&self) -> &str {
+        "TextStringMutator"
+    }
+}
+
+impl<I, R, S> TextStringMutator<I, R, S>
+where
+    I: Input + HasBytesVec,
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    /// Creates a new [`TextStringMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bolts::rands::StdRand,
+        corpus::InMemoryCorpus,
+        inputs::{BytesInput, HasBytesVec},
+        mutators::{
+            text_mutations::{
+                find_text_numbers, find_text_strings, TextNumberMutator, TextStringMutator,
+            },
+            MutationResult, Mutator,
+        },
+        state::StdState,
+    };
+
+    #[test]
+    fn test_text_mutations() {
+        let text = b"{\"id\": -42, \"mask\": 0xFF, \"name\": \"a\\\"b\"}";
+        let numbers: Vec<_> = find_text_numbers(text)
+            .iter()
+            .map(|n| (&text[n.start..n.end], n.hex))
+            .collect();
+        assert_eq!(numbers, vec![(&b"-42"[..], false), (&b"0xFF"[..], true)]);
+        let strings: Vec<_> = find_text_strings(text)
+            .iter()
+            .map(|(start, end)| &text[*start..*end])
+            .collect();
+        assert_eq!(
+            strings,
+            vec![&b"id"[..], &b"mask"[..], &b"name"[..], &b"a\\\"b"[..]]
+        );
+
+        let mut state = StdState::new(
+            StdRand::with_seed(1337),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            (),
+        );
+        let mut numbers = TextNumberMutator::new();
+        let mut strings = TextStringMutator::new();
+        for _ in 0..64 {
+            let mut input = BytesInput::new(text.to_vec());
+            if numbers.mutate(&mut state, &mut input, 0).unwrap() == MutationResult::Mutated {
+                assert_ne!(input.bytes(), &text[..]);
+                // The structure around the numbers is preserved
+                assert_eq!(find_text_strings(input.bytes()).len(), 4);
+            }
+            let mut input = BytesInput::new(text.to_vec());
+            if strings.mutate(&mut state, &mut input, 0).unwrap() == MutationResult::Mutated {
+                assert!(input.bytes().starts_with(b"{\""));
+                assert!(input.bytes().ends_with(b"\"}"));
+            }
+        }
+
+        let mut input = BytesInput::new(b"no numbers here".to_vec());
+        assert_eq!(
+            numbers.mutate(&mut state, &mut input, 0).unwrap(),
+            MutationResult::Skipped
+        );
+    }
+}
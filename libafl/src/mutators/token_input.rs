@@ -0,0 +1,361 @@
+//! Mutations for [`TokenInput`]s, operating on runs of whole tokens instead of single bytes.
+
+use alloc::vec::Vec;
+use core::{cmp::min, marker::PhantomData};
+
+use crate::{
+    bolts::{
+        rands::Rand,
+        tuples::{tuple_list, tuple_list_type, Named},
+    },
+    inputs::{
+        token::{TokenInput, TokenTable},
+        HasLen,
+    },
+    mutators::{MutationResult, Mutator},
+    state::{HasMaxSize, HasRand},
+    Error,
+};
+
+/// The maximum number of tokens in a run touched by a single mutation
+const MAX_TOKEN_RUN: u64 = 8;
+
+/// Picks a random run of tokens in an input of `len` tokens, `len` must be at least 1.
+/// Returns the start and the length of the run.
+fn rand_run<R>(rand: &mut R, len: usize) -> (usize, usize)
+where
+    R: Rand,
+{
+    let start = rand.below(len as u64) as usize;
+    let max_run = min((len - start) as u64, MAX_TOKEN_RUN);
+    (start, 1 + rand.below(max_run) as usize)
+}
+
+/// Inserts a run of random tokens from the [`TokenTable`] at a random position
+pub struct TokenRunInsertMutator<'a, R, S>
+where
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    table: &'a TokenTable,
+    phantom: PhantomData<(R, S)>,
+}
+
+impl<'a, R, S> Mutator<TokenInput, S> for TokenRunInsertMutator<'a, R, S>
+where
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut TokenInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if self.table.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let max_size = state.max_size();
+        let mut size = self.table.rendered_len(input.ids());
+        let off = state.rand_mut().below((input.len() + 1) as u64) as usize;
+        let run = 1 + state.rand_mut().below(MAX_TOKEN_RUN) as usize;
+
+        let mut ids = vec![];
+        for _ in 0..run {
+            let id = state.rand_mut().below(self.table.len() as u64) as usize;
+            let token_len = self.table.get(id).unwrap().len();
+            if size + token_len > max_size {
+                break;
+            }
+            size += token_len;
+            ids.push(id);
+        }
+        if ids.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+
+        input.ids_mut().splice(off..off, ids);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<'a, R, S> Named for TokenRunInsertMutator<'a, R, S>
+where
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    fn name(&self) -> &str {
+        "TokenRunInsertMutator"
+    }
+}
+
+impl<'a, R, S> TokenRunInsertMutator<'a, R, S>
+where
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    /// Creates a new [`TokenRunInsertMutator`], inserting tokens of the given table.
+    #[must_use]
+    pub fn new(table: &'a TokenTable) -> Self {
+        Self {
+            table,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Deletes a random run of tokens
+#[derive(Default)]
+pub struct TokenRunDeleteMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    phantom: PhantomData<(R, S)>,
+}
+
+impl<R, S> Mutator<TokenInput, S> for TokenRunDeleteMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut TokenInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.len() <= 1 {
+            return Ok(MutationResult::Skipped);
+        }
+        let (start, run) = rand_run(state.rand_mut(), input.len());
+        input.ids_mut().drain(start..start + run);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<R, S> Named for TokenRunDeleteMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    fn name(&self) -> &str {
+        "TokenRunDeleteMutator"
+    }
+}
+
+impl<R, S> TokenRunDeleteMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    /// Creates a new [`TokenRunDeleteMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Duplicates a random run of tokens, inserting the copy at a random position
+pub struct TokenRunDuplicateMutator<'a, R, S>
+where
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    table: &'a TokenTable,
+    phantom: PhantomData<(R, S)>,
+}
+
+impl<'a, R, S> Mutator<TokenInput, S> for TokenRunDuplicateMutator<'a, R, S>
+where
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut TokenInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let (start, run) = rand_run(state.rand_mut(), input.len());
+        let copy = input.ids()[start..start + run].to_vec();
+        if self.table.rendered_len(input.ids()) + self.table.rendered_len(&copy) > state.max_size()
+        {
+            return Ok(MutationResult::Skipped);
+        }
+        let to = state.rand_mut().below((input.len() + 1) as u64) as usize;
+        input.ids_mut().splice(to..to, copy);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<'a, R, S> Named for TokenRunDuplicateMutator<'a, R, S>
+where
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    fn name(&self) -> &str {
+        "TokenRunDuplicateMutator"
+    }
+}
+
+impl<'a, R, S> TokenRunDuplicateMutator<'a, R, S>
+where
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    /// Creates a new [`TokenRunDuplicateMutator`], measuring the tokens of the given table.
+    #[must_use]
+    pub fn new(table: &'a TokenTable) -> Self {
+        Self {
+            table,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Swaps two random, non-overlapping, runs of tokens
+#[derive(Default)]
+pub struct TokenRunSwapMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    phantom: PhantomData<(R, S)>,
+}
+
+impl<R, S> Mutator<TokenInput, S> for TokenRunSwapMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut TokenInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.len() < 2 {
+            return Ok(MutationResult::Skipped);
+        }
+        // Split the input in two halves, and pick one run from each of them
+        let split = 1 + state.rand_mut().below((input.len() - 1) as u64) as usize;
+        let (first, first_run) = rand_run(state.rand_mut(), split);
+        let (second, second_run) = rand_run(state.rand_mut(), input.len() - split);
+        let second = split + second;
+
+        let ids = input.ids_mut();
+        let first_ids = ids[first..first + first_run].to_vec();
+        let second_ids: Vec<_> = ids.splice(second..second + second_run, first_ids).collect();
+        ids.splice(first..first + first_run, second_ids);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<R, S> Named for TokenRunSwapMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    fn name(&self) -> &str {
+        "TokenRunSwapMutator"
+    }
+}
+
+impl<R, S> TokenRunSwapMutator<R, S>
+where
+    S: HasRand<R>,
+    R: Rand,
+{
+    /// Creates a new [`TokenRunSwapMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Get the mutations for [`TokenInput`]s over the given [`TokenTable`]
+#[must_use]
+pub fn token_input_mutations<'a, R, S>(
+    table: &'a TokenTable,
+) -> tuple_list_type!(
+       TokenRunInsertMutator<'a, R, S>,
+       TokenRunDeleteMutator<R, S>,
+       TokenRunDuplicateMutator<'a, R, S>,
+       TokenRunSwapMutator<R, S>,
+   )
+where
+    S: HasRand<R> + HasMaxSize,
+    R: Rand,
+{
+    tuple_list!(
+        TokenRunInsertMutator::new(table),
+        TokenRunDeleteMutator::new(),
+        TokenRunDuplicateMutator::new(table),
+        TokenRunSwapMutator::new(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bolts::{rands::StdRand, tuples::HasLen as _},
+        corpus::InMemoryCorpus,
+        inputs::{
+            token::{TokenInput, TokenTable},
+            HasLen, HasTargetBytes,
+        },
+        mutators::{token_input::token_input_mutations, MutatorsTuple},
+        state::StdState,
+    };
+
+    #[test]
+    fn test_token_input_mutators() {
+        let table = TokenTable::new(vec![
+            b"SELECT ".to_vec(),
+            b"* ".to_vec(),
+            b"FROM ".to_vec(),
+            b"t ".to_vec(),
+            b"WHERE ".to_vec(),
+            b"1 ".to_vec(),
+        ]);
+        let input = table.input_from_ids(&[0, 1, 2, 3]).unwrap();
+        assert_eq!(
+            input.bind(&table).target_bytes().as_slice(),
+            b"SELECT * FROM t "
+        );
+        assert!(table.input_from_ids(&[6]).is_none());
+
+        let rand = StdRand::with_seed(1337);
+        let mut state = StdState::new(
+            rand,
+            InMemoryCorpus::<TokenInput>::new(),
+            InMemoryCorpus::new(),
+            (),
+        );
+
+        let mut mutations = token_input_mutations(&table);
+        for idx in 0..mutations.len() {
+            for _ in 0..32 {
+                let mut mutant = input.clone();
+                mutations
+                    .get_and_mutate(idx, &mut state, &mut mutant, 0)
+                    .unwrap();
+                // Every token must still be whole, and in the table
+                assert!(mutant.ids().iter().all(|id| table.get(*id).is_some()));
+                assert_eq!(
+                    mutant.bind(&table).target_bytes().as_slice().len(),
+                    mutant.bind(&table).len()
+                );
+                assert!(mutant.len() <= input.len() + 8);
+            }
+        }
+    }
+}
@@ -1,10 +1,11 @@
 //! The `MapObserver` provides access a map, usually injected into the target
 
+use ahash::AHasher;
 use alloc::{
     string::{String, ToString},
     vec::Vec,
 };
-use core::slice::from_raw_parts_mut;
+use core::{hash::Hasher, slice::from_raw_parts_mut};
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -53,6 +54,22 @@ where
         }
         Ok(())
     }
+
+    /// Hash the indexes of the entries hit in the last run, ignoring the hitcounts.
+    /// Runs with the same hash took the same path, modulo collisions.
+    fn coverage_hash(&self) -> u64
+    where
+        T: PartialEq,
+    {
+        let initial = self.initial();
+        let mut hasher = AHasher::new_with_keys(0, 0);
+        for (i, x) in self.map()[0..self.usable_count()].iter().enumerate() {
+            if *x != initial {
+                hasher.write_usize(i);
+            }
+        }
+        hasher.finish()
+    }
 }
 
 /// The Map Observer retrieves the state of a map,
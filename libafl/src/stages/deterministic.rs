@@ -0,0 +1,510 @@
+//! The deterministic stage runs the deterministic passes of AFL on new corpus entries:
+//! walking bitflips, arithmetics, interesting values and dictionary overwrites.
+//! Bytes that never change the coverage, according to the effector map, are skipped after the bitflips.
+
+use alloc::{string::String, vec::Vec};
+use core::marker::PhantomData;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId},
+    executors::HasObservers,
+    fuzzer::Evaluator,
+    inputs::{HasBytesVec, Input},
+    mutators::{
+        mutations::{ARITH_MAX, INTERESTING_16, INTERESTING_32, INTERESTING_8},
+        Tokens,
+    },
+    observers::{MapObserver, ObserversTuple},
+    stages::Stage,
+    state::{HasCorpus, HasMetadata},
+    Error,
+};
+
+/// Inputs shorter than this don't use the effector map, all their bytes are considered effective
+const EFF_MIN_LEN: usize = 128;
+
+/// If more than this percentage of the bytes are effective, all of them are considered effective
+const EFF_MAX_PERC: usize = 90;
+
+/// The default maximum length of the inputs processed by the [`DeterministicStage`]
+pub const DEFAULT_DETERMINISTIC_MAX_LEN: usize = 4096;
+
+/// Returns `true` if the change of a value, given as the xor of the old and new value,
+/// could be the result of one of the walking bitflips
+fn could_be_bitflip(xor_val: u32) -> bool {
+    if xor_val == 0 {
+        return true;
+    }
+    let shift = xor_val.trailing_zeros();
+    let xor_val = xor_val >> shift;
+    // 1, 2 and 4 bits flips, at any position
+    if xor_val == 1 || xor_val == 3 || xor_val == 15 {
+        return true;
+    }
+    // 8, 16 and 32 bits flips, byte-aligned only
+    shift & 7 == 0 && (xor_val == 0xff || xor_val == 0xffff || xor_val == 0xffff_ffff)
+}
+
+/// Returns `true` if `new_val` could be the result of one of the arithmetics on the `width` bytes of `old_val`:
+/// a small addition or subtraction on one of its bytes, words, in both byte orders, or on the whole dword
+fn could_be_arith(old_val: u32, new_val: u32, width: usize) -> bool {
+    if old_val == new_val {
+        return true;
+    }
+    let in_range = |diff: u32| diff <= ARITH_MAX as u32;
+
+    // A change of a single byte
+    let mut diffs = 0;
+    let (mut ov, mut nv) = (0_u8, 0_u8);
+    for i in 0..width {
+        let (a, b) = ((old_val >> (8 * i)) as u8, (new_val >> (8 * i)) as u8);
+        if a != b {
+            diffs += 1;
+            ov = a;
+            nv = b;
+        }
+    }
+    if diffs == 1 && (in_range(ov.wrapping_sub(nv).into()) || in_range(nv.wrapping_sub(ov).into()))
+    {
+        return true;
+    }
+    if width == 1 {
+        return false;
+    }
+
+    // A change of a single word, in either byte order
+    let mut diffs = 0;
+    let (mut ov, mut nv) = (0_u16, 0_u16);
+    for i in 0..width / 2 {
+        let (a, b) = ((old_val >> (16 * i)) as u16, (new_val >> (16 * i)) as u16);
+        if a != b {
+            diffs += 1;
+            ov = a;
+            nv = b;
+        }
+    }
+    if diffs == 1 {
+        for (ov, nv) in &[(ov, nv), (ov.swap_bytes(), nv.swap_bytes())] {
+            if in_range(ov.wrapping_sub(*nv).into()) || in_range(nv.wrapping_sub(*ov).into()) {
+                return true;
+            }
+        }
+    }
+
+    // A change of the whole dword, in either byte order
+    width == 4
+        && [
+            (old_val, new_val),
+            (old_val.swap_bytes(), new_val.swap_bytes()),
+        ]
+        .iter()
+        .any(|(ov, nv)| in_range(ov.wrapping_sub(*nv)) || in_range(nv.wrapping_sub(*ov)))
+}
+
+/// Reads an integer of `width` bytes at `off`
+fn read_int(bytes: &[u8], off: usize, width: usize, big_endian: bool) -> u32 {
+    let mut val = 0;
+    for i in 0..width {
+        let byte = if big_endian {
+            bytes[off + i]
+        } else {
+            bytes[off + width - 1 - i]
+        };
+        val = (val << 8) | u32::from(byte);
+    }
+    val
+}
+
+/// Writes an integer of `width` bytes at `off`
+fn write_int(bytes: &mut [u8], off: usize, width: usize, big_endian: bool, val: u32) {
+    for i in 0..width {
+        let byte = (val >> (8 * i)) as u8;
+        if big_endian {
+            bytes[off + width - 1 - i] = byte;
+        } else {
+            bytes[off + i] = byte;
+        }
+    }
+}
+
+/// The mask for integers of `width` bytes
+fn width_mask(width: usize) -> u32 {
+    if width >= 4 {
+        u32::MAX
+    } else {
+        (1 << (8 * width)) - 1
+    }
+}
+
+/// The interesting values for integers of `width` bytes
+fn interesting_values(width: usize) -> Vec<u32> {
+    match width {
+        1 => INTERESTING_8.iter().map(|v| *v as u32 & 0xff).collect(),
+        2 => INTERESTING_16.iter().map(|v| *v as u32 & 0xffff).collect(),
+        _ => INTERESTING_32.iter().map(|v| *v as u32).collect(),
+    }
+}
+
+/// Marks a testcase as processed by the [`DeterministicStage`]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct DeterministicMetadata {
+    /// The number of executions of the deterministic passes, `0` if the input was too long
+    pub executions: usize,
+    /// The number of bytes marked as effective by the effector map
+    pub effective_bytes: usize,
+}
+
+crate::impl_serdeany!(DeterministicMetadata);
+
+/// A stage running the deterministic passes of AFL, once per corpus entry.
+/// The coverage used for the effector map comes from the `map_observer_name` observer.
+/// Combine it with a [`crate::stages::StdMutationalStage`] to continue with the havoc mutations.
+#[derive(Clone, Debug)]
+pub struct DeterministicStage<C, E, EM, I, O, OT, S, T, Z>
+where
+    C: Corpus<I>,
+    E: HasObservers<OT>,
+    I: Input + HasBytesVec,
+    O: MapObserver<T>,
+    OT: ObserversTuple,
+    S: HasCorpus<C, I> + HasMetadata,
+    T: Default + Copy + PartialEq,
+    Z: Evaluator<E, EM, I, S>,
+{
+    map_observer_name: String,
+    max_len: usize,
+    #[allow(clippy::type_complexity)]
+    phantom: PhantomData<(C, E, EM, I, O, OT, S, T, Z)>,
+}
+
+impl<C, E, EM, I, O, OT, S, T, Z> Stage<E, EM, S, Z>
+    for DeterministicStage<C, E, EM, I, O, OT, S, T, Z>
+where
+    C: Corpus<I>,
+    E: HasObservers<OT>,
+    I: Input + HasBytesVec,
+    O: MapObserver<T>,
+    OT: ObserversTuple,
+    S: HasCorpus<C, I> + HasMetadata,
+    T: Default + Copy + PartialEq,
+    Z: Evaluator<E, EM, I, S>,
+{
+    #[allow(clippy::too_many_lines)]
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+        corpus_idx: CorpusId,
+    ) -> Result<(), Error> {
+        let input = {
+            let mut testcase = state.corpus().get(corpus_idx)?.borrow_mut();
+            if testcase.metadata().get::<DeterministicMetadata>().is_some() {
+                return Ok(());
+            }
+            testcase.load_input()?.clone()
+        };
+        let mut bytes = input.bytes().to_vec();
+        let len = bytes.len();
+        if len == 0 || len > self.max_len {
+            state
+                .corpus()
+                .get(corpus_idx)?
+                .borrow_mut()
+                .add_metadata(DeterministicMetadata::default());
+            return Ok(());
+        }
+        let tokens = state
+            .metadata()
+            .get::<Tokens>()
+            .map_or_else(Vec::new, |t| t.tokens().to_vec());
+
+        let map_observer_name = &self.map_observer_name;
+        let mut executions = 0;
+        // Evaluates the bytes, returning the coverage hash
+        let mut run = |bytes: &[u8]| -> Result<u64, Error> {
+            let mut mutated = input.clone();
+            mutated.bytes_mut().copy_from_slice(bytes);
+            fuzzer.evaluate_input(state, executor, manager, mutated)?;
+            executions += 1;
+            Ok(executor
+                .observers()
+                .match_name::<O>(map_observer_name)
+                .ok_or_else(|| {
+                    Error::KeyNotFound(format!("Map observer {} not found", map_observer_name))
+                })?
+                .coverage_hash())
+        };
+        let hash = run(&bytes)?;
+
+        // Walking bitflips, 1, 2 and 4 bits
+        for width in &[1, 2, 4] {
+            for bit in 0..=len * 8 - width {
+                for b in bit..bit + width {
+                    bytes[b >> 3] ^= 128 >> (b & 7);
+                }
+                run(&bytes)?;
+                for b in bit..bit + width {
+                    bytes[b >> 3] ^= 128 >> (b & 7);
+                }
+            }
+        }
+
+        // Walking byte flips, building the effector map
+        let mut eff = vec![false; len];
+        for i in 0..len {
+            bytes[i] ^= 0xff;
+            eff[i] = run(&bytes)? != hash;
+            bytes[i] ^= 0xff;
+        }
+        if len < EFF_MIN_LEN || eff.iter().filter(|e| **e).count() * 100 >= len * EFF_MAX_PERC {
+            eff = vec![true; len];
+        }
+        let effective_bytes = eff.iter().filter(|e| **e).count();
+        let effective = |off: usize, width: usize| eff[off..off + width].iter().any(|e| *e);
+
+        for width in &[2, 4] {
+            for i in 0..len.saturating_sub(width - 1) {
+                if !effective(i, *width) {
+                    continue;
+                }
+                for b in &mut bytes[i..i + width] {
+                    *b ^= 0xff;
+                }
+                run(&bytes)?;
+                for b in &mut bytes[i..i + width] {
+                    *b ^= 0xff;
+                }
+            }
+        }
+
+        // Arithmetics and interesting values, in both byte orders, skipping what the previous passes already did
+        for width in &[1, 2, 4] {
+            let orders: &[bool] = if *width == 1 {
+                &[false]
+            } else {
+                &[false, true]
+            };
+            let interesting = interesting_values(*width);
+            for i in 0..len.saturating_sub(width - 1) {
+                if !effective(i, *width) {
+                    continue;
+                }
+                for big_endian in orders {
+                    let orig = read_int(&bytes, i, *width, *big_endian);
+                    let arith = (1..=ARITH_MAX as u32)
+                        .flat_map(|j| vec![orig.wrapping_add(j), orig.wrapping_sub(j)])
+                        .map(|v| v & width_mask(*width));
+                    for val in arith {
+                        if could_be_bitflip(orig ^ val) {
+                            continue;
+                        }
+                        write_int(&mut bytes, i, *width, *big_endian, val);
+                        run(&bytes)?;
+                        write_int(&mut bytes, i, *width, *big_endian, orig);
+                    }
+                    for val in interesting.iter().copied() {
+                        if could_be_bitflip(orig ^ val) || could_be_arith(orig, val, *width) {
+                            continue;
+                        }
+                        write_int(&mut bytes, i, *width, *big_endian, val);
+                        run(&bytes)?;
+                        write_int(&mut bytes, i, *width, *big_endian, orig);
+                    }
+                }
+            }
+        }
+
+        // Dictionary overwrites
+        for i in 0..len {
+            if !eff[i] {
+                continue;
+            }
+            for token in &tokens {
+                let end = i + token.len();
+                if token.is_empty() || end > len || bytes[i..end] == token[..] {
+                    continue;
+                }
+                let orig = bytes[i..end].to_vec();
+                bytes[i..end].copy_from_slice(token);
+                run(&bytes)?;
+                bytes[i..end].copy_from_slice(&orig);
+            }
+        }
+
+        state
+            .corpus()
+            .get(corpus_idx)?
+            .borrow_mut()
+            .add_metadata(DeterministicMetadata {
+                executions,
+                effective_bytes,
+            });
+        Ok(())
+    }
+}
+
+impl<C, E, EM, I, O, OT, S, T, Z> DeterministicStage<C, E, EM, I, O, OT, S, T, Z>
+where
+    C: Corpus<I>,
+    E: HasObservers<OT>,
+    I: Input + HasBytesVec,
+    O: MapObserver<T>,
+    OT: ObserversTuple,
+    S: HasCorpus<C, I> + HasMetadata,
+    T: Default + Copy + PartialEq,
+    Z: Evaluator<E, EM, I, S>,
+{
+    /// Creates a new [`DeterministicStage`], for inputs up to [`DEFAULT_DETERMINISTIC_MAX_LEN`] bytes
+    #[must_use]
+    pub fn new(map_observer_name: &str) -> Self {
+        Self::with_max_len(map_observer_name, DEFAULT_DETERMINISTIC_MAX_LEN)
+    }
+
+    /// Creates a new [`DeterministicStage`], for inputs up to `max_len` bytes.
+    /// Longer inputs are marked as processed, without running the passes.
+    #[must_use]
+    pub fn with_max_len(map_observer_name: &str, max_len: usize) -> Self {
+        Self {
+            map_observer_name: map_observer_name.into(),
+            max_len,
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bolts::{rands::StdRand, tuples::tuple_list},
+        corpus::{Corpus, InMemoryCorpus, QueueCorpusScheduler, Testcase},
+        events::SimpleEventManager,
+        executors::{ExitKind, InProcessExecutor},
+        inputs::{BytesInput, HasBytesVec},
+        observers::StdMapObserver,
+        stages::{
+            deterministic::{
+                could_be_arith, could_be_bitflip, read_int, write_int, DeterministicMetadata,
+                DeterministicStage,
+            },
+            Stage,
+        },
+        state::{HasCorpus, HasMetadata, StdState},
+        stats::SimpleStats,
+        StdFuzzer,
+    };
+
+    static mut DETERMINISTIC_MAP: [u8; 16] = [0; 16];
+
+    #[test]
+    fn test_deterministic_helpers() {
+        assert!(could_be_bitflip(0));
+        assert!(could_be_bitflip(0b110 << 9));
+        assert!(could_be_bitflip(0xff << 16));
+        assert!(!could_be_bitflip(0xff << 4));
+        assert!(!could_be_bitflip(5));
+
+        assert!(could_be_arith(100, 127, 1));
+        assert!(could_be_arith(0x0102, 0x0002, 2));
+        assert!(could_be_arith(0x0010_0000, 0x000f_ffff, 4));
+        assert!(!could_be_arith(0, 0x80, 1));
+        assert!(!could_be_arith(0, 0x8000, 2));
+        assert!(!could_be_arith(0x10, 0x1000_0000, 4));
+
+        let mut bytes = vec![0_u8; 6];
+        write_int(&mut bytes, 1, 4, true, 0xdead_beef);
+        assert_eq!(bytes, [0, 0xde, 0xad, 0xbe, 0xef, 0]);
+        assert_eq!(read_int(&bytes, 1, 4, true), 0xdead_beef);
+        assert_eq!(read_int(&bytes, 1, 2, false), 0xadde);
+        write_int(&mut bytes, 4, 2, false, 0x1234);
+        assert_eq!(&bytes[4..], [0x34, 0x12]);
+    }
+
+    #[test]
+    fn test_deterministic_stage() {
+        type TestState = StdState<
+            InMemoryCorpus<BytesInput>,
+            (),
+            BytesInput,
+            StdRand,
+            InMemoryCorpus<BytesInput>,
+        >;
+
+        // Long enough to use the effector map
+        let len = 128;
+        let mut original = vec![0_u8; len];
+        original[0] = 0x40;
+
+        let mut state: TestState = StdState::new(
+            StdRand::with_seed(1337),
+            InMemoryCorpus::new(),
+            InMemoryCorpus::new(),
+            (),
+        );
+        let corpus_idx = state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(original.clone())))
+            .unwrap();
+
+        let stats = SimpleStats::new(|s| {
+            println!("{}", s);
+        });
+        let mut mgr = SimpleEventManager::new(stats);
+        let mut fuzzer: StdFuzzer<
+            InMemoryCorpus<BytesInput>,
+            _,
+            (),
+            BytesInput,
+            (),
+            _,
+            TestState,
+            InMemoryCorpus<BytesInput>,
+        > = StdFuzzer::new(QueueCorpusScheduler::new(), (), ());
+
+        // Only the first byte changes the coverage
+        let mut executed = vec![];
+        let mut harness = |input: &BytesInput| {
+            executed.push(input.bytes().to_vec());
+            unsafe {
+                DETERMINISTIC_MAP[0] = 1;
+                DETERMINISTIC_MAP[1 + (input.bytes()[0] & 7) as usize] = 1;
+            }
+            ExitKind::Ok
+        };
+        let observer = StdMapObserver::new("map", unsafe { &mut DETERMINISTIC_MAP });
+        let mut executor = InProcessExecutor::new(
+            &mut harness,
+            tuple_list!(observer),
+            &mut fuzzer,
+            &mut state,
+            &mut mgr,
+        )
+        .unwrap();
+
+        let mut stage =
+            DeterministicStage::<_, _, _, _, StdMapObserver<u8>, _, _, u8, _>::new("map");
+        stage
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut mgr, corpus_idx)
+            .unwrap();
+        // The testcase is processed only once
+        stage
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut mgr, corpus_idx)
+            .unwrap();
+        drop(executor);
+
+        let testcase = state.corpus().get(corpus_idx).unwrap().borrow();
+        let meta = testcase.metadata().get::<DeterministicMetadata>().unwrap();
+        assert_eq!(meta.executions, executed.len());
+        assert_eq!(meta.effective_bytes, 1);
+
+        // After the original run, the bitflips and the byte flips, only the first effective dword is mutated
+        let bitflips = 1 + (len * 8) + (len * 8 - 1) + (len * 8 - 3) + len;
+        assert!(executed.len() > bitflips);
+        for bytes in &executed[bitflips..] {
+            assert_ne!(bytes[..4], original[..4]);
+            assert_eq!(bytes[4..], original[4..]);
+        }
+    }
+}
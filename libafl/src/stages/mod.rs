@@ -11,9 +11,22 @@ pub use mutational::{MutationalStage, StdMutationalStage};
 pub mod tracing;
 pub use tracing::{ShadowTracingStage, TracingStage};
 
-//pub mod power;
-//pub use power::PowerMutationalStage;
-use crate::Error;
+pub mod redqueen;
+pub use redqueen::{RedQueenMetadata, RedQueenStage};
+
+pub mod deterministic;
+pub use deterministic::{DeterministicMetadata, DeterministicStage};
+
+pub mod tokens;
+pub use tokens::{CmpTokensMetadata, CmpTokensStage};
+
+pub mod power;
+pub use power::{
+    PowerMutationalStage, PowerSchedule, PowerScheduleMetadata, PowerScoreContext,
+    PowerTestcaseMetadata,
+};
+
+use crate::{corpus::CorpusId, Error};
 
 /// A stage is one step in the fuzzing process.
 /// Multiple stages will be scheduled one by one for each input.
@@ -25,7 +38,7 @@ pub trait Stage<E, EM, S, Z> {
         executor: &mut E,
         state: &mut S,
         manager: &mut EM,
-        corpus_idx: usize,
+        corpus_idx: CorpusId,
     ) -> Result<(), Error>;
 }
 
@@ -38,7 +51,7 @@ pub trait StagesTuple<E, EM, S, Z> {
         executor: &mut E,
         state: &mut S,
         manager: &mut EM,
-        corpus_idx: usize,
+        corpus_idx: CorpusId,
     ) -> Result<(), Error>;
 }
 
@@ -49,7 +62,7 @@ impl<E, EM, S, Z> StagesTuple<E, EM, S, Z> for () {
         _: &mut E,
         _: &mut S,
         _: &mut EM,
-        _: usize,
+        _: CorpusId,
     ) -> Result<(), Error> {
         Ok(())
     }
@@ -66,7 +79,7 @@ where
         executor: &mut E,
         state: &mut S,
         manager: &mut EM,
-        corpus_idx: usize,
+        corpus_idx: CorpusId,
     ) -> Result<(), Error> {
         // Perform the current stage
         self.0
@@ -1,12 +1,12 @@
-use core::marker::PhantomData;
+use core::{marker::PhantomData, time::Duration};
 
 use crate::{
-    bolts::rands::Rand,
-    corpus::Corpus,
+    bolts::{current_time, rands::Rand},
+    corpus::{Corpus, CorpusId},
     fuzzer::Evaluator,
     inputs::Input,
     mark_feature_time,
-    mutators::Mutator,
+    mutators::{MutationResult, Mutator},
     stages::Stage,
     start_timer,
     state::{HasClientPerfStats, HasCorpus, HasRand},
@@ -35,8 +35,22 @@ where
     /// The mutator registered for this stage (mutable)
     fn mutator_mut(&mut self) -> &mut M;
 
-    /// Gets the number of iterations this mutator should run for.
-    fn iterations(&self, state: &mut S) -> usize;
+    /// Gets the number of iterations this mutator should run for on the given testcase.
+    fn iterations(&self, state: &mut S, corpus_idx: CorpusId) -> Result<usize, Error>;
+
+    /// Called after each execution of a mutant of the testcase `corpus_idx`, while the observers still hold its run.
+    /// `new_idx` is the index of the mutant, if it was added to the corpus,
+    /// and `exec_time` the time it took to evaluate it.
+    fn post_evaluation(
+        &mut self,
+        _executor: &mut E,
+        _state: &mut S,
+        _corpus_idx: CorpusId,
+        _new_idx: Option<CorpusId>,
+        _exec_time: Duration,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
 
     /// Runs this (mutational) stage for the given testcase
     #[allow(clippy::cast_possible_wrap)] // more than i32 stages on 32 bit system - highly unlikely...
@@ -46,9 +60,9 @@ where
         executor: &mut E,
         state: &mut S,
         manager: &mut EM,
-        corpus_idx: usize,
+        corpus_idx: CorpusId,
     ) -> Result<(), Error> {
-        let num = self.iterations(state);
+        let num = self.iterations(state, corpus_idx)?;
 
         for i in 0..num {
             start_timer!(state);
@@ -61,14 +75,23 @@ where
             mark_feature_time!(state, PerfFeature::GetInputFromCorpus);
 
             start_timer!(state);
-            self.mutator_mut().mutate(state, &mut input, i as i32)?;
+            let mutated = self.mutator_mut().mutate(state, &mut input, i as i32)?;
             mark_feature_time!(state, PerfFeature::Mutate);
 
-            // Time is measured directly the `evaluate_input` function
-            let (_, corpus_idx) = fuzzer.evaluate_input(state, executor, manager, input)?;
+            // Skipped mutations are not executed, but the mutator still gets to know about them
+            let new_idx = if mutated == MutationResult::Skipped {
+                None
+            } else {
+                // Time is measured directly the `evaluate_input` function
+                let start = current_time();
+                let (_, new_idx) = fuzzer.evaluate_input(state, executor, manager, input)?;
+                let exec_time = current_time() - start;
+                self.post_evaluation(executor, state, corpus_idx, new_idx, exec_time)?;
+                new_idx
+            };
 
             start_timer!(state);
-            self.mutator_mut().post_exec(state, i as i32, corpus_idx)?;
+            self.mutator_mut().post_exec(state, i as i32, new_idx)?;
             mark_feature_time!(state, PerfFeature::MutatePostExec);
         }
         Ok(())
@@ -118,8 +141,8 @@ where
     }
 
     /// Gets the number of iterations as a random number
-    fn iterations(&self, state: &mut S) -> usize {
-        1 + state.rand_mut().below(DEFAULT_MUTATIONAL_MAX_ITERATIONS) as usize
+    fn iterations(&self, state: &mut S, _corpus_idx: CorpusId) -> Result<usize, Error> {
+        Ok(1 + state.rand_mut().below(DEFAULT_MUTATIONAL_MAX_ITERATIONS) as usize)
     }
 }
 
@@ -140,7 +163,7 @@ where
         executor: &mut E,
         state: &mut S,
         manager: &mut EM,
-        corpus_idx: usize,
+        corpus_idx: CorpusId,
     ) -> Result<(), Error> {
         let ret = self.perform_mutational(fuzzer, executor, state, manager, corpus_idx);
 
@@ -1,48 +1,315 @@
-use core::marker::PhantomData;
+//! The power schedules of AFLFast and AFL++, giving the testcases exercising rare paths more energy,
+//! i.e. more mutations, in the [`PowerMutationalStage`].
+
+use alloc::{string::String, vec::Vec};
+use core::{marker::PhantomData, time::Duration};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    bolts::rands::Rand,
-    corpus::{Corpus, CorpusScheduler},
-    events::EventManager,
-    executors::{Executor, HasObservers},
+    bolts::current_time,
+    corpus::{Corpus, CorpusId, IsFavoredMetadata, TopRatedsMetadata},
+    executors::{Executor, HasExecHooksTuple, HasObservers, HasObserversHooks},
+    fuzzer::Evaluator,
     inputs::Input,
     mutators::Mutator,
-    observers::ObserversTuple,
-    stages::{Stage, MutationalStage},
-    state::{Evaluator, HasCorpus, HasRand},
+    observers::{MapObserver, ObserversTuple},
+    stages::{MutationalStage, Stage},
+    state::{HasClientPerfStats, HasCorpus, HasExecutions, HasMetadata},
     Error,
 };
 
-/// The mutational stage using power schedules
+/// The number of path frequency counters, paths are bucketed by the hash of their coverage
+pub const N_FUZZ_SIZE: usize = 1 << 16;
+/// The number of mutations given to a testcase of average score
+pub const HAVOC_CYCLES: u64 = 256;
+/// The minimum number of mutations given to a testcase with a non-zero score
+pub const HAVOC_MIN: u64 = 16;
+/// The maximum score of a testcase, as a multiple of the average score
+pub const HAVOC_MAX_MULT: f64 = 64.0;
+/// The exponent base of the schedules
+const POWER_BETA: f64 = 1.0;
+/// The maximum factor applied by the schedules
+const MAX_FACTOR: f64 = POWER_BETA * 32.0;
+
+/// The power schedules of AFLFast and AFL++
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PowerSchedule {
+    /// The AFL score, only depending on the speed, coverage, depth and age of the testcase
+    Explore,
+    /// Boost the testcases on rare paths, and shrink the energy of the ones on frequent paths
+    Fast,
+    /// Skip the testcases on paths more frequent than the average, exponentially boosting the others
+    Coe,
+    /// Boost the testcases linearly with how often they have been fuzzed, divided by the path frequency
+    Lin,
+    /// Boost the testcases quadratically with how often they have been fuzzed, divided by the path frequency
+    Quad,
+    /// Boost the testcases favored for many map entries, and shrink the ones on frequent paths
+    Rare,
+}
+
+/// The global statistics of the power schedules
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PowerScheduleMetadata {
+    /// The sum of the execution times of the calibrated testcases
+    pub exec_time: Duration,
+    /// The number of calibrated testcases
+    pub cycles: u64,
+    /// The sum of the bitmap sizes of the calibrated testcases
+    pub bitmap_size: u64,
+    /// The number of times the first testcase of the corpus has been fuzzed, i.e. of queue cycles
+    pub queue_cycles: u64,
+    /// The number of executions taking each path, bucketed by hash
+    pub n_fuzz: Vec<u32>,
+}
+
+crate::impl_serdeany!(PowerScheduleMetadata);
+
+impl PowerScheduleMetadata {
+    /// Creates a new [`struct@PowerScheduleMetadata`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            exec_time: Duration::from_millis(0),
+            cycles: 0,
+            bitmap_size: 0,
+            queue_cycles: 0,
+            n_fuzz: vec![0; N_FUZZ_SIZE],
+        }
+    }
+
+    /// The average execution time of the calibrated testcases
+    #[must_use]
+    pub fn avg_exec_time(&self) -> Duration {
+        if self.cycles == 0 {
+            Duration::from_millis(0)
+        } else {
+            self.exec_time / self.cycles as u32
+        }
+    }
+
+    /// The average bitmap size of the calibrated testcases
+    #[must_use]
+    pub fn avg_bitmap_size(&self) -> u64 {
+        self.bitmap_size.checked_div(self.cycles).unwrap_or(0)
+    }
+
+    /// Records a newly calibrated testcase
+    fn calibrated(&mut self, meta: &PowerTestcaseMetadata) {
+        self.exec_time += meta.exec_time;
+        self.bitmap_size += meta.bitmap_size;
+        self.cycles += 1;
+    }
+}
+
+impl Default for PowerScheduleMetadata {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The statistics of a testcase used by the power schedules
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PowerTestcaseMetadata {
+    /// The number of times the testcase has been fuzzed
+    pub fuzz_level: u64,
+    /// The number of queue cycles missed by the testcase, compensated by extra energy
+    pub handicap: u64,
+    /// The number of ancestors of the testcase
+    pub depth: u64,
+    /// The number of map entries the testcase hits
+    pub bitmap_size: u64,
+    /// The execution time of the testcase
+    pub exec_time: Duration,
+    /// The index of the path of the testcase in [`PowerScheduleMetadata::n_fuzz`]
+    pub n_fuzz_entry: usize,
+}
+
+crate::impl_serdeany!(PowerTestcaseMetadata);
+
+/// The properties of a testcase and of the corpus, besides its [`struct@PowerTestcaseMetadata`], needed to score it
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PowerScoreContext {
+    /// If the testcase is favored by the minimizer
+    pub favored: bool,
+    /// The number of map entries the testcase is the top rated of
+    pub tc_ref: usize,
+    /// The average path frequency of the corpus
+    pub fuzz_mu: f64,
+    /// The total number of executions
+    pub executions: usize,
+}
+
+impl PowerSchedule {
+    /// Computes the AFL++ performance score of a testcase, `100` being the score of an average testcase.
+    /// Consumes part of the handicap of the testcase.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn perf_score(
+        self,
+        meta: &mut PowerTestcaseMetadata,
+        psmeta: &PowerScheduleMetadata,
+        ctx: &PowerScoreContext,
+    ) -> f64 {
+        let mut perf_score = 100.0;
+
+        // Fast testcases get more energy
+        let exec_us = meta.exec_time.as_micros() as f64;
+        let avg_exec_us = psmeta.avg_exec_time().as_micros() as f64;
+        if exec_us * 0.1 > avg_exec_us {
+            perf_score = 10.0;
+        } else if exec_us * 0.25 > avg_exec_us {
+            perf_score = 25.0;
+        } else if exec_us * 0.5 > avg_exec_us {
+            perf_score = 50.0;
+        } else if exec_us * 0.75 > avg_exec_us {
+            perf_score = 75.0;
+        } else if exec_us * 4.0 < avg_exec_us {
+            perf_score = 300.0;
+        } else if exec_us * 3.0 < avg_exec_us {
+            perf_score = 200.0;
+        } else if exec_us * 2.0 < avg_exec_us {
+            perf_score = 150.0;
+        }
+
+        // Testcases with a large coverage get more energy
+        let bitmap_size = meta.bitmap_size as f64;
+        let avg_bitmap_size = psmeta.avg_bitmap_size() as f64;
+        if bitmap_size * 0.3 > avg_bitmap_size {
+            perf_score *= 3.0;
+        } else if bitmap_size * 0.5 > avg_bitmap_size {
+            perf_score *= 2.0;
+        } else if bitmap_size * 0.75 > avg_bitmap_size {
+            perf_score *= 1.5;
+        } else if bitmap_size * 3.0 < avg_bitmap_size {
+            perf_score *= 0.25;
+        } else if bitmap_size * 2.0 < avg_bitmap_size {
+            perf_score *= 0.5;
+        } else if bitmap_size * 1.5 < avg_bitmap_size {
+            perf_score *= 0.75;
+        }
+
+        // Late testcases catch up on the cycles they missed
+        if meta.handicap >= 4 {
+            perf_score *= 4.0;
+            meta.handicap -= 4;
+        } else if meta.handicap > 0 {
+            perf_score *= 2.0;
+            meta.handicap -= 1;
+        }
+
+        // Deep testcases are likely harder to reach by mutation
+        perf_score *= match meta.depth {
+            0..=3 => 1.0,
+            4..=7 => 2.0,
+            8..=13 => 3.0,
+            14..=25 => 4.0,
+            _ => 5.0,
+        };
+
+        let n_fuzz = f64::from(psmeta.n_fuzz[meta.n_fuzz_entry]);
+        // Integer log2, available without std
+        let n_fuzz_log2 = 31 - psmeta.n_fuzz[meta.n_fuzz_entry].max(1).leading_zeros();
+        let mut factor = 1.0;
+        match self {
+            PowerSchedule::Explore => {}
+            PowerSchedule::Fast => {
+                // Unfuzzed testcases are left alone
+                if meta.fuzz_level > 0 {
+                    factor = match n_fuzz_log2 {
+                        0..=1 => 4.0,
+                        2..=3 => 3.0,
+                        4 => 2.0,
+                        5 => 1.0,
+                        6 if !ctx.favored => 0.8,
+                        7 if !ctx.favored => 0.6,
+                        _ if !ctx.favored => 0.4,
+                        _ => 1.0,
+                    };
+                    if ctx.favored {
+                        factor *= 1.15;
+                    }
+                }
+            }
+            PowerSchedule::Coe => {
+                factor = if n_fuzz > ctx.fuzz_mu {
+                    0.0
+                } else if meta.fuzz_level < 16 {
+                    (1_u64 << meta.fuzz_level) as f64
+                } else {
+                    MAX_FACTOR
+                };
+            }
+            PowerSchedule::Lin => {
+                factor = meta.fuzz_level as f64 / (n_fuzz + 1.0);
+            }
+            PowerSchedule::Quad => {
+                factor = (meta.fuzz_level * meta.fuzz_level) as f64 / (n_fuzz + 1.0);
+            }
+            PowerSchedule::Rare => {
+                perf_score += ctx.tc_ref as f64 * 10.0;
+                if ctx.executions > 0 {
+                    perf_score *= 1.0 - (n_fuzz / ctx.executions as f64).min(1.0);
+                }
+            }
+        }
+
+        perf_score *= factor.min(MAX_FACTOR) / POWER_BETA;
+        perf_score.min(HAVOC_MAX_MULT * 100.0)
+    }
+
+    /// The number of mutations given to a testcase with the given performance score,
+    /// fewer if the target is slow, and none if the score is zero
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn iterations(perf_score: f64, avg_exec_time: Duration) -> u64 {
+        if perf_score <= 0.0 {
+            return 0;
+        }
+        let havoc_div = match avg_exec_time.as_micros() {
+            0..=10_000 => 1,
+            10_001..=20_000 => 2,
+            20_001..=50_000 => 5,
+            _ => 10,
+        };
+        ((HAVOC_CYCLES as f64 * perf_score / 100.0) as u64 / havoc_div).max(HAVOC_MIN)
+    }
+}
+
+/// A mutational stage giving each testcase an energy, i.e. a number of mutations, following a [`PowerSchedule`].
+/// The testcases are calibrated the first time they are fuzzed, and the path frequencies are counted
+/// by hashing the coverage of the map observer after each execution.
 #[derive(Clone, Debug)]
-pub struct PowerMutationalStage<C, CS, E, EM, I, M, OT, R, S>
+pub struct PowerMutationalStage<C, E, EM, I, M, O, OT, S, T, Z>
 where
-    M: Mutator<I, S>,
-    I: Input,
-    S: HasCorpus<C, I> + Evaluator<I> + HasRand<R>,
     C: Corpus<I>,
-    EM: EventManager<I, S>,
-    E: Executor<I> + HasObservers<OT>,
-    OT: ObserversTuple,
-    CS: CorpusScheduler<I, S>,
-    R: Rand,
+    E: Executor<EM, I, S, Z> + HasObservers<OT> + HasObserversHooks<EM, I, OT, S, Z>,
+    I: Input,
+    M: Mutator<I, S>,
+    O: MapObserver<T>,
+    OT: ObserversTuple + HasExecHooksTuple<EM, I, S, Z>,
+    S: HasClientPerfStats + HasCorpus<C, I> + HasExecutions + HasMetadata,
+    T: Default + Copy + PartialEq,
+    Z: Evaluator<E, EM, I, S>,
 {
     mutator: M,
-    phantom: PhantomData<(C, CS, E, EM, I, OT, R, S)>,
+    schedule: PowerSchedule,
+    map_observer_name: String,
+    #[allow(clippy::type_complexity)]
+    phantom: PhantomData<(C, E, EM, I, O, OT, S, T, Z)>,
 }
 
-impl<C, CS, E, EM, I, M, OT, R, S> MutationalStage<C, CS, E, EM, I, M, OT, S>
-    for PowerMutationalStage<C, CS, E, EM, I, M, OT, R, S>
+impl<C, E, EM, I, M, O, OT, S, T, Z> MutationalStage<C, E, EM, I, M, S, Z>
+    for PowerMutationalStage<C, E, EM, I, M, O, OT, S, T, Z>
 where
-    M: Mutator<I, S>,
-    I: Input,
-    S: HasCorpus<C, I> + Evaluator<I> + HasRand<R>,
     C: Corpus<I>,
-    EM: EventManager<I, S>,
-    E: Executor<I> + HasObservers<OT>,
-    OT: ObserversTuple,
-    CS: CorpusScheduler<I, S>,
-    R: Rand,
+    E: Executor<EM, I, S, Z> + HasObservers<OT> + HasObserversHooks<EM, I, OT, S, Z>,
+    I: Input,
+    M: Mutator<I, S>,
+    O: MapObserver<T>,
+    OT: ObserversTuple + HasExecHooksTuple<EM, I, S, Z>,
+    S: HasClientPerfStats + HasCorpus<C, I> + HasExecutions + HasMetadata,
+    T: Default + Copy + PartialEq,
+    Z: Evaluator<E, EM, I, S>,
 {
     /// The mutator, added to this stage
     #[inline]
@@ -56,55 +323,315 @@ where
         &mut self.mutator
     }
 
-    /// Gets the number of iterations as a random number
-    fn iterations(&self, state: &mut S) -> usize {
-        1 + state.rand_mut().below(DEFAULT_MUTATIONAL_MAX_ITERATIONS) as usize
+    /// The energy of the testcase, following the power schedule
+    #[allow(clippy::cast_possible_truncation)]
+    fn iterations(&self, state: &mut S, corpus_idx: CorpusId) -> Result<usize, Error> {
+        Ok(self.energy(state, corpus_idx)? as usize)
+    }
+
+    /// Counts the path of the mutant, and calibrates it if it was added to the corpus
+    fn post_evaluation(
+        &mut self,
+        executor: &mut E,
+        state: &mut S,
+        corpus_idx: CorpusId,
+        new_idx: Option<CorpusId>,
+        exec_time: Duration,
+    ) -> Result<(), Error> {
+        let (n_fuzz_entry, bitmap_size) = self.observe_path(executor)?;
+        let psmeta = Self::psmeta_mut(state);
+        psmeta.n_fuzz[n_fuzz_entry] = psmeta.n_fuzz[n_fuzz_entry].saturating_add(1);
+        if let Some(new_idx) = new_idx {
+            let handicap = psmeta.queue_cycles.saturating_sub(1);
+            let depth = state
+                .corpus()
+                .get(corpus_idx)?
+                .borrow()
+                .metadata()
+                .get::<PowerTestcaseMetadata>()
+                .map_or(0, |meta| meta.depth + 1);
+            let mut testcase = state.corpus().get(new_idx)?.borrow_mut();
+            let meta = PowerTestcaseMetadata {
+                fuzz_level: 0,
+                handicap,
+                depth,
+                bitmap_size,
+                exec_time: testcase.exec_time().unwrap_or(exec_time),
+                n_fuzz_entry,
+            };
+            testcase.add_metadata(meta.clone());
+            drop(testcase);
+            Self::psmeta_mut(state).calibrated(&meta);
+        }
+        Ok(())
     }
 }
 
-impl<C, CS, E, EM, I, M, OT, R, S> Stage<CS, E, EM, I, S>
-    for PowerMutationalStage<C, CS, E, EM, I, M, OT, R, S>
+impl<C, E, EM, I, M, O, OT, S, T, Z> Stage<E, EM, S, Z>
+    for PowerMutationalStage<C, E, EM, I, M, O, OT, S, T, Z>
 where
-    M: Mutator<I, S>,
-    I: Input,
-    S: HasCorpus<C, I> + Evaluator<I> + HasRand<R>,
     C: Corpus<I>,
-    EM: EventManager<I, S>,
-    E: Executor<I> + HasObservers<OT>,
-    OT: ObserversTuple,
-    CS: CorpusScheduler<I, S>,
-    R: Rand,
+    E: Executor<EM, I, S, Z> + HasObservers<OT> + HasObserversHooks<EM, I, OT, S, Z>,
+    I: Input,
+    M: Mutator<I, S>,
+    O: MapObserver<T>,
+    OT: ObserversTuple + HasExecHooksTuple<EM, I, S, Z>,
+    S: HasClientPerfStats + HasCorpus<C, I> + HasExecutions + HasMetadata,
+    T: Default + Copy + PartialEq,
+    Z: Evaluator<E, EM, I, S>,
 {
-    #[inline]
     fn perform(
-        &self,
-        state: &mut S,
+        &mut self,
+        fuzzer: &mut Z,
         executor: &mut E,
+        state: &mut S,
         manager: &mut EM,
-        scheduler: &CS,
-        corpus_idx: usize,
+        corpus_idx: CorpusId,
     ) -> Result<(), Error> {
-        self.perform_mutational(state, executor, manager, scheduler, corpus_idx)
+        if state.metadata().get::<PowerScheduleMetadata>().is_none() {
+            state.add_metadata(PowerScheduleMetadata::new());
+        }
+        self.calibrate(fuzzer, executor, state, manager, corpus_idx)?;
+        if state.corpus().first() == Some(corpus_idx) {
+            Self::psmeta_mut(state).queue_cycles += 1;
+        }
+
+        let ret = self.perform_mutational(fuzzer, executor, state, manager, corpus_idx);
+
+        // Only a testcase fuzzed to the end counts as fuzzed once more
+        if ret.is_ok() {
+            let mut testcase = state.corpus().get(corpus_idx)?.borrow_mut();
+            if let Some(meta) = testcase.metadata_mut().get_mut::<PowerTestcaseMetadata>() {
+                meta.fuzz_level += 1;
+            }
+        }
+
+        #[cfg(feature = "introspection")]
+        state.introspection_stats_mut().finish_stage();
+
+        ret
     }
 }
 
-impl<C, CS, E, EM, I, M, OT, R, S> PowerMutationalStage<C, CS, E, EM, I, M, OT, R, S>
+impl<C, E, EM, I, M, O, OT, S, T, Z> PowerMutationalStage<C, E, EM, I, M, O, OT, S, T, Z>
 where
-    M: Mutator<I, S>,
-    I: Input,
-    S: HasCorpus<C, I> + Evaluator<I> + HasRand<R>,
     C: Corpus<I>,
-    EM: EventManager<I, S>,
-    E: Executor<I> + HasObservers<OT>,
-    OT: ObserversTuple,
-    CS: CorpusScheduler<I, S>,
-    R: Rand,
+    E: Executor<EM, I, S, Z> + HasObservers<OT> + HasObserversHooks<EM, I, OT, S, Z>,
+    I: Input,
+    M: Mutator<I, S>,
+    O: MapObserver<T>,
+    OT: ObserversTuple + HasExecHooksTuple<EM, I, S, Z>,
+    S: HasClientPerfStats + HasCorpus<C, I> + HasExecutions + HasMetadata,
+    T: Default + Copy + PartialEq,
+    Z: Evaluator<E, EM, I, S>,
 {
-    /// Creates a new default mutational stage
-    pub fn new(mutator: M) -> Self {
+    /// Creates a new [`PowerMutationalStage`], following the given `schedule`,
+    /// and counting the path frequencies with the map observer named `map_observer_name`
+    pub fn new(mutator: M, schedule: PowerSchedule, map_observer_name: &str) -> Self {
         Self {
-            mutator: mutator,
+            mutator,
+            schedule,
+            map_observer_name: map_observer_name.into(),
             phantom: PhantomData,
         }
     }
+
+    /// The power schedule of this stage
+    pub fn schedule(&self) -> PowerSchedule {
+        self.schedule
+    }
+
+    fn psmeta_mut(state: &mut S) -> &mut PowerScheduleMetadata {
+        state
+            .metadata_mut()
+            .get_mut::<PowerScheduleMetadata>()
+            .unwrap()
+    }
+
+    /// The path frequency counter and the bitmap size of the last execution
+    fn observe_path(&self, executor: &E) -> Result<(usize, u64), Error> {
+        let observer = executor
+            .observers()
+            .match_name::<O>(&self.map_observer_name)
+            .ok_or_else(|| {
+                Error::KeyNotFound(format!("Map observer {} not found", self.map_observer_name))
+            })?;
+        let initial = observer.initial();
+        let bitmap_size = observer.map()[0..observer.usable_count()]
+            .iter()
+            .filter(|x| **x != initial)
+            .count();
+        Ok((
+            (observer.coverage_hash() % N_FUZZ_SIZE as u64) as usize,
+            bitmap_size as u64,
+        ))
+    }
+
+    /// Runs the testcase once to record its statistics, if it was not added by this stage.
+    /// The target is run through the executor and its observers only, as the [`crate::stages::TracingStage`] does,
+    /// so that the run fires no feedback nor event.
+    fn calibrate(
+        &self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+        corpus_idx: CorpusId,
+    ) -> Result<(), Error> {
+        let input = {
+            let mut testcase = state.corpus().get(corpus_idx)?.borrow_mut();
+            if testcase.has_metadata::<PowerTestcaseMetadata>() {
+                return Ok(());
+            }
+            testcase.load_input()?.clone()
+        };
+
+        executor.pre_exec_observers(fuzzer, state, manager, &input)?;
+        let start = current_time();
+        drop(executor.run_target(fuzzer, state, manager, &input)?);
+        let exec_time = current_time() - start;
+        *state.executions_mut() += 1;
+        executor.post_exec_observers(fuzzer, state, manager, &input)?;
+        let (n_fuzz_entry, bitmap_size) = self.observe_path(executor)?;
+
+        let mut testcase = state.corpus().get(corpus_idx)?.borrow_mut();
+        let meta = PowerTestcaseMetadata {
+            bitmap_size,
+            exec_time: testcase.exec_time().unwrap_or(exec_time),
+            n_fuzz_entry,
+            ..PowerTestcaseMetadata::default()
+        };
+        testcase.add_metadata(meta.clone());
+        drop(testcase);
+
+        let psmeta = Self::psmeta_mut(state);
+        psmeta.n_fuzz[n_fuzz_entry] = psmeta.n_fuzz[n_fuzz_entry].saturating_add(1);
+        psmeta.calibrated(&meta);
+        Ok(())
+    }
+
+    /// The number of mutations of the testcase, following the power schedule
+    #[allow(clippy::cast_precision_loss)]
+    fn energy(&self, state: &mut S, corpus_idx: CorpusId) -> Result<u64, Error> {
+        let psmeta = state.metadata().get::<PowerScheduleMetadata>().unwrap();
+
+        // The average path frequency is only needed by COE, walking the corpus is not for free
+        let mut fuzz_mu = 0.0;
+        if self.schedule == PowerSchedule::Coe {
+            let (mut sum, mut count) = (0_u64, 0_u64);
+            let mut current = state.corpus().first();
+            while let Some(idx) = current {
+                if let Some(meta) = state
+                    .corpus()
+                    .get(idx)?
+                    .borrow()
+                    .metadata()
+                    .get::<PowerTestcaseMetadata>()
+                {
+                    sum += u64::from(psmeta.n_fuzz[meta.n_fuzz_entry]);
+                    count += 1;
+                }
+                current = state.corpus().next(idx);
+            }
+            if count > 0 {
+                fuzz_mu = sum as f64 / count as f64;
+            }
+        }
+        let tc_ref = state
+            .metadata()
+            .get::<TopRatedsMetadata>()
+            .map_or(0, |top_rated| {
+                top_rated
+                    .map
+                    .values()
+                    .filter(|idx| **idx == corpus_idx)
+                    .count()
+            });
+
+        let mut testcase = state.corpus().get(corpus_idx)?.borrow_mut();
+        let ctx = PowerScoreContext {
+            favored: testcase.has_metadata::<IsFavoredMetadata>(),
+            tc_ref,
+            fuzz_mu,
+            executions: *state.executions(),
+        };
+        let meta = testcase
+            .metadata_mut()
+            .get_mut::<PowerTestcaseMetadata>()
+            .unwrap();
+        let perf_score = self.schedule.perf_score(meta, psmeta, &ctx);
+        Ok(PowerSchedule::iterations(
+            perf_score,
+            psmeta.avg_exec_time(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use crate::stages::power::{
+        PowerSchedule, PowerScheduleMetadata, PowerScoreContext, PowerTestcaseMetadata,
+        HAVOC_CYCLES, HAVOC_MIN,
+    };
+
+    #[test]
+    fn test_power_schedules() {
+        let mut psmeta = PowerScheduleMetadata::new();
+        let average = PowerTestcaseMetadata {
+            fuzz_level: 1,
+            bitmap_size: 100,
+            exec_time: Duration::from_micros(100),
+            n_fuzz_entry: 1,
+            ..PowerTestcaseMetadata::default()
+        };
+        psmeta.calibrated(&average);
+        psmeta.n_fuzz[0] = 1000;
+        psmeta.n_fuzz[1] = 1;
+        let ctx = PowerScoreContext {
+            fuzz_mu: 10.0,
+            executions: 1001,
+            ..PowerScoreContext::default()
+        };
+        let frequent = PowerTestcaseMetadata {
+            n_fuzz_entry: 0,
+            ..average.clone()
+        };
+
+        // An average testcase gets the average energy
+        let score = PowerSchedule::Explore.perf_score(&mut average.clone(), &psmeta, &ctx);
+        assert!((score - 100.0).abs() < f64::EPSILON);
+        assert_eq!(
+            PowerSchedule::iterations(score, psmeta.avg_exec_time()),
+            HAVOC_CYCLES
+        );
+
+        // Rare paths get more energy than frequent ones
+        for schedule in &[
+            PowerSchedule::Fast,
+            PowerSchedule::Coe,
+            PowerSchedule::Lin,
+            PowerSchedule::Quad,
+            PowerSchedule::Rare,
+        ] {
+            let rare = schedule.perf_score(&mut average.clone(), &psmeta, &ctx);
+            let frequent = schedule.perf_score(&mut frequent.clone(), &psmeta, &ctx);
+            assert!(rare > frequent, "{:?}", schedule);
+        }
+
+        // COE skips the testcases on paths more frequent than the average
+        let score = PowerSchedule::Coe.perf_score(&mut frequent.clone(), &psmeta, &ctx);
+        assert_eq!(PowerSchedule::iterations(score, psmeta.avg_exec_time()), 0);
+
+        // Handicapped testcases get more energy, consuming their handicap
+        let mut late = PowerTestcaseMetadata {
+            handicap: 5,
+            ..average
+        };
+        let score = PowerSchedule::Explore.perf_score(&mut late, &psmeta, &ctx);
+        assert!((score - 400.0).abs() < f64::EPSILON);
+        assert_eq!(late.handicap, 1);
+        assert!(PowerSchedule::iterations(1.0, psmeta.avg_exec_time()) >= HAVOC_MIN);
+    }
 }
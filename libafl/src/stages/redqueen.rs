@@ -0,0 +1,540 @@
+//! The `RedQueen` input-to-state stage, see the [RedQueen paper](https://www.ndss-symposium.org/ndss-paper/redqueen-fuzzing-with-input-to-state-correspondence/).
+//! The input is colorized, to find the bytes that don't affect coverage, then traced to log the operands of comparisons.
+//! Operands that can be found in the input, in one of their encodings, are replaced with the other operand,
+//! trying all the candidates deterministically.
+
+use alloc::{string::String, vec::Vec};
+use core::{marker::PhantomData, mem::drop};
+use hashbrown::HashSet;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bolts::rands::Rand,
+    corpus::{Corpus, CorpusId},
+    executors::{Executor, HasExecHooksTuple, HasObservers, HasObserversHooks},
+    fuzzer::Evaluator,
+    inputs::{HasBytesVec, Input},
+    observers::{CmpMap, CmpObserver, CmpValues, MapObserver, ObserversTuple},
+    stages::Stage,
+    state::{HasClientPerfStats, HasCorpus, HasExecutions, HasMetadata, HasRand},
+    Error,
+};
+
+/// The maximum number of executions spent colorizing an input
+pub const REDQUEEN_MAX_COLORIZATION_EXECS: usize = 1024;
+
+/// The maximum number of candidate replacements tried for each input
+pub const REDQUEEN_MAX_CANDIDATES: usize = 4096;
+
+/// How a comparison operand was found in the input
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum I2SEncoding {
+    /// An integer of `size` bytes, possibly narrower than the comparison, and zero or sign extended to its width.
+    /// The replacement is the other operand, plus `delta`, to also solve `<` and `>` comparisons.
+    Integer {
+        /// The number of bytes in the input
+        size: u8,
+        /// If the bytes are in big endian order
+        big_endian: bool,
+        /// The value added to the replacement
+        delta: i8,
+    },
+    /// The raw bytes of a memory comparison
+    Bytes,
+}
+
+/// The mask for integers of `width` bytes
+fn width_mask(width: usize) -> u64 {
+    if width >= 8 {
+        u64::MAX
+    } else {
+        (1 << (8 * width)) - 1
+    }
+}
+
+/// Returns `true` if `value`, of `width` bytes, is the zero or sign extension of its lowest `size` bytes
+fn is_extension_of(value: u64, size: usize, width: usize) -> bool {
+    if size >= width {
+        return true;
+    }
+    let truncated = value & width_mask(size);
+    let shift = 64 - 8 * size;
+    let sign_extended = (((truncated << shift) as i64) >> shift) as u64;
+    value == truncated || value == sign_extended & width_mask(width)
+}
+
+impl I2SEncoding {
+    /// All the encodings of `pattern` in the input, for a comparison of `width` bytes with `replacement`
+    fn integers(pattern: u64, replacement: u64, width: usize) -> Vec<Self> {
+        let mut encodings = vec![];
+        for size in &[1, 2, 4, 8] {
+            if *size > width || !is_extension_of(pattern, *size, width) {
+                continue;
+            }
+            for delta in &[0_i8, 1, -1] {
+                let replacement = replacement.wrapping_add(*delta as u64) & width_mask(width);
+                if !is_extension_of(replacement, *size, width) {
+                    continue;
+                }
+                for big_endian in &[false, true] {
+                    if *size == 1 && *big_endian {
+                        continue;
+                    }
+                    encodings.push(Self::Integer {
+                        size: *size as u8,
+                        big_endian: *big_endian,
+                        delta: *delta,
+                    });
+                }
+            }
+        }
+        encodings
+    }
+
+    /// The bytes of an integer operand, in this encoding
+    fn encode(&self, value: u64) -> Vec<u8> {
+        match self {
+            Self::Integer {
+                size, big_endian, ..
+            } => {
+                let mut bytes = value.to_le_bytes()[..*size as usize].to_vec();
+                if *big_endian {
+                    bytes.reverse();
+                }
+                bytes
+            }
+            Self::Bytes => value.to_le_bytes().to_vec(),
+        }
+    }
+
+    /// The bytes of the replacement for an integer operand, in this encoding
+    fn encode_replacement(&self, value: u64, width: usize) -> Vec<u8> {
+        match self {
+            Self::Integer { delta, .. } => {
+                self.encode(value.wrapping_add(*delta as u64) & width_mask(width))
+            }
+            Self::Bytes => self.encode(value),
+        }
+    }
+}
+
+/// A candidate replacement of input bytes, with the other operand of a comparison
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct I2SCandidate {
+    /// The offset of the replaced bytes
+    pub offset: usize,
+    /// How the operand is encoded in the input
+    pub encoding: I2SEncoding,
+    /// The new bytes
+    pub replacement: Vec<u8>,
+}
+
+impl I2SCandidate {
+    /// Applies the replacement to the given bytes
+    pub fn apply(&self, bytes: &mut [u8]) {
+        let end = (self.offset + self.replacement.len()).min(bytes.len());
+        bytes[self.offset..end].copy_from_slice(&self.replacement[..end - self.offset]);
+    }
+}
+
+/// The width, in bytes, of a numeric comparison
+fn cmp_width(values: &CmpValues) -> usize {
+    match values {
+        CmpValues::U8(_) => 1,
+        CmpValues::U16(_) => 2,
+        CmpValues::U32(_) => 4,
+        CmpValues::U64(_) => 8,
+        CmpValues::Bytes((v0, _)) => v0.len(),
+    }
+}
+
+/// Collects the candidate replacements, with their dedup
+struct Candidates<'a> {
+    orig: &'a [u8],
+    colorized: &'a [u8],
+    seen: HashSet<(usize, Vec<u8>)>,
+    list: Vec<I2SCandidate>,
+}
+
+impl<'a> Candidates<'a> {
+    /// Adds a candidate for each offset where `pattern` is in the original input,
+    /// and `colorized_pattern` (if logged) is at the same offset of the colorized input
+    fn add(
+        &mut self,
+        pattern: &[u8],
+        colorized_pattern: Option<&[u8]>,
+        encoding: I2SEncoding,
+        replacement: &[u8],
+    ) {
+        if pattern.is_empty() || pattern.len() > self.orig.len() || pattern == replacement {
+            return;
+        }
+        for offset in 0..=self.orig.len() - pattern.len() {
+            let end = offset + pattern.len();
+            if self.orig[offset..end] != *pattern {
+                continue;
+            }
+            if let Some(colorized_pattern) = colorized_pattern {
+                if colorized_pattern.len() != pattern.len()
+                    || self.colorized[offset..end] != *colorized_pattern
+                {
+                    continue;
+                }
+            }
+            if self.seen.insert((offset, replacement.to_vec())) {
+                self.list.push(I2SCandidate {
+                    offset,
+                    encoding,
+                    replacement: replacement.to_vec(),
+                });
+            }
+        }
+    }
+}
+
+/// Computes all the candidate replacements for the comparisons logged running the original and the colorized input.
+/// The logs hold the values of each execution of each comparison.
+#[must_use]
+pub fn i2s_candidates(
+    orig: &[u8],
+    colorized: &[u8],
+    orig_cmps: &[Vec<CmpValues>],
+    colorized_cmps: &[Vec<CmpValues>],
+) -> Vec<I2SCandidate> {
+    let mut candidates = Candidates {
+        orig,
+        colorized,
+        seen: HashSet::new(),
+        list: vec![],
+    };
+    for (i, executions) in orig_cmps.iter().enumerate() {
+        for (j, values) in executions.iter().enumerate() {
+            let colorized_values = colorized_cmps
+                .get(i)
+                .and_then(|c| c.get(j))
+                .filter(|c| cmp_width(c) == cmp_width(values));
+            let width = cmp_width(values);
+
+            if let CmpValues::Bytes((v0, v1)) = values {
+                let (c0, c1) = match colorized_values {
+                    Some(CmpValues::Bytes((c0, c1))) => (Some(&c0[..]), Some(&c1[..])),
+                    _ => (None, None),
+                };
+                candidates.add(v0, c0, I2SEncoding::Bytes, v1);
+                candidates.add(v1, c1, I2SEncoding::Bytes, v0);
+                continue;
+            }
+
+            let (v0, v1) = values.to_u64_tuple().unwrap();
+            let colorized_values = colorized_values.and_then(CmpValues::to_u64_tuple);
+            for (pattern, replacement, colorized_pattern) in &[
+                (v0, v1, colorized_values.map(|c| c.0)),
+                (v1, v0, colorized_values.map(|c| c.1)),
+            ] {
+                for encoding in I2SEncoding::integers(*pattern, *replacement, width) {
+                    candidates.add(
+                        &encoding.encode(*pattern),
+                        colorized_pattern.map(|c| encoding.encode(c)).as_deref(),
+                        encoding,
+                        &encoding.encode_replacement(*replacement, width),
+                    );
+                }
+            }
+        }
+    }
+    candidates.list
+}
+
+/// The outcome of the [`RedQueenStage`] for a testcase
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RedQueenMetadata {
+    /// The number of colorized bytes
+    pub colorized: usize,
+    /// The number of candidate replacements found
+    pub candidates: usize,
+    /// The replacements that changed the coverage, flipping branches
+    pub flips: Vec<I2SCandidate>,
+}
+
+crate::impl_serdeany!(RedQueenMetadata);
+
+/// The `RedQueen` stage. It colorizes each testcase using the coverage of the `map_observer_name` observer,
+/// traces it with the `tracer_executor`, reading the comparisons from the `cmp_observer_name` observer,
+/// and runs all the input-to-state replacements.
+/// It works with any [`CmpObserver`], e.g. the `CmpLogObserver` of `libafl_targets`,
+/// filled by the sancov cmplog or by the `CmpLogRuntime` of `libafl_frida`.
+/// Each testcase is processed once, and gets a [`RedQueenMetadata`].
+#[derive(Clone, Debug)]
+pub struct RedQueenStage<C, CM, CO, E, EM, I, O, OT, R, S, T, TE, TOT, Z>
+where
+    C: Corpus<I>,
+    CM: CmpMap,
+    CO: CmpObserver<CM>,
+    E: HasObservers<OT>,
+    I: Input + HasBytesVec,
+    O: MapObserver<T>,
+    OT: ObserversTuple,
+    R: Rand,
+    S: HasClientPerfStats + HasExecutions + HasCorpus<C, I> + HasRand<R>,
+    T: Default + Copy + PartialEq,
+    TE: Executor<EM, I, S, Z> + HasObservers<TOT> + HasObserversHooks<EM, I, TOT, S, Z>,
+    TOT: ObserversTuple + HasExecHooksTuple<EM, I, S, Z>,
+    Z: Evaluator<E, EM, I, S>,
+{
+    tracer_executor: TE,
+    map_observer_name: String,
+    cmp_observer_name: String,
+    #[allow(clippy::type_complexity)]
+    phantom: PhantomData<(C, CM, CO, E, EM, I, O, OT, R, S, T, TOT, Z)>,
+}
+
+impl<C, CM, CO, E, EM, I, O, OT, R, S, T, TE, TOT, Z> Stage<E, EM, S, Z>
+    for RedQueenStage<C, CM, CO, E, EM, I, O, OT, R, S, T, TE, TOT, Z>
+where
+    C: Corpus<I>,
+    CM: CmpMap,
+    CO: CmpObserver<CM>,
+    E: HasObservers<OT>,
+    I: Input + HasBytesVec,
+    O: MapObserver<T>,
+    OT: ObserversTuple,
+    R: Rand,
+    S: HasClientPerfStats + HasExecutions + HasCorpus<C, I> + HasRand<R>,
+    T: Default + Copy + PartialEq,
+    TE: Executor<EM, I, S, Z> + HasObservers<TOT> + HasObserversHooks<EM, I, TOT, S, Z>,
+    TOT: ObserversTuple + HasExecHooksTuple<EM, I, S, Z>,
+    Z: Evaluator<E, EM, I, S>,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+        corpus_idx: CorpusId,
+    ) -> Result<(), Error> {
+        let input = {
+            let mut testcase = state.corpus().get(corpus_idx)?.borrow_mut();
+            if testcase.metadata().get::<RedQueenMetadata>().is_some() {
+                return Ok(());
+            }
+            testcase.load_input()?.clone()
+        };
+
+        let hash = self.coverage_hash(fuzzer, executor, state, manager, input.clone())?;
+        let colorized = self.colorize(fuzzer, executor, state, manager, &input, hash)?;
+
+        let orig_cmps = self.trace(fuzzer, state, manager, &input)?;
+        let colorized_cmps = self.trace(fuzzer, state, manager, &colorized)?;
+        let candidates = i2s_candidates(
+            input.bytes(),
+            colorized.bytes(),
+            &orig_cmps,
+            &colorized_cmps,
+        );
+
+        let mut meta = RedQueenMetadata {
+            colorized: input
+                .bytes()
+                .iter()
+                .zip(colorized.bytes())
+                .filter(|(a, b)| a != b)
+                .count(),
+            candidates: candidates.len(),
+            flips: vec![],
+        };
+        for candidate in candidates.into_iter().take(REDQUEEN_MAX_CANDIDATES) {
+            let mut mutated = input.clone();
+            candidate.apply(mutated.bytes_mut());
+            if self.coverage_hash(fuzzer, executor, state, manager, mutated)? != hash {
+                meta.flips.push(candidate);
+            }
+        }
+
+        state
+            .corpus()
+            .get(corpus_idx)?
+            .borrow_mut()
+            .add_metadata(meta);
+        Ok(())
+    }
+}
+
+impl<C, CM, CO, E, EM, I, O, OT, R, S, T, TE, TOT, Z>
+    RedQueenStage<C, CM, CO, E, EM, I, O, OT, R, S, T, TE, TOT, Z>
+where
+    C: Corpus<I>,
+    CM: CmpMap,
+    CO: CmpObserver<CM>,
+    E: HasObservers<OT>,
+    I: Input + HasBytesVec,
+    O: MapObserver<T>,
+    OT: ObserversTuple,
+    R: Rand,
+    S: HasClientPerfStats + HasExecutions + HasCorpus<C, I> + HasRand<R>,
+    T: Default + Copy + PartialEq,
+    TE: Executor<EM, I, S, Z> + HasObservers<TOT> + HasObserversHooks<EM, I, TOT, S, Z>,
+    TOT: ObserversTuple + HasExecHooksTuple<EM, I, S, Z>,
+    Z: Evaluator<E, EM, I, S>,
+{
+    /// Creates a new [`RedQueenStage`]
+    pub fn new(tracer_executor: TE, map_observer_name: &str, cmp_observer_name: &str) -> Self {
+        Self {
+            tracer_executor,
+            map_observer_name: map_observer_name.into(),
+            cmp_observer_name: cmp_observer_name.into(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Evaluates the input, and hashes the coverage entries it hit
+    fn coverage_hash(
+        &self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+        input: I,
+    ) -> Result<u64, Error> {
+        fuzzer.evaluate_input(state, executor, manager, input)?;
+        Ok(executor
+            .observers()
+            .match_name::<O>(&self.map_observer_name)
+            .ok_or_else(|| {
+                Error::KeyNotFound(format!("Map observer {} not found", self.map_observer_name))
+            })?
+            .coverage_hash())
+    }
+
+    /// Replaces as many bytes as possible with random ones, keeping the same coverage.
+    /// Ranges changing the coverage are split in halves, until single bytes.
+    fn colorize(
+        &self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+        input: &I,
+        hash: u64,
+    ) -> Result<I, Error> {
+        let mut colorized = input.clone();
+        let len = input.bytes().len();
+        let mut ranges = vec![(0, len)];
+        let mut execs = 0;
+        while let Some((start, end)) = ranges.pop() {
+            if start == end || execs >= REDQUEEN_MAX_COLORIZATION_EXECS.min(2 * len) {
+                continue;
+            }
+            let mut changed = colorized.clone();
+            for byte in &mut changed.bytes_mut()[start..end] {
+                *byte ^= 1 + state.rand_mut().below(255) as u8;
+            }
+            execs += 1;
+            if self.coverage_hash(fuzzer, executor, state, manager, changed.clone())? == hash {
+                colorized = changed;
+            } else if end - start > 1 {
+                let mid = start + (end - start) / 2;
+                ranges.push((mid, end));
+                ranges.push((start, mid));
+            }
+        }
+        Ok(colorized)
+    }
+
+    /// Runs the tracer, and collects the logged comparisons
+    fn trace(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut S,
+        manager: &mut EM,
+        input: &I,
+    ) -> Result<Vec<Vec<CmpValues>>, Error> {
+        self.tracer_executor
+            .pre_exec_observers(fuzzer, state, manager, input)?;
+        drop(
+            self.tracer_executor
+                .run_target(fuzzer, state, manager, input)?,
+        );
+        *state.executions_mut() += 1;
+        self.tracer_executor
+            .post_exec_observers(fuzzer, state, manager, input)?;
+
+        let observer = self
+            .tracer_executor
+            .observers()
+            .match_name::<CO>(&self.cmp_observer_name)
+            .ok_or_else(|| {
+                Error::KeyNotFound(format!("Cmp observer {} not found", self.cmp_observer_name))
+            })?;
+        Ok((0..observer.usable_count())
+            .map(|i| {
+                (0..observer.map().usable_executions_for(i))
+                    .map(|j| observer.map().values_of(i, j))
+                    .collect()
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        observers::CmpValues,
+        stages::redqueen::{i2s_candidates, I2SEncoding},
+    };
+
+    #[test]
+    fn test_i2s_candidates() {
+        // A big endian u32 magic at 2, and a sign extended byte at 8, which was colorized
+        let orig = b"xx\x00\x00\x00\x01yy\xf0z".to_vec();
+        let colorized = b"xx\x00\x00\x00\x01yy\x17z".to_vec();
+        let orig_cmps = vec![
+            vec![CmpValues::U32((1, 0xdead_beef))],
+            vec![CmpValues::U64((
+                0xffff_ffff_ffff_fff0,
+                0xffff_ffff_ffff_ff80,
+            ))],
+            vec![CmpValues::Bytes((b"yy".to_vec(), b"ok".to_vec()))],
+        ];
+        let colorized_cmps = vec![
+            vec![CmpValues::U32((1, 0xdead_beef))],
+            vec![CmpValues::U64((0x17, 0xffff_ffff_ffff_ff80))],
+            vec![CmpValues::Bytes((b"yy".to_vec(), b"ok".to_vec()))],
+        ];
+        let candidates = i2s_candidates(&orig, &colorized, &orig_cmps, &colorized_cmps);
+
+        let mut magic = orig.clone();
+        let candidate = candidates
+            .iter()
+            .find(|c| {
+                c.encoding
+                    == I2SEncoding::Integer {
+                        size: 4,
+                        big_endian: true,
+                        delta: 0,
+                    }
+            })
+            .unwrap();
+        assert_eq!(candidate.offset, 2);
+        candidate.apply(&mut magic);
+        assert_eq!(&magic[2..6], b"\xde\xad\xbe\xef");
+
+        assert!(candidates
+            .iter()
+            .any(|c| c.offset == 8 && c.replacement == [0x80]));
+        assert!(candidates
+            .iter()
+            .any(|c| c.offset == 6 && c.encoding == I2SEncoding::Bytes));
+
+        // Colorization changed the byte, but not the operand: they are unrelated
+        let colorized_cmps = vec![
+            vec![],
+            vec![CmpValues::U64((
+                0xffff_ffff_ffff_fff0,
+                0xffff_ffff_ffff_ff80,
+            ))],
+        ];
+        let candidates = i2s_candidates(&orig, &colorized, &orig_cmps, &colorized_cmps);
+        assert!(candidates.iter().all(|c| c.offset != 8));
+    }
+}
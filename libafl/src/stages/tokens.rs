@@ -0,0 +1,210 @@
+//! The cmp tokens stage promotes the byte operands that comparisons see again and again,
+//! such as the constant operand of a `memcmp`, to the [`Tokens`] dictionary.
+//! It reads the [`CmpValuesMetadata`] that a [`crate::observers::CmpObserver`] left in the state,
+//! so it goes after the [`crate::stages::TracingStage`] running the `CmpLog` executor.
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use hashbrown::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::CorpusId,
+    mutators::Tokens,
+    observers::cmp::{CmpValues, CmpValuesMetadata},
+    stages::Stage,
+    state::HasMetadata,
+    Error,
+};
+
+/// The default number of runs in which an operand must be seen to become a token
+pub const DEFAULT_CMP_TOKENS_MIN_HITS: u32 = 8;
+/// The default maximum number of tokens in the dictionary, after which no tokens are added
+pub const DEFAULT_CMP_TOKENS_MAX_TOKENS: usize = 512;
+/// The maximum number of distinct operands tracked, to bound the memory used by the stage
+const CMP_TOKENS_MAX_CANDIDATES: usize = 65536;
+/// Operands shorter than this are not worth a token, the integer mutations cover them
+const CMP_TOKENS_MIN_LEN: usize = 2;
+
+/// A state metadata counting in how many runs each byte operand was seen
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct CmpTokensMetadata {
+    /// The number of runs in which each operand not yet promoted was seen
+    pub hits: HashMap<Vec<u8>, u32>,
+    /// The number of tokens promoted to the dictionary
+    pub promoted: usize,
+}
+
+crate::impl_serdeany!(CmpTokensMetadata);
+
+/// Strips the terminators logged with string operands
+fn trim_operand(operand: &[u8]) -> &[u8] {
+    let end = operand
+        .iter()
+        .rposition(|b| *b != 0)
+        .map_or(0, |pos| pos + 1);
+    &operand[..end]
+}
+
+/// A stage promoting frequent byte operands of comparisons to [`Tokens`]
+#[derive(Clone, Debug)]
+pub struct CmpTokensStage<E, EM, S, Z>
+where
+    S: HasMetadata,
+{
+    min_hits: u32,
+    max_tokens: usize,
+    #[allow(clippy::type_complexity)]
+    phantom: PhantomData<(E, EM, S, Z)>,
+}
+
+impl<E, EM, S, Z> Stage<E, EM, S, Z> for CmpTokensStage<E, EM, S, Z>
+where
+    S: HasMetadata,
+{
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut S,
+        _manager: &mut EM,
+        _corpus_idx: CorpusId,
+    ) -> Result<(), Error> {
+        // Each operand counts once per run, no matter how many times it is compared
+        let operands: HashSet<Vec<u8>> = match state.metadata().get::<CmpValuesMetadata>() {
+            None => return Ok(()),
+            Some(meta) => meta
+                .list
+                .iter()
+                .filter_map(|cmp| match cmp {
+                    CmpValues::Bytes(v) => Some(v),
+                    _ => None,
+                })
+                .flat_map(|(v0, v1)| vec![trim_operand(v0), trim_operand(v1)])
+                .filter(|v| v.len() >= CMP_TOKENS_MIN_LEN)
+                .map(<[u8]>::to_vec)
+                .collect(),
+        };
+        if operands.is_empty() {
+            return Ok(());
+        }
+
+        if state.metadata().get::<CmpTokensMetadata>().is_none() {
+            state.add_metadata(CmpTokensMetadata::default());
+        }
+        let mut promote = vec![];
+        {
+            let meta = state.metadata_mut().get_mut::<CmpTokensMetadata>().unwrap();
+            for operand in operands {
+                if meta.hits.len() >= CMP_TOKENS_MAX_CANDIDATES && !meta.hits.contains_key(&operand)
+                {
+                    continue;
+                }
+                let hits = meta.hits.entry(operand.clone()).or_insert(0);
+                *hits += 1;
+                if *hits >= self.min_hits {
+                    meta.hits.remove(&operand);
+                    promote.push(operand);
+                }
+            }
+        }
+        if promote.is_empty() {
+            return Ok(());
+        }
+
+        if state.metadata().get::<Tokens>().is_none() {
+            state.add_metadata(Tokens::new(vec![]));
+        }
+        let tokens = state.metadata_mut().get_mut::<Tokens>().unwrap();
+        let mut promoted = 0;
+        for token in &promote {
+            if tokens.tokens().len() >= self.max_tokens {
+                break;
+            }
+            if tokens.add_token(token) {
+                promoted += 1;
+            }
+        }
+        state
+            .metadata_mut()
+            .get_mut::<CmpTokensMetadata>()
+            .unwrap()
+            .promoted += promoted;
+        Ok(())
+    }
+}
+
+impl<E, EM, S, Z> CmpTokensStage<E, EM, S, Z>
+where
+    S: HasMetadata,
+{
+    /// Creates a new [`CmpTokensStage`], promoting operands seen in [`DEFAULT_CMP_TOKENS_MIN_HITS`] runs,
+    /// up to [`DEFAULT_CMP_TOKENS_MAX_TOKENS`] tokens
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_limits(DEFAULT_CMP_TOKENS_MIN_HITS, DEFAULT_CMP_TOKENS_MAX_TOKENS)
+    }
+
+    /// Creates a new [`CmpTokensStage`], promoting operands seen in `min_hits` runs,
+    /// as long as the dictionary holds less than `max_tokens` tokens
+    #[must_use]
+    pub fn with_limits(min_hits: u32, max_tokens: usize) -> Self {
+        Self {
+            min_hits,
+            max_tokens,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E, EM, S, Z> Default for CmpTokensStage<E, EM, S, Z>
+where
+    S: HasMetadata,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bolts::rands::StdRand,
+        corpus::{CorpusId, InMemoryCorpus},
+        inputs::BytesInput,
+        mutators::Tokens,
+        observers::cmp::{CmpValues, CmpValuesMetadata},
+        stages::{tokens::CmpTokensStage, Stage},
+        state::{HasMetadata, StdState},
+    };
+
+    #[test]
+    fn test_cmp_tokens_stage() {
+        let mut state = StdState::new(
+            StdRand::with_seed(1337),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            (),
+        );
+        let mut stage = CmpTokensStage::<(), (), _, ()>::with_limits(2, 1);
+
+        for run in 0..3_u8 {
+            state.add_metadata(CmpValuesMetadata {
+                list: vec![
+                    CmpValues::Bytes((b"MAGIC\0".to_vec(), vec![run, run + 1])),
+                    CmpValues::Bytes((b"MAGIC\0".to_vec(), vec![0; 4])),
+                    CmpValues::Bytes((b"OTHER".to_vec(), vec![1])),
+                    CmpValues::U8((1, 2)),
+                ],
+            });
+            stage
+                .perform(&mut (), &mut (), &mut state, &mut (), CorpusId(0))
+                .unwrap();
+        }
+
+        // The varying operand is never promoted, and the dictionary is capped
+        let tokens = state.metadata().get::<Tokens>().unwrap().tokens();
+        assert_eq!(tokens.len(), 1);
+        assert!(tokens[0] == b"MAGIC" || tokens[0] == b"OTHER");
+    }
+}
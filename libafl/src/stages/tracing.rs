@@ -1,7 +1,7 @@
 use core::{marker::PhantomData, mem::drop};
 
 use crate::{
-    corpus::Corpus,
+    corpus::{Corpus, CorpusId},
     executors::{Executor, HasExecHooksTuple, HasObservers, HasObserversHooks, ShadowExecutor},
     inputs::Input,
     mark_feature_time,
@@ -45,7 +45,7 @@ where
         _executor: &mut E,
         state: &mut S,
         manager: &mut EM,
-        corpus_idx: usize,
+        corpus_idx: CorpusId,
     ) -> Result<(), Error> {
         start_timer!(state);
         let input = state
@@ -120,7 +120,7 @@ where
         executor: &mut ShadowExecutor<E, SOT>,
         state: &mut S,
         manager: &mut EM,
-        corpus_idx: usize,
+        corpus_idx: CorpusId,
     ) -> Result<(), Error> {
         start_timer!(state);
         let input = state
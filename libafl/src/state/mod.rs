@@ -13,7 +13,7 @@ use crate::{
         rands::Rand,
         serdeany::{SerdeAny, SerdeAnyMap},
     },
-    corpus::Corpus,
+    corpus::{Corpus, CorpusScheduler},
     events::{Event, EventManager, LogSeverity},
     feedbacks::FeedbackStatesTuple,
     fuzzer::Evaluator,
@@ -345,6 +345,27 @@ where
     }
 }
 
+/// The outcome of loading initial inputs from disk
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct LoadReport {
+    /// The number of inputs added to the corpus
+    pub added: usize,
+    /// The files that were loaded, but not considered `interesting`
+    pub skipped: Vec<PathBuf>,
+    /// The files (or directories) that could not be loaded, and why
+    pub failed: Vec<(PathBuf, Error)>,
+}
+
+#[cfg(feature = "std")]
+impl LoadReport {
+    /// The total number of files that were looked at
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.added + self.skipped.len() + self.failed.len()
+    }
+}
+
 #[cfg(feature = "std")]
 impl<C, FT, I, R, SC> StdState<C, FT, I, R, SC>
 where
@@ -354,9 +375,10 @@ where
     FT: FeedbackStatesTuple,
     SC: Corpus<I>,
 {
-    /// loads inputs from a directory
+    /// loads inputs from a directory, recursively.
     /// If `forced` is `true`, the value will be loaded,
     /// even if it's not considered to be `interesting`.
+    /// Files that can't be read are recorded in the `report`, errors during the evaluation are returned.
     fn load_from_directory<E, EM, Z>(
         &mut self,
         fuzzer: &mut Z,
@@ -364,35 +386,56 @@ where
         manager: &mut EM,
         in_dir: &Path,
         forced: bool,
+        report: &mut LoadReport,
     ) -> Result<(), Error>
     where
         Z: Evaluator<E, EM, I, Self>,
     {
-        for entry in fs::read_dir(in_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            let attributes = fs::metadata(&path);
-
-            if attributes.is_err() {
-                continue;
+        let entries = match fs::read_dir(in_dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                report.failed.push((in_dir.to_path_buf(), err.into()));
+                return Ok(());
             }
-
-            let attr = attributes?;
+        };
+        for entry in entries {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(err) => {
+                    report.failed.push((in_dir.to_path_buf(), err.into()));
+                    continue;
+                }
+            };
+            let attr = match fs::metadata(&path) {
+                Ok(attr) => attr,
+                Err(err) => {
+                    report.failed.push((path, err.into()));
+                    continue;
+                }
+            };
 
             if attr.is_file() && attr.len() > 0 {
-                println!("Loading file {:?} ...", &path);
-                let input = I::from_file(&path)?;
+                let input = match I::from_file(&path) {
+                    Ok(input) => input,
+                    Err(err) => {
+                        report.failed.push((path, err));
+                        continue;
+                    }
+                };
                 if forced {
                     let _ = fuzzer.add_input(self, executor, manager, input)?;
+                    report.added += 1;
                 } else {
                     let (is_interesting, _) =
                         fuzzer.evaluate_input(self, executor, manager, input)?;
-                    if !is_interesting {
-                        println!("File {:?} was not interesting, skipped.", &path);
+                    if is_interesting {
+                        report.added += 1;
+                    } else {
+                        report.skipped.push(path);
                     }
                 }
             } else if attr.is_dir() {
-                self.load_from_directory(fuzzer, executor, manager, &path, forced)?;
+                self.load_from_directory(fuzzer, executor, manager, &path, forced, report)?;
             }
         }
 
@@ -408,36 +451,53 @@ where
         manager: &mut EM,
         in_dirs: &[PathBuf],
         forced: bool,
-    ) -> Result<(), Error>
+    ) -> Result<LoadReport, Error>
     where
         Z: Evaluator<E, EM, I, Self>,
         EM: EventManager<E, I, Self, Z>,
     {
+        let mut report = LoadReport::default();
         for in_dir in in_dirs {
-            self.load_from_directory(fuzzer, executor, manager, in_dir, forced)?;
+            self.load_from_directory(fuzzer, executor, manager, in_dir, forced, &mut report)?;
+        }
+        for (path, err) in &report.failed {
+            manager.fire(
+                self,
+                Event::Log {
+                    severity_level: LogSeverity::Warn,
+                    message: format!("Failed to load {:?}: {}", path, err),
+                    phantom: PhantomData,
+                },
+            )?;
         }
         manager.fire(
             self,
             Event::Log {
                 severity_level: LogSeverity::Debug,
-                message: format!("Loaded {} initial testcases.", self.corpus().count()), // get corpus count
+                message: format!(
+                    "Loaded {} initial testcases, {} not interesting, {} failed.",
+                    report.added,
+                    report.skipped.len(),
+                    report.failed.len()
+                ),
                 phantom: PhantomData,
             },
         )?;
         manager.process(fuzzer, self, executor)?;
-        Ok(())
+        Ok(report)
     }
 
     /// Loads all intial inputs, even if they are not consiered `intesting`.
     /// This is rarely the right method, use `load_initial_inputs`,
     /// and potentially fix your `Feedback`, instead.
+    /// Files that fail to load are listed in the returned [`LoadReport`].
     pub fn load_initial_inputs_forced<E, EM, Z>(
         &mut self,
         fuzzer: &mut Z,
         executor: &mut E,
         manager: &mut EM,
         in_dirs: &[PathBuf],
-    ) -> Result<(), Error>
+    ) -> Result<LoadReport, Error>
     where
         Z: Evaluator<E, EM, I, Self>,
         EM: EventManager<E, I, Self, Z>,
@@ -445,14 +505,16 @@ where
         self.load_initial_inputs_internal(fuzzer, executor, manager, in_dirs, true)
     }
 
-    /// Loads initial inputs from the passed-in `in_dirs`.
+    /// Loads initial inputs from the passed-in `in_dirs`, recursively.
+    /// Each input is evaluated, and only added to the corpus if it is `interesting`.
+    /// Files that fail to load are listed in the returned [`LoadReport`].
     pub fn load_initial_inputs<E, EM, Z>(
         &mut self,
         fuzzer: &mut Z,
         executor: &mut E,
         manager: &mut EM,
         in_dirs: &[PathBuf],
-    ) -> Result<(), Error>
+    ) -> Result<LoadReport, Error>
     where
         Z: Evaluator<E, EM, I, Self>,
         EM: EventManager<E, I, Self, Z>,
@@ -469,6 +531,23 @@ where
     FT: FeedbackStatesTuple,
     SC: Corpus<I>,
 {
+    /// Replays all the testcases already in the corpus through the `on_add` hook of the `scheduler`, in insertion order.
+    /// Use it to resume a campaign from a corpus loaded from disk, e.g. with [`crate::corpus::OnDiskCorpus::load_from_dir`].
+    /// Returns the number of replayed testcases.
+    pub fn replay_corpus<CS>(&mut self, scheduler: &CS) -> Result<usize, Error>
+    where
+        CS: CorpusScheduler<I, Self>,
+    {
+        let mut replayed = 0;
+        let mut current = self.corpus().first();
+        while let Some(idx) = current {
+            scheduler.on_add(self, idx)?;
+            replayed += 1;
+            current = self.corpus().next(idx);
+        }
+        Ok(replayed)
+    }
+
     /// Generate `num` initial inputs, using the passed-in generator.
     pub fn generate_initial_inputs<G, E, EM, Z>(
         &mut self,
@@ -556,3 +635,84 @@ where
         unimplemented!()
     }
 }
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        bolts::{rands::StdRand, tuples::tuple_list},
+        corpus::{Corpus, InMemoryCorpus, QueueCorpusScheduler},
+        events::SimpleEventManager,
+        executors::{ExitKind, InProcessExecutor},
+        feedbacks::CrashFeedback,
+        inputs::{BytesInput, HasTargetBytes},
+        state::{HasCorpus, StdState},
+        stats::SimpleStats,
+        StdFuzzer,
+    };
+
+    #[test]
+    fn test_load_report() {
+        let dir =
+            std::env::temp_dir().join(format!("libafl_test_load_report_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("good"), b"good").unwrap();
+        fs::write(dir.join("nested").join("boring"), b"boring").unwrap();
+        // Empty files are ignored altogether
+        fs::write(dir.join("empty"), b"").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(dir.join("missing"), dir.join("dangling")).unwrap();
+
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            (),
+        );
+        let mut manager = SimpleEventManager::new(SimpleStats::new(|_| ()));
+        // The harness reports a crash for the interesting inputs
+        let mut fuzzer = StdFuzzer::new(QueueCorpusScheduler::new(), CrashFeedback::new(), ());
+        let mut harness = |input: &BytesInput| {
+            if input.target_bytes().as_slice() == b"good" {
+                ExitKind::Crash
+            } else {
+                ExitKind::Ok
+            }
+        };
+        let mut executor = InProcessExecutor::new(
+            &mut harness,
+            tuple_list!(),
+            &mut fuzzer,
+            &mut state,
+            &mut manager,
+        )
+        .unwrap();
+
+        // A missing directory does not abort the loading of the others
+        let report = state
+            .load_initial_inputs(
+                &mut fuzzer,
+                &mut executor,
+                &mut manager,
+                &[dir.join("does_not_exist"), dir.clone()],
+            )
+            .unwrap();
+        assert_eq!(report.added, 1);
+        assert_eq!(report.skipped, vec![dir.join("nested").join("boring")]);
+        let mut failed: Vec<_> = report.failed.iter().map(|(path, _)| path.clone()).collect();
+        failed.sort();
+        #[cfg(unix)]
+        assert_eq!(
+            failed,
+            vec![dir.join("dangling"), dir.join("does_not_exist")]
+        );
+        #[cfg(not(unix))]
+        assert_eq!(failed, vec![dir.join("does_not_exist")]);
+        assert_eq!(report.total(), 2 + failed.len());
+        assert_eq!(state.corpus().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,133 @@
+//! Checks the code generated by `#[derive(Input, Mutate)]` from `libafl_derive`
+#![cfg(feature = "derive")]
+
+use serde::{Deserialize, Serialize};
+
+use libafl::{
+    bolts::rands::StdRand,
+    corpus::InMemoryCorpus,
+    inputs::{BytesInput, Input},
+    mutators::{Mutate, MutationResult, Mutator, StructuredMutator},
+    state::StdState,
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, libafl::Mutate)]
+enum Method {
+    Get,
+    Post,
+    Put,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, libafl::Input, libafl::Mutate)]
+enum Payload {
+    Empty,
+    Text(String),
+    Binary { bytes: Vec<u8>, compressed: bool },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, libafl::Input, libafl::Mutate)]
+struct Request {
+    method: Method,
+    port: u16,
+    path: String,
+    keep_alive: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, libafl::Mutate)]
+enum Single {
+    Only,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, libafl::Mutate)]
+struct Marker;
+
+fn new_state(
+) -> StdState<InMemoryCorpus<BytesInput>, (), BytesInput, StdRand, InMemoryCorpus<BytesInput>> {
+    StdState::new(
+        StdRand::with_seed(1337),
+        InMemoryCorpus::new(),
+        InMemoryCorpus::new(),
+        (),
+    )
+}
+
+#[test]
+fn test_derive_struct() {
+    let mut state = new_state();
+    let mut mutator = StructuredMutator::new();
+    let input = Request {
+        method: Method::Get,
+        port: 80,
+        path: "/".into(),
+        keep_alive: false,
+    };
+    assert_eq!(input.generate_name(3), "Request-3");
+
+    // Each call mutates a single, random, field
+    let (mut method, mut port, mut path, mut keep_alive) = (false, false, false, false);
+    for i in 0..256 {
+        let mut mutant = input.clone();
+        if mutator.mutate(&mut state, &mut mutant, i).unwrap() == MutationResult::Skipped {
+            assert_eq!(mutant, input);
+            continue;
+        }
+        let changed = [
+            mutant.method != input.method,
+            mutant.port != input.port,
+            mutant.path != input.path,
+            mutant.keep_alive != input.keep_alive,
+        ];
+        assert!(changed.iter().filter(|c| **c).count() <= 1);
+        method |= changed[0];
+        port |= changed[1];
+        path |= changed[2];
+        keep_alive |= changed[3];
+    }
+    assert!(method && port && path && keep_alive);
+}
+
+#[test]
+fn test_derive_enum() {
+    let mut state = new_state();
+    let mut mutator = StructuredMutator::new();
+
+    let (mut empty, mut text, mut binary, mut in_place) = (false, false, false, false);
+    let mut input = Payload::Empty;
+    for i in 0..256 {
+        let before = input.clone();
+        let res = mutator.mutate(&mut state, &mut input, i).unwrap();
+        match (&before, &input) {
+            // Unit variants have nothing to mutate, they always switch
+            (Payload::Empty, _) => {
+                assert_eq!(res, MutationResult::Mutated);
+                assert_ne!(input, Payload::Empty);
+            }
+            (Payload::Text(_), Payload::Text(_))
+            | (Payload::Binary { .. }, Payload::Binary { .. }) => {
+                in_place |= res == MutationResult::Mutated;
+            }
+            _ => (),
+        }
+        match input {
+            Payload::Empty => empty = true,
+            Payload::Text(_) => text = true,
+            Payload::Binary { .. } => binary = true,
+        }
+    }
+    assert!(empty && text && binary && in_place);
+
+    // An enum made only of unit variants switches at every call
+    let mut method = Method::Get;
+    for _ in 0..64 {
+        let before = method.clone();
+        assert_eq!(method.mutate(&mut state).unwrap(), MutationResult::Mutated);
+        assert_ne!(method, before);
+    }
+
+    // Nothing to mutate
+    assert_eq!(
+        Single::Only.mutate(&mut state).unwrap(),
+        MutationResult::Skipped
+    );
+    assert_eq!(Marker.mutate(&mut state).unwrap(), MutationResult::Skipped);
+}
@@ -0,0 +1,129 @@
+//! Extraction of tokens from the strings of a linked binary, written as an AFL dictionary.
+//! The strings the target compares its input against are usually stored in its read-only data.
+
+use std::{collections::HashSet, fs, path::Path, string::String, vec::Vec};
+
+use crate::Error;
+
+/// The default minimum length of the extracted tokens
+pub const DEFAULT_AUTOTOKENS_MIN_LEN: usize = 3;
+/// The default maximum length of the extracted tokens
+pub const DEFAULT_AUTOTOKENS_MAX_LEN: usize = 32;
+
+/// Reads a little endian integer of `n` bytes at `off`, if in bounds
+fn read_le(bytes: &[u8], off: usize, n: usize) -> Option<u64> {
+    let slice = bytes.get(off..off + n)?;
+    Some(
+        slice
+            .iter()
+            .rev()
+            .fold(0, |val, b| (val << 8) | u64::from(*b)),
+    )
+}
+
+/// Returns the content of the `.rodata` section, if `binary` is a little endian ELF64 file
+fn elf64_rodata(binary: &[u8]) -> Option<&[u8]> {
+    // Magic, 64 bit class, little endian
+    if binary.len() < 0x40 || binary[..4] != *b"\x7fELF" || binary[4] != 2 || binary[5] != 1 {
+        return None;
+    }
+    let shoff = read_le(binary, 0x28, 8)? as usize;
+    let shentsize = read_le(binary, 0x3a, 2)? as usize;
+    let shnum = read_le(binary, 0x3c, 2)? as usize;
+    let shstrndx = read_le(binary, 0x3e, 2)? as usize;
+
+    let section = |idx: usize| -> Option<(usize, &[u8])> {
+        let hdr = shoff + idx * shentsize;
+        let name = read_le(binary, hdr, 4)? as usize;
+        let offset = read_le(binary, hdr + 0x18, 8)? as usize;
+        let size = read_le(binary, hdr + 0x20, 8)? as usize;
+        Some((name, binary.get(offset..offset.checked_add(size)?)?))
+    };
+    let (_, names) = section(shstrndx)?;
+    (0..shnum).find_map(|idx| {
+        let (name, content) = section(idx)?;
+        let name = names.get(name..)?;
+        let end = name.iter().position(|b| *b == 0)?;
+        if &name[..end] == b".rodata" {
+            Some(content)
+        } else {
+            None
+        }
+    })
+}
+
+/// Extracts the NUL-terminated printable strings of `min_len` to `max_len` bytes from a binary,
+/// deduplicated, in order of appearance.
+/// Only the `.rodata` section is scanned for ELF64 files, the whole file for other formats.
+#[must_use]
+pub fn extract_tokens(binary: &[u8], min_len: usize, max_len: usize) -> Vec<Vec<u8>> {
+    let data = elf64_rodata(binary).unwrap_or(binary);
+    let mut tokens: Vec<Vec<u8>> = vec![];
+    let mut seen = HashSet::new();
+    let mut start = 0;
+    for (i, b) in data.iter().enumerate() {
+        if (0x20..0x7f).contains(b) || *b == b'\t' {
+            continue;
+        }
+        let len = i - start;
+        if *b == 0 && len >= min_len && len <= max_len {
+            let token = &data[start..i];
+            if seen.insert(token) {
+                tokens.push(token.to_vec());
+            }
+        }
+        start = i + 1;
+    }
+    tokens
+}
+
+/// Formats tokens as an AFL dictionary, escaping quotes, backslashes and non-printable bytes
+#[must_use]
+pub fn format_tokens(tokens: &[Vec<u8>]) -> String {
+    let mut dict = String::new();
+    for token in tokens {
+        dict.push('"');
+        for b in token {
+            match b {
+                b'"' | b'\\' => {
+                    dict.push('\\');
+                    dict.push(*b as char);
+                }
+                0x20..=0x7e => dict.push(*b as char),
+                _ => dict.push_str(&format!("\\x{:02x}", b)),
+            }
+        }
+        dict.push_str("\"\n");
+    }
+    dict
+}
+
+/// Extracts the tokens of the binary at `binary_path`, writing them as an AFL dictionary to `dict_path`.
+/// Returns the number of tokens written.
+pub fn write_tokens_file<P, Q>(binary_path: P, dict_path: Q) -> Result<usize, Error>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let binary = fs::read(binary_path).map_err(Error::Io)?;
+    let tokens = extract_tokens(
+        &binary,
+        DEFAULT_AUTOTOKENS_MIN_LEN,
+        DEFAULT_AUTOTOKENS_MAX_LEN,
+    );
+    fs::write(dict_path, format_tokens(&tokens)).map_err(Error::Io)?;
+    Ok(tokens.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::autotokens::{extract_tokens, format_tokens};
+
+    #[test]
+    fn test_extract_tokens() {
+        let binary = b"\x01\x02MAGIC\0ab\0\x7fEL\0say \"hi\\\0MAGIC\0toolongtoken\0";
+        let tokens = extract_tokens(binary, 3, 8);
+        assert_eq!(tokens, vec![b"MAGIC".to_vec(), b"say \"hi\\".to_vec()]);
+        assert_eq!(format_tokens(&tokens), "\"MAGIC\"\n\"say \\\"hi\\\\\"\n");
+    }
+}
@@ -2,6 +2,8 @@
 
 use std::{process::Command, string::String, vec::Vec};
 
+pub mod autotokens;
+
 /// `LibAFL` CC Error Type
 #[derive(Debug)]
 pub enum Error {
@@ -48,6 +50,11 @@ pub trait CompilerWrapper {
     /// Get if in linking mode
     fn is_linking(&self) -> bool;
 
+    /// Called after the compiler successfully linked the output
+    fn post_link(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
     /// Run the compiler
     fn run(&mut self) -> Result<(), Error> {
         let args = self.command()?;
@@ -62,6 +69,9 @@ pub trait CompilerWrapper {
             Err(e) => return Err(Error::Io(e)),
         };
         dbg!(status);
+        if status.success() && self.is_linking() {
+            self.post_link()?;
+        }
         Ok(())
     }
 }
@@ -78,6 +88,8 @@ pub struct ClangWrapper {
     linking: bool,
     x_set: bool,
     bit_mode: u32,
+    autotokens: bool,
+    output: Option<String>,
 
     base_args: Vec<String>,
     cc_args: Vec<String>,
@@ -113,7 +125,13 @@ impl CompilerWrapper for ClangWrapper {
             linking = false;
         }
 
-        for arg in &args[1..] {
+        let mut output = None;
+        for (i, arg) in args.iter().enumerate().skip(1) {
+            if arg == "-o" {
+                output = args.get(i + 1).cloned();
+            } else if let Some(out) = arg.strip_prefix("-o") {
+                output = Some(out.into());
+            }
             match arg.as_str() {
                 "-x" => self.x_set = true,
                 "-m32" => self.bit_mode = 32,
@@ -126,6 +144,7 @@ impl CompilerWrapper for ClangWrapper {
             new_args.push(arg.clone());
         }
         self.linking = linking;
+        self.output = output;
 
         if self.optimize {
             new_args.push("-g".into());
@@ -180,6 +199,15 @@ impl CompilerWrapper for ClangWrapper {
     fn is_linking(&self) -> bool {
         self.linking
     }
+
+    fn post_link(&mut self) -> Result<(), Error> {
+        if self.autotokens {
+            // The default output of the linker
+            let output = self.output.clone().unwrap_or_else(|| "a.out".into());
+            autotokens::write_tokens_file(&output, format!("{}.dict", output))?;
+        }
+        Ok(())
+    }
 }
 
 impl ClangWrapper {
@@ -195,6 +223,8 @@ impl ClangWrapper {
             linking: false,
             x_set: false,
             bit_mode: 0,
+            autotokens: false,
+            output: None,
             base_args: vec![],
             cc_args: vec![],
             link_args: vec![],
@@ -207,6 +237,13 @@ impl ClangWrapper {
         self
     }
 
+    /// Extract tokens from the strings of the linked binary,
+    /// writing them as an AFL dictionary next to it, with the `.dict` extension
+    pub fn autotokens(&mut self) -> &'_ mut Self {
+        self.autotokens = true;
+        self
+    }
+
     /// set cpp mode
     pub fn is_cpp(&mut self) -> &'_ mut Self {
         self.is_cpp = true;
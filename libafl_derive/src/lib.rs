@@ -1,7 +1,8 @@
 extern crate proc_macro;
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, DeriveInput};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Index};
 
 #[proc_macro_derive(SerdeAny)]
 pub fn libafl_serdeany_derive(input: TokenStream) -> TokenStream {
@@ -10,3 +11,165 @@ pub fn libafl_serdeany_derive(input: TokenStream) -> TokenStream {
         libafl::impl_serdeany!(#name);
     })
 }
+
+/// Derives `libafl::inputs::Input` for a struct or enum.
+/// The type must also implement `Clone`, `Debug`, `Serialize` and `Deserialize`.
+#[proc_macro_derive(Input)]
+pub fn libafl_input_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    TokenStream::from(quote! {
+        impl #impl_generics libafl::inputs::Input for #name #ty_generics #where_clause {
+            fn generate_name(&self, idx: usize) -> String {
+                format!("{}-{}", stringify!(#name), idx)
+            }
+        }
+    })
+}
+
+/// Derives `libafl::mutators::Mutate` for a struct or enum whose fields all implement `Mutate`.
+/// Structs mutate one random field per call, enums either switch to another random variant
+/// (with `Default` fields) or mutate one field of the current variant.
+/// Unit variants always switch, unless the enum has a single variant.
+#[proc_macro_derive(Mutate)]
+pub fn libafl_mutate_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let fields: Vec<TokenStream2> = match &data.fields {
+                Fields::Named(fields) => fields
+                    .named
+                    .iter()
+                    .map(|f| {
+                        let ident = f.ident.as_ref().unwrap();
+                        quote! { &mut self.#ident }
+                    })
+                    .collect(),
+                Fields::Unnamed(fields) => (0..fields.unnamed.len())
+                    .map(|i| {
+                        let idx = Index::from(i);
+                        quote! { &mut self.#idx }
+                    })
+                    .collect(),
+                Fields::Unit => vec![],
+            };
+            mutate_one_of(&fields)
+        }
+        Data::Enum(data) if data.variants.is_empty() => quote! { match *self {} },
+        Data::Enum(data) => {
+            let switches = data.variants.iter().enumerate().map(|(i, v)| {
+                let ident = &v.ident;
+                let ctor = match &v.fields {
+                    Fields::Named(fields) => {
+                        let names = fields.named.iter().map(|f| f.ident.as_ref().unwrap());
+                        quote! { Self::#ident { #(#names: Default::default()),* } }
+                    }
+                    Fields::Unnamed(fields) => {
+                        let defaults = fields.unnamed.iter().map(|_| quote! { Default::default() });
+                        quote! { Self::#ident(#(#defaults),*) }
+                    }
+                    Fields::Unit => quote! { Self::#ident },
+                };
+                let i = i as u64;
+                quote! { #i => #ctor, }
+            });
+            let kinds = data.variants.iter().enumerate().map(|(i, v)| {
+                let ident = &v.ident;
+                let i = i as u64;
+                let is_unit = matches!(v.fields, Fields::Unit);
+                quote! { Self::#ident { .. } => (#i, #is_unit), }
+            });
+            let arms = data.variants.iter().map(|v| {
+                let ident = &v.ident;
+                match &v.fields {
+                    Fields::Named(fields) => {
+                        let names: Vec<_> = fields
+                            .named
+                            .iter()
+                            .map(|f| f.ident.clone().unwrap())
+                            .collect();
+                        let refs: Vec<_> = names.iter().map(|n| quote! { #n }).collect();
+                        let inner = mutate_one_of(&refs);
+                        quote! { Self::#ident { #(#names),* } => { #inner } }
+                    }
+                    Fields::Unnamed(fields) => {
+                        let names: Vec<_> = (0..fields.unnamed.len())
+                            .map(|i| format_ident!("field_{}", i))
+                            .collect();
+                        let refs: Vec<_> = names.iter().map(|n| quote! { #n }).collect();
+                        let inner = mutate_one_of(&refs);
+                        quote! { Self::#ident(#(#names),*) => { #inner } }
+                    }
+                    Fields::Unit => quote! {
+                        Self::#ident => Ok(libafl::mutators::MutationResult::Skipped),
+                    },
+                }
+            });
+            let variants = data.variants.len() as u64;
+            // Unit variants have nothing to mutate in place, so they always switch
+            quote! {
+                let (current, is_unit): (u64, bool) = match self {
+                    #(#kinds)*
+                };
+                if #variants > 1 && (is_unit || state.rand_mut().below(8) == 0) {
+                    let mut next = state.rand_mut().below(#variants - 1);
+                    if next >= current {
+                        next += 1;
+                    }
+                    *self = match next {
+                        #(#switches)*
+                        _ => unreachable!(),
+                    };
+                    return Ok(libafl::mutators::MutationResult::Mutated);
+                }
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(_) => {
+            return syn::Error::new_spanned(&input.ident, "Mutate cannot be derived for unions")
+                .to_compile_error()
+                .into();
+        }
+    };
+
+    TokenStream::from(quote! {
+        impl #impl_generics libafl::mutators::Mutate for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn mutate<R, S>(
+                &mut self,
+                state: &mut S,
+            ) -> Result<libafl::mutators::MutationResult, libafl::Error>
+            where
+                S: libafl::state::HasRand<R> + libafl::state::HasMetadata + libafl::state::HasMaxSize,
+                R: libafl::bolts::rands::Rand,
+            {
+                use libafl::bolts::rands::Rand as _;
+                #body
+            }
+        }
+    })
+}
+
+/// Mutates one of the given fields (expressions evaluating to `&mut` references), picked at random
+fn mutate_one_of(fields: &[TokenStream2]) -> TokenStream2 {
+    if fields.is_empty() {
+        return quote! { Ok(libafl::mutators::MutationResult::Skipped) };
+    }
+    let count = fields.len() as u64;
+    let arms = fields.iter().enumerate().map(|(i, field)| {
+        let i = i as u64;
+        quote! { #i => libafl::mutators::Mutate::mutate(#field, state), }
+    });
+    quote! {
+        match state.rand_mut().below(#count) {
+            #(#arms)*
+            _ => unreachable!(),
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use ahash::AHasher;
 use backtrace::Backtrace;
 use capstone::{arch::BuildsCapstone, Capstone};
 use color_backtrace::{default_output_stream, BacktracePrinter, Verbosity};
@@ -6,18 +7,18 @@ use frida_gum::interceptor::Interceptor;
 use frida_gum::ModuleDetails;
 
 use libafl::{
-    bolts::{ownedref::OwnedPtr, tuples::Named},
+    bolts::{os::hash_module_offset, ownedref::OwnedPtr, tuples::Named},
     corpus::Testcase,
     events::EventFirer,
     executors::{ExitKind, HasExecHooks},
-    feedbacks::Feedback,
+    feedbacks::{Feedback, ObjectiveMetadata},
     inputs::{HasTargetBytes, Input},
     observers::{Observer, ObserversTuple},
     state::HasMetadata,
     Error, SerdeAny,
 };
 use serde::{Deserialize, Serialize};
-use std::io::Write;
+use std::{hash::Hasher, io::Write};
 use termcolor::{Color, ColorSpec, WriteColor};
 
 use crate::{alloc::AllocationMetadata, FridaOptions};
@@ -64,8 +65,61 @@ impl AsanError {
             AsanError::BadFuncArgWrite(_) => "function arg resulting in bad write",
         }
     }
+
+    /// The pc the error occurred at, if known
+    fn pc(&self) -> Option<usize> {
+        match self {
+            AsanError::OobRead(error)
+            | AsanError::OobWrite(error)
+            | AsanError::ReadAfterFree(error)
+            | AsanError::WriteAfterFree(error) => Some(error.pc),
+            AsanError::Unknown((_, pc, _, _))
+            | AsanError::StackOobRead((_, pc, _, _))
+            | AsanError::StackOobWrite((_, pc, _, _))
+            | AsanError::BadFuncArgRead((_, pc, _, _, _))
+            | AsanError::BadFuncArgWrite((_, pc, _, _, _)) => Some(*pc),
+            AsanError::DoubleFree(_) | AsanError::UnallocatedFree(_) | AsanError::Leak(_) => None,
+        }
+    }
+
+    /// The backtrace of the error, or of the allocation of the leaked memory
+    fn backtrace(&self) -> Option<&Backtrace> {
+        match self {
+            AsanError::OobRead(error)
+            | AsanError::OobWrite(error)
+            | AsanError::ReadAfterFree(error)
+            | AsanError::WriteAfterFree(error) => Some(&error.backtrace),
+            AsanError::DoubleFree((_, _, backtrace))
+            | AsanError::UnallocatedFree((_, backtrace))
+            | AsanError::Unknown((_, _, _, backtrace))
+            | AsanError::StackOobRead((_, _, _, backtrace))
+            | AsanError::StackOobWrite((_, _, _, backtrace))
+            | AsanError::BadFuncArgRead((_, _, _, _, backtrace))
+            | AsanError::BadFuncArgWrite((_, _, _, _, backtrace)) => Some(backtrace),
+            AsanError::Leak((_, metadata)) => metadata.allocation_site_backtrace.as_ref(),
+        }
+    }
+
+    /// The signature of the root cause of the error, hashing its type, its pc and the top of its backtrace.
+    /// Addresses are hashed as offsets in their module, so signatures match across runs despite ASLR.
+    fn signature(&self) -> u64 {
+        let mut hasher = AHasher::new_with_keys(0, 0);
+        hasher.write(self.description().as_bytes());
+        if let Some(pc) = self.pc() {
+            hash_module_offset(&mut hasher, pc);
+        }
+        if let Some(backtrace) = self.backtrace() {
+            for frame in backtrace.frames().iter().take(ASAN_SIGNATURE_FRAMES) {
+                hash_module_offset(&mut hasher, frame.ip() as usize);
+            }
+        }
+        hasher.finish()
+    }
 }
 
+/// The number of backtrace frames hashed in the signature of an [`AsanError`]
+const ASAN_SIGNATURE_FRAMES: usize = 8;
+
 /// A struct holding errors that occurred during frida address sanitizer runs
 #[allow(clippy::unsafe_derive_deserialize)]
 #[derive(Debug, Clone, Serialize, Deserialize, SerdeAny)]
@@ -509,7 +563,9 @@ impl AsanErrorsObserver {
     }
 }
 
-/// A feedback reporting potential [`AsanErrors`] from an `AsanErrorsObserver`
+/// A feedback reporting potential [`AsanErrors`] from an `AsanErrorsObserver`.
+/// Its testcases are tagged with an [`struct@ObjectiveMetadata`] naming the type and the signature of the first error,
+/// so that an [`libafl::corpus::ObjectiveCorpus`] buckets and deduplicates them.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct AsanErrorsFeedback {
     errors: Option<AsanErrors>,
@@ -549,6 +605,11 @@ where
 
     fn append_metadata(&mut self, _state: &mut S, testcase: &mut Testcase<I>) -> Result<(), Error> {
         if let Some(errors) = &self.errors {
+            // The first error is the root cause, the following ones may be consequences of it
+            if let Some(error) = errors.errors.first() {
+                let kind = format!("asan-{}", error.description().replace(' ', "-"));
+                testcase.add_metadata(ObjectiveMetadata::new(&kind, Some(error.signature())));
+            }
             testcase.add_metadata(errors.clone());
         }
 
@@ -7,6 +7,8 @@ extern "C" {
     pub fn libafl_targets_cmplog_wrapper(k: u64, shape: u8, arg1: u64, arg2: u64);
 }
 
+/// The frida `CmpLog` runtime, logging the operands of comparisons to the `libafl_targets` `CMPLOG_MAP`.
+/// Observe it with a `CmpLogObserver`, e.g. to run the `RedQueenStage`.
 pub struct CmpLogRuntime {
     ops_save_register_and_blr_to_populate: Option<Box<[u8]>>,
 }
@@ -20,6 +20,16 @@ typedef struct CmpLogOperands {
     uint64_t v1;
 } CmpLogOperands;
 
+#define CMPLOG_RTN_LEN 32
+
+typedef struct CmpLogRoutine {
+    uint8_t v0[CMPLOG_RTN_LEN];
+    uint8_t v1[CMPLOG_RTN_LEN];
+} CmpLogRoutine;
+
+// Routine operands are stored in the space of the instruction operands of the same entry
+#define CMPLOG_MAP_RTN_H ((CMPLOG_MAP_H * sizeof(CmpLogOperands)) / sizeof(CmpLogRoutine))
+
 typedef struct CmpLogMap {
   CmpLogHeader headers[CMPLOG_MAP_W];
   CmpLogOperands operands[CMPLOG_MAP_W][CMPLOG_MAP_H];
@@ -52,4 +62,32 @@ static void __libafl_targets_cmplog(uintptr_t k, uint8_t shape, uint64_t arg1, u
   
 }
 
+static void __libafl_targets_cmplog_routines(uintptr_t k, const uint8_t *ptr1, const uint8_t *ptr2, uint8_t len) {
+
+  if (!libafl_cmplog_enabled) return;
+
+  if (len > CMPLOG_RTN_LEN) len = CMPLOG_RTN_LEN;
+
+  uint16_t hits;
+  if (libafl_cmplog_map.headers[k].kind != CMPLOG_KIND_RTN) {
+    libafl_cmplog_map.headers[k].kind = CMPLOG_KIND_RTN;
+    libafl_cmplog_map.headers[k].hits = 1;
+    libafl_cmplog_map.headers[k].shape = len;
+    hits = 0;
+  } else {
+    hits = libafl_cmplog_map.headers[k].hits++;
+    if (libafl_cmplog_map.headers[k].shape < len) {
+      libafl_cmplog_map.headers[k].shape = len;
+    }
+  }
+
+  hits &= CMPLOG_MAP_RTN_H - 1;
+  CmpLogRoutine *routine = &((CmpLogRoutine *)libafl_cmplog_map.operands[k])[hits];
+  for (uint8_t i = 0; i < CMPLOG_RTN_LEN; i++) {
+    routine->v0[i] = i < len ? ptr1[i] : 0;
+    routine->v1[i] = i < len ? ptr2[i] : 0;
+  }
+
+}
+
 #endif
@@ -19,6 +19,12 @@ pub const CMPLOG_MAP_H: usize = 32;
 /// The `CmpLog` map size
 pub const CMPLOG_MAP_SIZE: usize = CMPLOG_MAP_W * CMPLOG_MAP_H;
 
+/// The maximum number of bytes logged for each operand of a routine, such as `memcmp`
+pub const CMPLOG_RTN_LEN: usize = 32;
+/// The `CmpLogMap` H value for routines, whose operands are stored in place of the instruction operands
+pub const CMPLOG_MAP_RTN_H: usize =
+    (CMPLOG_MAP_H * core::mem::size_of::<CmpLogOperands>()) / (2 * CMPLOG_RTN_LEN);
+
 big_array! { BigArray; }
 
 /// `CmpLog` instruction kind
@@ -73,10 +79,15 @@ impl CmpMap for CmpLogMap {
     }
 
     fn usable_executions_for(&self, idx: usize) -> usize {
-        if self.executions_for(idx) < CMPLOG_MAP_H {
-            self.executions_for(idx)
+        let max = if self.headers[idx].kind == CMPLOG_KIND_RTN {
+            CMPLOG_MAP_RTN_H
         } else {
             CMPLOG_MAP_H
+        };
+        if self.executions_for(idx) < max {
+            self.executions_for(idx)
+        } else {
+            max
         }
     }
 
@@ -110,7 +121,9 @@ impl CmpMap for CmpLogMap {
                 _ => {}
             };
         }
-        // TODO bytes
+        if self.headers[idx].kind == CMPLOG_KIND_RTN {
+            return CmpValues::Bytes(self.routine_operands(idx, execution));
+        }
         CmpValues::Bytes((vec![], vec![]))
     }
 
@@ -125,6 +138,25 @@ impl CmpMap for CmpLogMap {
     }
 }
 
+impl CmpLogMap {
+    /// The operands of a routine, laid out as in the C `CmpLogRoutine` struct:
+    /// the bytes of the first operand, followed by the bytes of the second one.
+    fn routine_operands(&self, idx: usize, execution: usize) -> (Vec<u8>, Vec<u8>) {
+        let per_routine = (2 * CMPLOG_RTN_LEN) / core::mem::size_of::<CmpLogOperands>();
+        let start = execution * per_routine;
+        let mut bytes = Vec::with_capacity(2 * CMPLOG_RTN_LEN);
+        for operands in &self.operands[idx][start..start + per_routine] {
+            bytes.extend_from_slice(&operands.0.to_ne_bytes());
+            bytes.extend_from_slice(&operands.1.to_ne_bytes());
+        }
+        let len = core::cmp::min(self.headers[idx].shape as usize, CMPLOG_RTN_LEN);
+        (
+            bytes[..len].to_vec(),
+            bytes[CMPLOG_RTN_LEN..CMPLOG_RTN_LEN + len].to_vec(),
+        )
+    }
+}
+
 /// The global `CmpLog` map for the current `LibAFL` run.
 #[no_mangle]
 pub static mut libafl_cmplog_map: CmpLogMap = CmpLogMap {
@@ -76,6 +76,61 @@ void __sanitizer_cov_trace_cmp8(uint64_t arg1, uint64_t arg2) {
 void libafl_targets_cmplog_wrapper(uintptr_t k, uint8_t shape, uint64_t arg1, uint64_t arg2){
     return __libafl_targets_cmplog(k, shape, arg1, arg2);
 }
+
+#include <stddef.h>
+
+static uint8_t __libafl_targets_strnlen(const char *s, size_t n) {
+  size_t i = 0;
+  while (i < n && i < CMPLOG_RTN_LEN && s[i]) i++;
+  return (uint8_t)i;
+}
+
+static void __libafl_targets_cmplog_strings(void *called_pc, const char *s1, const char *s2, size_t n) {
+  uintptr_t k = (uintptr_t)called_pc;
+  k = (k >> 4) ^ (k << 8);
+  k &= CMPLOG_MAP_W - 1;
+  // Log each string with its terminator, so that the replacement ends where the operand ends.
+  // The shorter one is zero-filled, it must not be read past its terminator.
+  uint8_t len1 = __libafl_targets_strnlen(s1, n);
+  uint8_t len2 = __libafl_targets_strnlen(s2, n);
+  if (len1 < n && len1 < CMPLOG_RTN_LEN) len1++;
+  if (len2 < n && len2 < CMPLOG_RTN_LEN) len2++;
+  uint8_t v0[CMPLOG_RTN_LEN] = {0};
+  uint8_t v1[CMPLOG_RTN_LEN] = {0};
+  for (uint8_t i = 0; i < len1; i++) v0[i] = (uint8_t)s1[i];
+  for (uint8_t i = 0; i < len2; i++) v1[i] = (uint8_t)s2[i];
+  __libafl_targets_cmplog_routines(k, v0, v1, MAX(len1, len2));
+}
+
+// The sanitizers call these hooks on the intercepted functions, logging their operands as routines
+
+void __sanitizer_weak_hook_memcmp(void *called_pc, const void *s1, const void *s2, size_t n, int result) {
+  (void)result;
+  uintptr_t k = (uintptr_t)called_pc;
+  k = (k >> 4) ^ (k << 8);
+  k &= CMPLOG_MAP_W - 1;
+  __libafl_targets_cmplog_routines(k, (const uint8_t *)s1, (const uint8_t *)s2, n > CMPLOG_RTN_LEN ? CMPLOG_RTN_LEN : n);
+}
+
+void __sanitizer_weak_hook_strncmp(void *called_pc, const char *s1, const char *s2, size_t n, int result) {
+  (void)result;
+  __libafl_targets_cmplog_strings(called_pc, s1, s2, n);
+}
+
+void __sanitizer_weak_hook_strncasecmp(void *called_pc, const char *s1, const char *s2, size_t n, int result) {
+  (void)result;
+  __libafl_targets_cmplog_strings(called_pc, s1, s2, n);
+}
+
+void __sanitizer_weak_hook_strcmp(void *called_pc, const char *s1, const char *s2, int result) {
+  (void)result;
+  __libafl_targets_cmplog_strings(called_pc, s1, s2, CMPLOG_RTN_LEN);
+}
+
+void __sanitizer_weak_hook_strcasecmp(void *called_pc, const char *s1, const char *s2, int result) {
+  (void)result;
+  __libafl_targets_cmplog_strings(called_pc, s1, s2, CMPLOG_RTN_LEN);
+}
 #endif
 
 void __sanitizer_cov_trace_switch(uint64_t val, uint64_t *cases) {